@@ -0,0 +1,703 @@
+//! Runs the whole analysis pipeline on a P4 program and collects the results
+
+use crate::diagnostics::Diagnostic;
+use crate::dot::graph_to_dot;
+use crate::external_solver::ExternalSolver;
+use crate::gcl::{
+    BugKind, GclExpr, GclExprData, GclFact, GclGraph, GclLValue, GclNode, MemoryLocation,
+    TableChoice,
+};
+use crate::ir::{IrBaseType, IrType};
+use crate::optimizations::merge_simple_edges;
+use crate::parser::parse;
+use crate::solver::{CheckResult, ModelValue, SolverBackend, SolverError, SolverKind, SolverModel};
+use crate::span::{LineIndex, Span};
+use crate::to_gcl::ToGcl;
+use crate::to_predicates::{apply_commands, PredicateMap, VariableMap};
+use crate::type_checker::run_type_checking;
+use crate::unroll::{unroll_loops, DEFAULT_UNROLL_BOUND};
+#[cfg(feature = "z3")]
+use crate::z3_solver::Z3Solver;
+use num_bigint::BigInt;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::{EdgeRef, IntoNodeReferences};
+use petgraph::Direction;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::thread;
+use std::time::{Duration, Instant};
+#[cfg(feature = "z3")]
+use z3::Config;
+
+/// Settings which control the analysis
+#[derive(Clone, Debug)]
+pub struct AnalysisOptions {
+    /// Only check the reachability of bug nodes, instead of every node
+    pub only_bugs: bool,
+    /// How many times loops are unrolled
+    pub unroll_bound: usize,
+    /// The solver which checks if nodes are reachable
+    pub solver: SolverKind,
+    /// How long the solver can spend on each node before giving up, in which
+    /// case the node's reachability is unknown
+    pub timeout: Option<Duration>,
+    /// How many threads check reachability at once. Each thread has its own
+    /// solver.
+    pub jobs: usize,
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        Self {
+            only_bugs: true,
+            unroll_bound: DEFAULT_UNROLL_BOUND,
+            solver: SolverKind::default(),
+            timeout: None,
+            jobs: 1,
+        }
+    }
+}
+
+/// The program could not be analyzed because it is invalid
+#[derive(Debug)]
+pub enum AnalysisError {
+    /// The program has syntax errors
+    Parse(Vec<Diagnostic>),
+    /// The program has type errors
+    TypeCheck(Vec<Diagnostic>),
+    /// The program could not be read (ex. the file does not exist)
+    Read(Diagnostic),
+    /// The solver could not be run (ex. it is not installed)
+    Solver(Diagnostic),
+}
+
+impl AnalysisError {
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        match self {
+            AnalysisError::Parse(diagnostics) | AnalysisError::TypeCheck(diagnostics) => {
+                diagnostics
+            }
+            AnalysisError::Read(diagnostic) | AnalysisError::Solver(diagnostic) => {
+                std::slice::from_ref(diagnostic)
+            }
+        }
+    }
+}
+
+/// The results of analyzing one of several programs
+pub struct AnalyzedFile {
+    /// The file the program was read from, or `<stdin>`
+    pub name: String,
+    pub source: String,
+    pub result: Result<AnalysisReport, AnalysisError>,
+}
+
+/// The results of analyzing a program
+pub struct AnalysisReport {
+    /// The reachable bugs, in the order they were found in the graph
+    pub bugs: Vec<Bug>,
+    /// True if a loop can run more times than the unroll bound allows, so
+    /// some bugs may not have been found
+    pub unroll_bound_exceeded: bool,
+    /// The control flow graph of the program
+    pub graph: GclGraph,
+    /// The result of each reachability check (only bug nodes are checked
+    /// unless `only_bugs` is false)
+    pub reachability: HashMap<NodeIndex, Reachability>,
+    pub timings: Timings,
+}
+
+/// The result of checking if a node is reachable
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Reachability {
+    Reachable,
+    Unreachable,
+    /// The solver gave up (ex. it timed out), so the node could not be
+    /// proven unreachable
+    Unknown,
+}
+
+/// A bug in the program which is reachable, or could not be proven
+/// unreachable
+#[derive(Clone, Debug)]
+pub struct Bug {
+    pub kind: BugKind,
+    /// The variable involved in the bug, if any
+    pub variable: Option<String>,
+    /// A description of the bug
+    pub message: String,
+    /// The code which causes the bug
+    pub span: Span,
+    /// The 1-based line and column of the start of the span
+    pub line: usize,
+    pub column: usize,
+    /// `Reachable`, or `Unknown` if the bug could not be proven unreachable.
+    /// Unknown bugs have no path or counterexample.
+    pub reachability: Reachability,
+    /// The path through the graph which the program takes to the bug with the
+    /// counterexample's inputs. It is missing if the run does not reach the
+    /// bug, since the solver only checks an over-approximation of the program.
+    pub path: Option<Vec<PathStep>>,
+    /// The inputs to the program which the solver found for the bug. They
+    /// only cause the bug if it is confirmed.
+    pub counterexample: Counterexample,
+}
+
+impl Bug {
+    /// Check if running the program with the counterexample's inputs reaches
+    /// the bug. If not, the bug is only possible: the solver's model satisfies
+    /// an over-approximation of the program, but may not be a real run.
+    pub fn is_confirmed(&self) -> bool {
+        self.path.is_some()
+    }
+}
+
+/// Inputs to the program which drive it to a bug, decoded from a Z3 model
+#[derive(Clone, Debug, Default)]
+pub struct Counterexample {
+    /// The values of variables (split into fields for structs and headers)
+    /// and facts, sorted by name
+    pub values: Vec<ModelEntry>,
+    /// The action chosen by the control plane for each table application,
+    /// in the order of the `apply` calls
+    pub table_actions: Vec<TableAction>,
+}
+
+/// The value of a variable or fact in a model
+#[derive(Clone, Debug)]
+pub struct ModelEntry {
+    /// The variable name from the source code, or a description of the fact
+    pub name: String,
+    /// The value, written like a P4 literal when possible
+    pub value: String,
+}
+
+/// The action run when a table is applied
+#[derive(Clone, Debug)]
+pub struct TableAction {
+    pub table: String,
+    pub action: String,
+    /// The table's declaration
+    pub span: Span,
+    /// The `apply` call which ran the action
+    pub call_span: Span,
+    /// The loop iteration of the `apply` call, counting from 0
+    pub iteration: usize,
+}
+
+/// A node on the path to a bug
+#[derive(Clone, Debug)]
+pub struct PathStep {
+    pub node: NodeIndex,
+    pub name: String,
+    /// The code which the node was created from, if any
+    pub span: Option<Span>,
+}
+
+/// How long each stage of the analysis took
+#[derive(Clone, Debug, Default)]
+pub struct Timings {
+    pub parse: Duration,
+    pub type_check: Duration,
+    pub to_gcl: Duration,
+    pub unroll: Duration,
+    pub optimize_gcl: Duration,
+    pub reachability_predicates: Duration,
+    pub calculate_reachability: Duration,
+    pub total: Duration,
+}
+
+/// Analyze the P4 program, returning the reachable bugs
+pub fn analyze(source: &str, options: &AnalysisOptions) -> Result<AnalysisReport, AnalysisError> {
+    let mut timings = Timings::default();
+
+    // Parse P4
+    let parse_start = Instant::now();
+    let p4_program = parse(source).map_err(AnalysisError::Parse)?;
+    timings.parse = parse_start.elapsed();
+
+    // Type check P4
+    let type_checking_start = Instant::now();
+    let (p4_program_ir, metadata) = run_type_checking(&p4_program).map_err(|errors| {
+        AnalysisError::TypeCheck(errors.into_iter().map(Diagnostic::from).collect())
+    })?;
+    timings.type_check = type_checking_start.elapsed();
+    log::trace!("After type checking: {:#?}", p4_program_ir);
+
+    // Convert to GCL
+    let gcl_start = Instant::now();
+    let mut graph = GclGraph::new();
+    let gcl_start_node = p4_program_ir.to_gcl(&mut graph, &metadata);
+    timings.to_gcl = gcl_start.elapsed();
+
+    // Unroll loops so the graph is acyclic
+    let unroll_start = Instant::now();
+    unroll_loops(&mut graph, gcl_start_node, options.unroll_bound);
+    timings.unroll = unroll_start.elapsed();
+
+    // Optimize GCL
+    let gcl_optimize_start = Instant::now();
+    merge_simple_edges(&mut graph);
+    timings.optimize_gcl = gcl_optimize_start.elapsed();
+
+    // Calculate a reachability predicate for each node
+    let reachability_start = Instant::now();
+    let (node_predicates, node_variables) = graph.to_reachability_predicates();
+    timings.reachability_predicates = reachability_start.elapsed();
+    display_node_vars(&graph, &node_variables);
+    display_reachability(&graph, &node_predicates);
+
+    // Check which nodes are reachable, and find the bugs
+    let reachable_start = Instant::now();
+    let model_decoder = ModelDecoder {
+        graph: &graph,
+        node_predicates: &node_predicates,
+    };
+    let (reachability, bugs) = check_reachability(
+        &model_decoder,
+        &metadata.types_in_order,
+        gcl_start_node,
+        source,
+        options,
+    )
+    .map_err(|error| AnalysisError::Solver(Diagnostic::error(error.0, None)))?;
+    timings.calculate_reachability = reachable_start.elapsed();
+
+    let unroll_bound_exceeded = graph.node_references().any(|(node_idx, node)| {
+        node.is_unroll_bound_exceeded()
+            && reachability.get(&node_idx) == Some(&Reachability::Reachable)
+    });
+    timings.total = parse_start.elapsed();
+
+    Ok(AnalysisReport {
+        bugs,
+        unroll_bound_exceeded,
+        graph,
+        reachability,
+        timings,
+    })
+}
+
+impl AnalysisReport {
+    /// Create a graphviz representation of the graph, colored by reachability.
+    /// The source code is used to label nodes with their line numbers, and
+    /// nodes can be clustered by the block they came from.
+    pub fn graphviz(&self, source: &str, cluster_blocks: bool) -> String {
+        graph_to_dot(&self.graph, &self.reachability, source, cluster_blocks)
+    }
+}
+
+fn display_reachability(graph: &GclGraph, node_preds: &PredicateMap) {
+    log::debug!("Reachability Predicates:");
+    for (node_idx, pred) in node_preds {
+        let node_name = &graph.node_weight(*node_idx).unwrap().name;
+
+        log::debug!("Node '{}': {}", node_name, pred);
+    }
+}
+
+fn display_node_vars(graph: &GclGraph, node_vars: &VariableMap) {
+    log::trace!("Node Variables:");
+    let mut node_vars: Vec<_> = node_vars
+        .iter()
+        .map(|(node_idx, values)| (graph.node_weight(*node_idx).unwrap().name.as_str(), values))
+        .collect();
+    node_vars.sort_by_key(|(name, _)| *name);
+
+    for (node_name, vars) in node_vars {
+        log::trace!("Node '{}':", node_name);
+        for (var, values) in vars {
+            log::trace!(
+                "    {} = [{}]",
+                var,
+                values
+                    .iter()
+                    .map(|v| format!("{}", v))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+}
+
+/// Check if the node's reachability is checked by the analysis. Only the bug
+/// nodes (and the nodes which show that loops need to be unrolled more) are
+/// checked, unless `only_bugs` is false.
+pub(crate) fn is_checked(node: &GclNode, options: &AnalysisOptions) -> bool {
+    !options.only_bugs || node.is_bug() || node.is_unroll_bound_exceeded()
+}
+
+/// Check the reachability of the nodes, and collect the bugs which are not
+/// unreachable, along with a path to each reachable one. The nodes are split
+/// between `options.jobs` threads, each with its own solver: thread `i` checks
+/// every `jobs`-th node starting from the `i`-th, so the results don't depend
+/// on how the threads are scheduled. The bugs are in graph order.
+fn check_reachability(
+    model_decoder: &ModelDecoder,
+    types: &[(String, IrType)],
+    start_idx: NodeIndex,
+    source: &str,
+    options: &AnalysisOptions,
+) -> Result<(HashMap<NodeIndex, Reachability>, Vec<Bug>), SolverError> {
+    let graph = model_decoder.graph;
+    let line_index = LineIndex::new(source);
+    let checked_nodes: Vec<_> = graph
+        .node_references()
+        .filter(|(_, node)| is_checked(node, options))
+        .map(|(node_idx, _)| node_idx)
+        .collect();
+
+    // Each thread checks its nodes in order, and decodes each model before the
+    // next check
+    let check_nodes = |thread: usize, threads: usize| {
+        with_solver(types, options, |solver| {
+            checked_nodes
+                .iter()
+                .skip(thread)
+                .step_by(threads)
+                .map(|&node_idx| {
+                    check_node(solver, model_decoder, node_idx, start_idx, &line_index)
+                        .map(|result| (node_idx, result))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+    };
+    let threads = options.jobs.clamp(1, checked_nodes.len().max(1));
+    let results = if threads == 1 {
+        vec![check_nodes(0, 1)]
+    } else {
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads)
+                .map(|thread| scope.spawn(move || check_nodes(thread, threads)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        })
+    };
+
+    let mut results: HashMap<_, _> = results
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .flatten()
+        .collect();
+    let mut reachability = HashMap::new();
+    let mut bugs = Vec::new();
+    for node_idx in checked_nodes {
+        let (node_reachability, bug) = results.remove(&node_idx).unwrap();
+        reachability.insert(node_idx, node_reachability);
+        bugs.extend(bug);
+    }
+
+    Ok((reachability, bugs))
+}
+
+/// Start the solver picked by the options, and run `f` with it
+fn with_solver<T: Send + Sync>(
+    types: &[(String, IrType)],
+    options: &AnalysisOptions,
+    f: impl FnOnce(&mut dyn SolverBackend) -> Result<T, SolverError> + Send + Sync,
+) -> Result<T, SolverError> {
+    match &options.solver {
+        // Z3 contexts can't be shared between threads, so each solver has its
+        // own, which the Z3 calls in `f` use instead of the thread's default
+        #[cfg(feature = "z3")]
+        SolverKind::Z3 => z3::with_z3_config(&Config::new(), || {
+            let mut solver = Z3Solver::new(types, options.timeout);
+            f(&mut solver)
+        }),
+        SolverKind::External(command) => {
+            let mut solver = ExternalSolver::start(command, types, options.timeout)?;
+            f(&mut solver)
+        }
+    }
+}
+
+/// Check the reachability of a node. If it is a bug which is not unreachable,
+/// the bug is returned too, with a path to it if it is reachable.
+fn check_node(
+    solver: &mut dyn SolverBackend,
+    model_decoder: &ModelDecoder,
+    node_idx: NodeIndex,
+    start_idx: NodeIndex,
+    line_index: &LineIndex,
+) -> Result<(Reachability, Option<Bug>), SolverError> {
+    let graph = model_decoder.graph;
+    let node = graph.node_weight(node_idx).unwrap();
+    let (node_reachability, model) =
+        match solver.check(&model_decoder.node_predicates[&node_idx])? {
+            CheckResult::Sat(model) => (Reachability::Reachable, Some(model)),
+            CheckResult::Unsat => (Reachability::Unreachable, None),
+            CheckResult::Unknown => (Reachability::Unknown, None),
+        };
+
+    let bug = match node.bug() {
+        Some(bug) if node_reachability != Reachability::Unreachable => bug,
+        _ => return Ok((node_reachability, None)),
+    };
+    let path = model
+        .as_ref()
+        .and_then(|model| model_decoder.witness_path(start_idx, node_idx, model.as_ref()))
+        .map(|path| {
+            path.into_iter()
+                .map(|node_idx| {
+                    let node = graph.node_weight(node_idx).unwrap();
+                    PathStep {
+                        node: node_idx,
+                        name: node.name.clone(),
+                        span: node.span,
+                    }
+                })
+                .collect()
+        });
+    let (line, column) = line_index.line_col(bug.span.start);
+
+    let bug = Bug {
+        kind: bug.kind,
+        variable: bug.variable.clone(),
+        message: bug.to_string(),
+        span: bug.span,
+        line,
+        column,
+        reachability: node_reachability,
+        path,
+        counterexample: model
+            .map(|model| model_decoder.decode(node_idx, model.as_ref()))
+            .unwrap_or_default(),
+    };
+
+    Ok((node_reachability, Some(bug)))
+}
+
+/// Decodes models into counterexamples which use names from the P4 program
+struct ModelDecoder<'a> {
+    graph: &'a GclGraph,
+    node_predicates: &'a PredicateMap,
+}
+
+impl ModelDecoder<'_> {
+    /// Get the inputs in the node's reachability predicate which make it
+    /// reachable: the values of variables and facts, and the action chosen
+    /// by each table
+    fn decode(&self, node_idx: NodeIndex, model: &dyn SolverModel) -> Counterexample {
+        let predicate = &self.node_predicates[&node_idx];
+        let mut variables = Vec::new();
+        predicate.visit_variables(&mut |variable| {
+            if !variables.contains(&variable) {
+                variables.push(variable);
+            }
+        });
+
+        let mut counterexample = Counterexample::default();
+        let mut decoded = HashSet::new();
+        for variable in variables {
+            if let GclExprData::Var(loc) = &variable.data {
+                if let Some(choice) = self.graph.get_table_choice(loc) {
+                    counterexample
+                        .table_actions
+                        .extend(decode_table_choice(choice, variable, model));
+                    continue;
+                }
+            }
+
+            if let Some(name) = self.describe_variable(variable) {
+                self.decode_value(name, variable, model, &mut decoded, &mut counterexample);
+            }
+        }
+
+        counterexample.values.sort_by(|a, b| a.name.cmp(&b.name));
+        counterexample
+            .table_actions
+            .sort_by_key(|table_action| (table_action.call_span.start, table_action.iteration));
+        counterexample
+    }
+
+    /// Find the path which the program takes to the node with the model's
+    /// inputs, by running the graph forward from the start node. Each node's
+    /// commands are applied to the variables, and the first outgoing edge
+    /// whose guard is true under the model is taken. The reachability
+    /// predicates over-approximate the program (values from converging
+    /// branches are merged), so a model may not drive the program to the
+    /// node, in which case there is no path.
+    fn witness_path(
+        &self,
+        start_idx: NodeIndex,
+        node_idx: NodeIndex,
+        model: &dyn SolverModel,
+    ) -> Option<Vec<NodeIndex>> {
+        let mut variables = HashMap::new();
+        let mut facts = HashMap::new();
+        let mut path = vec![start_idx];
+        let mut current_idx = start_idx;
+
+        while current_idx != node_idx {
+            let node = self.graph.node_weight(current_idx).unwrap();
+            apply_commands(&node.commands, &mut variables, &mut facts);
+
+            // The run only follows one path, so every value is a single
+            // expression over the inputs
+            current_idx = self
+                .graph
+                .edges_directed(current_idx, Direction::Outgoing)
+                .find(|edge| {
+                    edge.weight()
+                        .fill_in(&variables, &facts)
+                        .iter()
+                        .all(|guard| self.is_true(guard, model))
+                })?
+                .target();
+            path.push(current_idx);
+        }
+
+        Some(path)
+    }
+
+    fn is_true(&self, predicate: &GclExpr, model: &dyn SolverModel) -> bool {
+        model.eval(predicate) == Some(ModelValue::Bool(true))
+    }
+
+    /// Add the variable's value to the counterexample. Structs and headers
+    /// are split into an entry for each field. A field may also be an input
+    /// by itself (ex. `s.h.f` when `s` is one), so fields which were already
+    /// decoded are skipped.
+    fn decode_value(
+        &self,
+        name: String,
+        variable: &GclExpr,
+        model: &dyn SolverModel,
+        decoded: &mut HashSet<GclExpr>,
+        counterexample: &mut Counterexample,
+    ) {
+        let fields = match &variable.ty {
+            IrType::Base(IrBaseType::Struct { fields })
+            | IrType::Base(IrBaseType::Header { fields }) => fields,
+            _ => {
+                if !decoded.insert(variable.clone()) {
+                    return;
+                }
+                if let Some(value) = model.eval(variable) {
+                    counterexample.values.push(ModelEntry {
+                        name,
+                        value: format_value(&value, &variable.ty),
+                    });
+                }
+                return;
+            }
+        };
+
+        for (field_ty, field_name) in fields {
+            let field = GclExpr {
+                ty: IrType::Base(field_ty.clone()),
+                data: GclExprData::FieldAccess(Box::new(variable.clone()), field_name.clone()),
+            };
+
+            self.decode_value(
+                format!("{}.{}", name, field_name),
+                &field,
+                model,
+                decoded,
+                counterexample,
+            );
+        }
+    }
+
+    /// Describe a variable or fact using names from the source code.
+    /// Temporary variables (which have no name) are skipped.
+    fn describe_variable(&self, variable: &GclExpr) -> Option<String> {
+        match &variable.data {
+            GclExprData::Var(loc) => self.location_name(loc),
+            GclExprData::FieldAccess(target, field) => {
+                Some(format!("{}.{}", self.describe_variable(target)?, field))
+            }
+            GclExprData::Fact(GclFact::HasValue(loc)) => {
+                Some(format!("{} is initialized", self.location_name(loc)?))
+            }
+            GclExprData::Fact(GclFact::IsValid(header)) => {
+                Some(format!("{}.isValid()", self.lvalue_name(header)?))
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the source name of the variable which owns the location. If other
+    /// variables have the same name (ex. parameters of different actions),
+    /// the variable's ID is added to tell them apart.
+    fn location_name(&self, loc: &MemoryLocation) -> Option<String> {
+        let name = match loc {
+            MemoryLocation::Var(_, Some(name)) => name,
+            MemoryLocation::Var(_, None)
+            | MemoryLocation::ReturnVal
+            | MemoryLocation::EdgeSelector(_)
+            | MemoryLocation::MergedValue(..) => return None,
+        };
+        let var = match self.graph.location_var(loc) {
+            Some(var) => var,
+            // Locations such as packet data don't belong to a variable
+            None => return Some(name.clone()),
+        };
+
+        let is_ambiguous = self
+            .graph
+            .variables()
+            .any(|(other_var, _)| other_var != var && other_var.1 == var.1);
+        if is_ambiguous {
+            Some(format!("{}#{}", var.1, var.0))
+        } else {
+            Some(var.1.clone())
+        }
+    }
+
+    fn lvalue_name(&self, lvalue: &GclLValue) -> Option<String> {
+        match lvalue {
+            GclLValue::Var(loc) => self.location_name(loc),
+            GclLValue::Field(target, _, field) => {
+                Some(format!("{}.{}", self.lvalue_name(target)?, field))
+            }
+        }
+    }
+}
+
+/// Get the action which the control plane chose for a table application
+fn decode_table_choice(
+    choice: &TableChoice,
+    variable: &GclExpr,
+    model: &dyn SolverModel,
+) -> Option<TableAction> {
+    let action_idx = match model.eval(variable)? {
+        ModelValue::Int(action_idx) => usize::try_from(action_idx).ok()?,
+        _ => return None,
+    };
+    let action = choice.actions.get(action_idx)?;
+
+    Some(TableAction {
+        table: choice.table.clone(),
+        action: action.clone(),
+        span: choice.span,
+        call_span: choice.call_span,
+        iteration: choice.iteration,
+    })
+}
+
+/// Format a value from a model like a P4 literal of the given type
+fn format_value(value: &ModelValue, ty: &IrType) -> String {
+    match (value, ty) {
+        (ModelValue::Bool(value), _) => value.to_string(),
+        (ModelValue::Int(value), _) => value.to_string(),
+        (ModelValue::BitVector(value), IrType::Base(IrBaseType::Bit { width })) => {
+            format!("{}w{}", width, value)
+        }
+        (ModelValue::BitVector(value), IrType::Base(IrBaseType::SignedInt { width })) => {
+            // Undo the two's complement representation
+            let value = if value.bit(*width as u64 - 1) {
+                value - (BigInt::from(1) << width)
+            } else {
+                value.clone()
+            };
+            format!("{}s{}", width, value)
+        }
+        (ModelValue::BitVector(value), _) => value.to_string(),
+        (ModelValue::String(value), _) => format!("{:?}", value),
+    }
+}
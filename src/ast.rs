@@ -1,10 +1,29 @@
 // Note: types are sorted alphabetically
 
+use crate::pretty::{write_block, CommaSeparated, Indented};
+use crate::span::Span;
+use num_bigint::BigInt;
+use std::fmt;
+use std::fmt::{Display, Formatter, Write};
+
 #[derive(Debug)]
 pub struct ActionDecl {
     pub name: String,
     pub params: Vec<Param>,
     pub body: BlockStatement,
+    pub span: Span,
+}
+
+impl Display for ActionDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "action {}({}) {}",
+            self.name,
+            CommaSeparated(&self.params),
+            self.body
+        )
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -14,27 +33,141 @@ pub enum Argument {
     DontCare,
 }
 
+impl Display for Argument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Argument::Value(value) => Display::fmt(value, f),
+            Argument::Named(name, value) => write!(f, "{} = {}", name, value),
+            Argument::DontCare => f.write_str("_"),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Assignment {
     pub lvalue: LValue,
     pub value: Expr,
+    pub span: Span,
+}
+
+impl Display for Assignment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {};", self.lvalue, self.value)
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum BaseType {
     Bool,
     String,
+    /// Arbitrary-precision integer
+    Int,
+    /// Unsigned fixed-width integer (bit<N>)
+    Bit(usize),
+    /// Signed fixed-width integer (int<N>)
+    SignedInt(usize),
     // TODO: Add more base types
 }
 
+impl Display for BaseType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BaseType::Bool => f.write_str("bool"),
+            BaseType::String => f.write_str("string"),
+            BaseType::Int => f.write_str("int"),
+            BaseType::Bit(width) => write!(f, "bit<{}>", width),
+            BaseType::SignedInt(width) => write!(f, "int<{}>", width),
+        }
+    }
+}
+
+/// Binary operators other than the short-circuiting `&&` and `||`
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    SaturatingAdd,
+    SaturatingSub,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+    Equals,
+    NotEquals,
+    LessThan,
+    LessThanEquals,
+    GreaterThan,
+    GreaterThanEquals,
+}
+
+impl BinOp {
+    /// True if the operator compares its operands (and results in a bool)
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            BinOp::Equals
+                | BinOp::NotEquals
+                | BinOp::LessThan
+                | BinOp::LessThanEquals
+                | BinOp::GreaterThan
+                | BinOp::GreaterThanEquals
+        )
+    }
+}
+
+impl Display for BinOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Mod => "%",
+            BinOp::SaturatingAdd => "|+|",
+            BinOp::SaturatingSub => "|-|",
+            BinOp::BitAnd => "&",
+            BinOp::BitOr => "|",
+            BinOp::BitXor => "^",
+            BinOp::ShiftLeft => "<<",
+            BinOp::ShiftRight => ">>",
+            BinOp::Equals => "==",
+            BinOp::NotEquals => "!=",
+            BinOp::LessThan => "<",
+            BinOp::LessThanEquals => "<=",
+            BinOp::GreaterThan => ">",
+            BinOp::GreaterThanEquals => ">=",
+        })
+    }
+}
+
 #[derive(Clone, Debug)]
-pub struct BlockStatement(pub Vec<StatementOrDecl>);
+pub struct BlockStatement {
+    pub statements: Vec<StatementOrDecl>,
+    pub span: Span,
+}
+
+impl Display for BlockStatement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_block(f, &self.statements)
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct ConstantDecl {
     pub ty: TypeRef,
     pub name: String,
     pub value: Expr,
+    pub span: Span,
+}
+
+impl Display for ConstantDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "const {} {} = {};", self.ty, self.name, self.value)
+    }
 }
 
 #[derive(Debug)]
@@ -43,6 +176,25 @@ pub struct ControlDecl {
     pub params: Vec<Param>,
     pub local_decls: Vec<ControlLocalDecl>,
     pub apply_body: BlockStatement,
+    pub span: Span,
+}
+
+impl Display for ControlDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "control {}({}) {{",
+            self.name,
+            CommaSeparated(&self.params)
+        )?;
+
+        for local_decl in &self.local_decls {
+            writeln!(Indented::new(&mut *f), "{}", local_decl)?;
+        }
+
+        writeln!(Indented::new(&mut *f), "apply {}", self.apply_body)?;
+        f.write_str("}")
+    }
 }
 
 #[derive(Debug)]
@@ -54,29 +206,130 @@ pub enum ControlLocalDecl {
     Table(TableDecl),
 }
 
+impl Display for ControlLocalDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlLocalDecl::Variable(var_decl) => Display::fmt(var_decl, f),
+            ControlLocalDecl::Instantiation(instantiation) => Display::fmt(instantiation, f),
+            ControlLocalDecl::Constant(constant) => Display::fmt(constant, f),
+            ControlLocalDecl::Action(action) => Display::fmt(action, f),
+            ControlLocalDecl::Table(table) => Display::fmt(table, f),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Declaration {
     Struct(StructDecl),
+    Header(HeaderDecl),
     Control(ControlDecl),
+    Parser(ParserDecl),
     Constant(ConstantDecl),
     Instantiation(Instantiation),
 }
 
+impl Display for Declaration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Declaration::Struct(struct_decl) => Display::fmt(struct_decl, f),
+            Declaration::Header(header_decl) => Display::fmt(header_decl, f),
+            Declaration::Control(control_decl) => Display::fmt(control_decl, f),
+            Declaration::Parser(parser_decl) => Display::fmt(parser_decl, f),
+            Declaration::Constant(constant) => Display::fmt(constant, f),
+            Declaration::Instantiation(instantiation) => Display::fmt(instantiation, f),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum Direction {
     In,
     Out,
     InOut,
+    /// No direction was given (ex. for `packet_in` parameters)
+    Directionless,
+}
+
+impl Display for Direction {
+    /// Display the direction's keyword (nothing for directionless)
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Direction::In => "in",
+            Direction::Out => "out",
+            Direction::InOut => "inout",
+            Direction::Directionless => "",
+        })
+    }
 }
 
 #[derive(Clone, Debug)]
-pub enum Expr {
+pub struct Expr {
+    pub data: ExprData,
+    pub span: Span,
+}
+
+impl Display for Expr {
+    /// Display the expression as P4 code. Operands which are operations are
+    /// put in parentheses, so the precedence of the operators doesn't matter.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.data {
+            ExprData::Bool(value) => Display::fmt(value, f),
+            ExprData::Int(literal) => Display::fmt(literal, f),
+            ExprData::Var(name) => f.write_str(name),
+            ExprData::And(left, right) => {
+                write!(f, "{} && {}", Operand(left), Operand(right))
+            }
+            ExprData::Or(left, right) => {
+                write!(f, "{} || {}", Operand(left), Operand(right))
+            }
+            ExprData::Negation(inner) => write!(f, "!{}", Operand(inner)),
+            ExprData::BinOp(op, left, right) => {
+                write!(f, "{} {} {}", Operand(left), op, Operand(right))
+            }
+            ExprData::UnOp(op, inner) => write!(f, "{}{}", op, Operand(inner)),
+            ExprData::FunctionCall(call) => Display::fmt(call, f),
+            ExprData::MethodCall(call) => Display::fmt(call, f),
+            ExprData::FieldAccess(target, field) => write!(f, "{}.{}", Operand(target), field),
+            ExprData::Struct(fields) => {
+                let fields: Vec<_> = fields
+                    .iter()
+                    .map(|(name, value)| format!("{} = {}", name, value))
+                    .collect();
+                write!(f, "{{ {} }}", CommaSeparated(&fields))
+            }
+        }
+    }
+}
+
+/// An operand of an operator, which is put in parentheses if it is an
+/// operation itself
+struct Operand<'a>(&'a Expr);
+
+impl Display for Operand<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.0.data {
+            ExprData::And(..)
+            | ExprData::Or(..)
+            | ExprData::Negation(_)
+            | ExprData::BinOp(..)
+            | ExprData::UnOp(..) => write!(f, "({})", self.0),
+            _ => Display::fmt(self.0, f),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ExprData {
     Bool(bool),
+    Int(IntLiteral),
     Var(String),
     And(Box<Expr>, Box<Expr>),
     Or(Box<Expr>, Box<Expr>),
     Negation(Box<Expr>),
+    BinOp(BinOp, Box<Expr>, Box<Expr>),
+    UnOp(UnOp, Box<Expr>),
     FunctionCall(FunctionCall),
+    MethodCall(MethodCall),
     FieldAccess(Box<Expr>, String),
     Struct(Vec<(String, Expr)>),
 }
@@ -85,6 +338,36 @@ pub enum Expr {
 pub struct FunctionCall {
     pub target: String,
     pub arguments: Vec<Argument>,
+    pub span: Span,
+}
+
+impl Display for FunctionCall {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.target, CommaSeparated(&self.arguments))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HeaderDecl {
+    pub name: String,
+    pub fields: Vec<(TypeRef, String)>,
+    pub span: Span,
+}
+
+impl Display for HeaderDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "header {} ", self.name)?;
+        write_fields(f, &self.fields)
+    }
+}
+
+/// Write the fields of a struct or header type as a block
+fn write_fields(f: &mut Formatter<'_>, fields: &[(TypeRef, String)]) -> fmt::Result {
+    let fields: Vec<_> = fields
+        .iter()
+        .map(|(ty, name)| format!("{} {};", ty, name))
+        .collect();
+    write_block(f, &fields)
 }
 
 #[derive(Clone, Debug)]
@@ -92,6 +375,19 @@ pub struct IfStatement {
     pub condition: Expr,
     pub then_case: BlockStatement,
     pub else_case: Option<BlockStatement>,
+    pub span: Span,
+}
+
+impl Display for IfStatement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "if ({}) {}", self.condition, self.then_case)?;
+
+        if let Some(else_case) = &self.else_case {
+            write!(f, " else {}", else_case)?;
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -99,25 +395,211 @@ pub struct Instantiation {
     pub ty: TypeRef,
     pub args: Vec<Argument>,
     pub name: String,
+    pub span: Span,
+}
+
+impl Display for Instantiation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}({}) {};",
+            self.ty,
+            CommaSeparated(&self.args),
+            self.name
+        )
+    }
+}
+
+/// An integer literal, such as `10`, `0x0800` or `8w255`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IntLiteral {
+    pub value: BigInt,
+    /// The width of the literal, if it was given (ex. the 8 in `8w255`)
+    pub width: Option<usize>,
+    /// True if the literal is a signed fixed-width integer (ex. `8s-1`)
+    pub signed: bool,
+}
+
+impl Display for IntLiteral {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.width {
+            Some(width) if self.signed => write!(f, "{}s{}", width, self.value),
+            Some(width) => write!(f, "{}w{}", width, self.value),
+            None => Display::fmt(&self.value, f),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct KeyElement {
     pub expr: Expr,
     pub match_kind: String,
+    pub span: Span,
+}
+
+impl Display for KeyElement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {};", self.expr, self.match_kind)
+    }
+}
+
+/// A set of values matched against a select key
+#[derive(Debug)]
+pub enum Keyset {
+    Expr(Expr),
+    /// Matches any value (`default` or `_`)
+    Default,
+}
+
+impl Display for Keyset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Keyset::Expr(expr) => Display::fmt(expr, f),
+            Keyset::Default => f.write_str("default"),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
-pub enum LValue {
+pub struct LValue {
+    pub data: LValueData,
+    pub span: Span,
+}
+
+impl Display for LValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.data {
+            LValueData::Var(name) => f.write_str(name),
+            LValueData::Field(target, field) => write!(f, "{}.{}", target, field),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum LValueData {
     Var(String),
     Field(Box<LValue>, String),
 }
 
+impl From<LValue> for Expr {
+    fn from(lvalue: LValue) -> Self {
+        let data = match lvalue.data {
+            LValueData::Var(name) => ExprData::Var(name),
+            LValueData::Field(target, field) => {
+                ExprData::FieldAccess(Box::new((*target).into()), field)
+            }
+        };
+
+        Expr {
+            data,
+            span: lvalue.span,
+        }
+    }
+}
+
+/// A call to a method of a value, such as `hdr.ethernet.isValid()`
+#[derive(Clone, Debug)]
+pub struct MethodCall {
+    pub target: Box<Expr>,
+    pub method: String,
+    pub arguments: Vec<Argument>,
+    pub span: Span,
+}
+
+impl Display for MethodCall {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}({})",
+            Operand(&self.target),
+            self.method,
+            CommaSeparated(&self.arguments)
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct Param {
     pub direction: Direction,
     pub ty: TypeRef,
     pub name: String,
+    pub span: Span,
+}
+
+impl Display for Param {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.direction {
+            Direction::Directionless => write!(f, "{} {}", self.ty, self.name),
+            direction => write!(f, "{} {} {}", direction, self.ty, self.name),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParserDecl {
+    pub name: String,
+    pub params: Vec<Param>,
+    pub local_decls: Vec<ParserLocalDecl>,
+    pub states: Vec<ParserState>,
+    pub span: Span,
+}
+
+impl Display for ParserDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "parser {}({}) {{",
+            self.name,
+            CommaSeparated(&self.params)
+        )?;
+
+        for local_decl in &self.local_decls {
+            writeln!(Indented::new(&mut *f), "{}", local_decl)?;
+        }
+        for state in &self.states {
+            writeln!(Indented::new(&mut *f), "{}", state)?;
+        }
+
+        f.write_str("}")
+    }
+}
+
+#[derive(Debug)]
+pub enum ParserLocalDecl {
+    Variable(VariableDecl),
+    Instantiation(Instantiation),
+    Constant(ConstantDecl),
+}
+
+impl Display for ParserLocalDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParserLocalDecl::Variable(var_decl) => Display::fmt(var_decl, f),
+            ParserLocalDecl::Instantiation(instantiation) => Display::fmt(instantiation, f),
+            ParserLocalDecl::Constant(constant) => Display::fmt(constant, f),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParserState {
+    pub name: String,
+    pub body: BlockStatement,
+    pub transition: Transition,
+    pub span: Span,
+}
+
+impl Display for ParserState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "state {} {{", self.name)?;
+
+        for statement in &self.body.statements {
+            writeln!(Indented::new(&mut *f), "{}", statement)?;
+        }
+
+        writeln!(Indented::new(&mut *f), "transition {}", self.transition)?;
+        f.write_str("}")
+    }
 }
 
 #[derive(Debug)]
@@ -125,12 +607,70 @@ pub struct Program {
     pub declarations: Vec<Declaration>,
 }
 
+impl Display for Program {
+    /// Display the program as P4 code, with a blank line between declarations
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, declaration) in self.declarations.iter().enumerate() {
+            if i != 0 {
+                f.write_str("\n")?;
+            }
+
+            writeln!(f, "{}", declaration)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct SelectCase {
+    pub keysets: Vec<Keyset>,
+    pub state: String,
+    pub span: Span,
+}
+
+impl Display for SelectCase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.keysets.as_slice() {
+            [keyset] => write!(f, "{}: {};", keyset, self.state),
+            keysets => write!(f, "({}): {};", CommaSeparated(keysets), self.state),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SelectExpr {
+    pub keys: Vec<Expr>,
+    pub cases: Vec<SelectCase>,
+    pub span: Span,
+}
+
+impl Display for SelectExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "select({}) ", CommaSeparated(&self.keys))?;
+        write_block(f, &self.cases)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum Statement {
     Block(BlockStatement),
     If(IfStatement),
     Assignment(Assignment),
     FunctionCall(FunctionCall),
+    MethodCall(MethodCall),
+}
+
+impl Display for Statement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::Block(block) => Display::fmt(block, f),
+            Statement::If(if_statement) => Display::fmt(if_statement, f),
+            Statement::Assignment(assignment) => Display::fmt(assignment, f),
+            Statement::FunctionCall(call) => write!(f, "{};", call),
+            Statement::MethodCall(call) => write!(f, "{};", call),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -141,22 +681,86 @@ pub enum StatementOrDecl {
     Instantiation(Instantiation),
 }
 
+impl Display for StatementOrDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StatementOrDecl::Statement(statement) => Display::fmt(statement, f),
+            StatementOrDecl::VariableDecl(var_decl) => Display::fmt(var_decl, f),
+            StatementOrDecl::ConstantDecl(constant) => Display::fmt(constant, f),
+            StatementOrDecl::Instantiation(instantiation) => Display::fmt(instantiation, f),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct StructDecl {
     pub name: String,
     pub fields: Vec<(TypeRef, String)>,
+    pub span: Span,
+}
+
+impl Display for StructDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "struct {} ", self.name)?;
+        write_fields(f, &self.fields)
+    }
 }
 
 #[derive(Debug)]
 pub struct TableDecl {
     pub name: String,
     pub properties: Vec<TableProperty>,
+    pub span: Span,
+}
+
+impl Display for TableDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "table {} ", self.name)?;
+        write_block(f, &self.properties)
+    }
 }
 
 #[derive(Debug)]
 pub enum TableProperty {
     Key(Vec<KeyElement>),
     Actions(Vec<String>),
+    /// The action to run when no entry matches
+    DefaultAction(FunctionCall),
+}
+
+impl Display for TableProperty {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TableProperty::Key(keys) => {
+                f.write_str("key = ")?;
+                write_block(f, keys)
+            }
+            TableProperty::Actions(actions) => {
+                let actions: Vec<_> = actions
+                    .iter()
+                    .map(|action| format!("{};", action))
+                    .collect();
+                f.write_str("actions = ")?;
+                write_block(f, &actions)
+            }
+            TableProperty::DefaultAction(call) => write!(f, "default_action = {};", call),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Transition {
+    Direct(String),
+    Select(SelectExpr),
+}
+
+impl Display for Transition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Transition::Direct(state) => write!(f, "{};", state),
+            Transition::Select(select) => Display::fmt(select, f),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -165,9 +769,49 @@ pub enum TypeRef {
     Identifier(String),
 }
 
+impl Display for TypeRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeRef::Base(ty) => Display::fmt(ty, f),
+            TypeRef::Identifier(name) => f.write_str(name),
+        }
+    }
+}
+
+/// Unary operators other than the logical negation `!`
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum UnOp {
+    /// Bitwise complement (`~`)
+    BitNot,
+    /// Arithmetic negation (`-`)
+    Minus,
+}
+
+impl Display for UnOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            UnOp::BitNot => "~",
+            UnOp::Minus => "-",
+        })
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct VariableDecl {
     pub ty: TypeRef,
     pub name: String,
     pub value: Option<Expr>,
+    pub span: Span,
+}
+
+impl Display for VariableDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.ty, self.name)?;
+
+        if let Some(value) = &self.value {
+            write!(f, " = {}", value)?;
+        }
+
+        f.write_str(";")
+    }
 }
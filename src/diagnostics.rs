@@ -0,0 +1,104 @@
+//! Compiler-style error messages which point at the P4 source code
+
+use crate::span::{LineIndex, Span};
+use std::fmt::Write;
+
+/// An error message, along with where it happened and any extra notes
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Option<Span>,
+    pub notes: Vec<Note>,
+}
+
+/// Extra information attached to a diagnostic, such as a related location
+#[derive(Debug)]
+pub struct Note {
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>, span: Option<Span>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_note(mut self, message: impl Into<String>, span: Option<Span>) -> Self {
+        self.notes.push(Note {
+            message: message.into(),
+            span,
+        });
+        self
+    }
+
+    /// Render the diagnostic with a snippet of the source code, for example:
+    ///
+    /// ```text
+    /// error: unknown variable `x`
+    ///  --> input.p4:12:9
+    ///    |
+    /// 12 |         x = 1;
+    ///    |         ^
+    /// ```
+    pub fn render(&self, file_name: &str, source: &str) -> String {
+        let line_index = LineIndex::new(source);
+        let mut output = String::new();
+
+        writeln!(output, "error: {}", self.message).unwrap();
+        render_snippet(&mut output, file_name, source, &line_index, self.span);
+
+        for note in &self.notes {
+            writeln!(output, "note: {}", note.message).unwrap();
+            render_snippet(&mut output, file_name, source, &line_index, note.span);
+        }
+
+        output
+    }
+}
+
+/// Write the location of the span and underline it in the line it starts on.
+/// Spans which cover multiple lines are underlined to the end of the first.
+fn render_snippet(
+    output: &mut String,
+    file_name: &str,
+    source: &str,
+    line_index: &LineIndex,
+    span: Option<Span>,
+) {
+    let span = match span {
+        Some(span) => span,
+        None => return,
+    };
+
+    let (line, col) = line_index.line_col(span.start);
+    let line_str = source.lines().nth(line - 1).unwrap_or_default();
+    let underline_len = source[span.start..span.end.max(span.start)]
+        .lines()
+        .next()
+        .map_or(0, |first_line| first_line.chars().count())
+        .max(1);
+
+    // Keep tabs so the underline lines up with the code
+    let indent: String = line_str
+        .chars()
+        .take(col - 1)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    let gutter = " ".repeat(line.to_string().len());
+    writeln!(output, "{}--> {}:{}:{}", gutter, file_name, line, col).unwrap();
+    writeln!(output, "{} |", gutter).unwrap();
+    writeln!(output, "{} | {}", line, line_str).unwrap();
+    writeln!(
+        output,
+        "{} | {}{}",
+        gutter,
+        indent,
+        "^".repeat(underline_len)
+    )
+    .unwrap();
+}
@@ -0,0 +1,121 @@
+//! Graphviz (DOT) versions of the control flow graph
+
+use crate::analysis::Reachability;
+use crate::gcl::{GclExprData, GclGraph, GclNode};
+use crate::span::LineIndex;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Create a DOT graph where each node is labeled with its commands and the
+/// line it came from, and colored by its reachability (orange if the solver
+/// could not decide). Edges are labeled with their guards. If `cluster_blocks`
+/// is true, the nodes of each control, action and parser state are grouped
+/// together.
+pub(crate) fn graph_to_dot(
+    graph: &GclGraph,
+    reachability: &HashMap<NodeIndex, Reachability>,
+    source: &str,
+    cluster_blocks: bool,
+) -> String {
+    let line_index = LineIndex::new(source);
+    let mut output = String::new();
+    writeln!(output, "digraph {{").unwrap();
+    writeln!(output, "    node [shape = box];").unwrap();
+
+    // Group the nodes by block, keeping the blocks in order of appearance
+    let mut blocks: Vec<(Option<&str>, Vec<NodeIndex>)> = Vec::new();
+    for node_idx in graph.node_indices() {
+        let block = if cluster_blocks {
+            graph.node_block(node_idx)
+        } else {
+            None
+        };
+
+        match blocks.iter_mut().find(|(name, _)| *name == block) {
+            Some((_, nodes)) => nodes.push(node_idx),
+            None => blocks.push((block, vec![node_idx])),
+        }
+    }
+
+    for (cluster_id, (block, nodes)) in blocks.iter().enumerate() {
+        let indent = match block {
+            Some(name) => {
+                writeln!(output, "    subgraph cluster_{} {{", cluster_id).unwrap();
+                writeln!(output, "        label = \"{}\";", escape(name)).unwrap();
+                "        "
+            }
+            None => "    ",
+        };
+
+        for node_idx in nodes {
+            let node = graph.node_weight(*node_idx).unwrap();
+            let color = match (node.is_bug(), reachability.get(node_idx)) {
+                (true, Some(Reachability::Reachable)) => "red",
+                (false, Some(Reachability::Reachable)) => "green",
+                (_, Some(Reachability::Unknown)) => "orange",
+                (_, Some(Reachability::Unreachable)) => "grey",
+                (_, None) => "black",
+            };
+
+            writeln!(
+                output,
+                "{}{} [label = \"{}\", color = {}];",
+                indent,
+                node_idx.index(),
+                node_label(node, &line_index),
+                color
+            )
+            .unwrap();
+        }
+
+        if block.is_some() {
+            writeln!(output, "    }}").unwrap();
+        }
+    }
+
+    for edge in graph.edge_references() {
+        write!(
+            output,
+            "    {} -> {}",
+            edge.source().index(),
+            edge.target().index()
+        )
+        .unwrap();
+
+        // Unconditional edges are left unlabeled
+        if !matches!(edge.weight().data, GclExprData::Bool(true)) {
+            write!(
+                output,
+                " [label = \"{}\"]",
+                escape(&edge.weight().to_string())
+            )
+            .unwrap();
+        }
+        writeln!(output, ";").unwrap();
+    }
+
+    writeln!(output, "}}").unwrap();
+    output
+}
+
+/// The node's name, line and commands, as left-aligned lines
+fn node_label(node: &GclNode, line_index: &LineIndex) -> String {
+    let mut label = escape(&node.name);
+    if let Some(span) = node.span {
+        write!(label, "\\lline {}", line_index.line_col(span.start).0).unwrap();
+    }
+
+    for cmd in &node.commands {
+        write!(label, "\\l{};", escape(&cmd.to_string())).unwrap();
+    }
+
+    label.push_str("\\l");
+    label
+}
+
+/// Escape text to be used in a DOT string
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,213 @@
+//! Text versions of the intermediate stages of the analysis, for debugging
+
+use crate::analysis::{analyze, is_checked, AnalysisError, AnalysisOptions};
+use crate::diagnostics::Diagnostic;
+use crate::gcl::GclGraph;
+use crate::optimizations::merge_simple_edges;
+use crate::parser::parse;
+use crate::smtlib::Smt2TypeMap;
+use crate::solver::SolverError;
+use crate::span::{LineIndex, Span};
+use crate::to_gcl::ToGcl;
+use crate::type_checker::run_type_checking;
+use crate::unroll::unroll_loops;
+use petgraph::algo::toposort;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use std::fmt::Write;
+use std::ops::Deref;
+use std::str::FromStr;
+
+/// A stage of the analysis which can be emitted
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EmitStage {
+    /// The parsed program
+    Ast,
+    /// The type checked program
+    Ir,
+    /// The control flow graph, after loops are unrolled
+    Gcl,
+    /// The control flow graph after it is optimized
+    GclOptimized,
+    /// The reachability predicate of each node
+    Predicates,
+    /// The reachability checks, as SMT-LIB2 scripts
+    Smt2,
+    /// The control flow graph in Graphviz format, colored by reachability
+    Dot,
+}
+
+impl FromStr for EmitStage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ast" => Ok(EmitStage::Ast),
+            "ir" => Ok(EmitStage::Ir),
+            "gcl" => Ok(EmitStage::Gcl),
+            "gcl-optimized" => Ok(EmitStage::GclOptimized),
+            "predicates" => Ok(EmitStage::Predicates),
+            "smt2" => Ok(EmitStage::Smt2),
+            "dot" => Ok(EmitStage::Dot),
+            _ => Err(format!(
+                "unknown stage `{}`, expected ast, ir, gcl, gcl-optimized, predicates, smt2 or dot",
+                s
+            )),
+        }
+    }
+}
+
+/// Run the analysis up to the stage, and write out the program as it is
+/// after that stage
+pub fn emit(
+    source: &str,
+    stage: EmitStage,
+    options: &AnalysisOptions,
+) -> Result<String, AnalysisError> {
+    if stage == EmitStage::Dot {
+        return analyze(source, options).map(|report| report.graphviz(source, false));
+    }
+    if stage == EmitStage::Smt2 {
+        // The scripts are separated by a reset so they can be run as one
+        let scripts: Vec<_> = smt2_queries(source, options)?
+            .into_iter()
+            .map(|query| query.script)
+            .collect();
+        return Ok(scripts.join("(reset)\n\n"));
+    }
+
+    let program = parse(source).map_err(AnalysisError::Parse)?;
+    if stage == EmitStage::Ast {
+        return Ok(program.to_string());
+    }
+
+    let (program_ir, metadata) = run_type_checking(&program).map_err(|errors| {
+        AnalysisError::TypeCheck(errors.into_iter().map(Diagnostic::from).collect())
+    })?;
+    if stage == EmitStage::Ir {
+        return Ok(program_ir.to_string());
+    }
+
+    let line_index = LineIndex::new(source);
+    let mut graph = GclGraph::new();
+    let start_idx = program_ir.to_gcl(&mut graph, &metadata);
+    unroll_loops(&mut graph, start_idx, options.unroll_bound);
+    if stage == EmitStage::Gcl {
+        return Ok(format_graph(&graph, &line_index));
+    }
+
+    merge_simple_edges(&mut graph);
+    if stage == EmitStage::GclOptimized {
+        return Ok(format_graph(&graph, &line_index));
+    }
+
+    let (node_predicates, _) = graph.to_reachability_predicates();
+    let mut output = String::new();
+    for node_idx in sorted_nodes(&graph) {
+        let node_name = &graph.node_weight(node_idx).unwrap().name;
+        writeln!(
+            output,
+            "Node '{}': {}",
+            node_name, node_predicates[&node_idx]
+        )
+        .unwrap();
+    }
+
+    Ok(output)
+}
+
+/// The reachability check of a node, as an SMT-LIB2 script
+pub struct Smt2Query {
+    pub node_name: String,
+    /// The code which the node was created from, if any
+    pub span: Option<Span>,
+    /// A self-contained script which declares the types and variables used by
+    /// the node's reachability predicate, asserts it, and asks for a model.
+    /// The node is reachable if the result is `sat`.
+    pub script: String,
+}
+
+/// Create an SMT-LIB2 script for each node which would be checked by the
+/// analysis (the bug nodes, unless `only_bugs` is false), in topological
+/// order. The scripts can be run by any solver which supports SMT-LIB2.
+pub fn smt2_queries(
+    source: &str,
+    options: &AnalysisOptions,
+) -> Result<Vec<Smt2Query>, AnalysisError> {
+    let program = parse(source).map_err(AnalysisError::Parse)?;
+    let (program_ir, metadata) = run_type_checking(&program).map_err(|errors| {
+        AnalysisError::TypeCheck(errors.into_iter().map(Diagnostic::from).collect())
+    })?;
+    let mut graph = GclGraph::new();
+    let start_idx = program_ir.to_gcl(&mut graph, &metadata);
+    unroll_loops(&mut graph, start_idx, options.unroll_bound);
+    merge_simple_edges(&mut graph);
+    let (node_predicates, _) = graph.to_reachability_predicates();
+
+    let solver_error = |error: SolverError| AnalysisError::Solver(Diagnostic::error(error.0, None));
+    let type_map = Smt2TypeMap::new(&metadata.types_in_order).map_err(solver_error)?;
+    let line_index = LineIndex::new(source);
+
+    let queries = sorted_nodes(&graph)
+        .into_iter()
+        .filter(|node_idx| is_checked(graph.node_weight(*node_idx).unwrap(), options))
+        .map(|node_idx| {
+            let node = graph.node_weight(node_idx).unwrap();
+            let predicate = &node_predicates[&node_idx];
+
+            let mut script = format!("; Reachability of node '{}'", node.name);
+            if let Some(span) = node.span {
+                write!(script, " (line {})", line_index.line_col(span.start).0).unwrap();
+            }
+            script.push_str("\n(set-option :produce-models true)\n");
+            for declaration in type_map.declarations() {
+                writeln!(script, "{}", declaration).unwrap();
+            }
+            for (_, declaration) in predicate
+                .smt2_declarations(&type_map)
+                .map_err(solver_error)?
+            {
+                writeln!(script, "{}", declaration).unwrap();
+            }
+            writeln!(script, "(assert {})", predicate.as_smt2(&type_map)).unwrap();
+            script.push_str("(check-sat)\n(get-model)\n");
+
+            Ok(Smt2Query {
+                node_name: node.name.clone(),
+                span: node.span,
+                script,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(queries)
+}
+
+/// Write out each node with its commands and the line it came from, followed
+/// by its out-edges and their guards
+fn format_graph(graph: &GclGraph, line_index: &LineIndex) -> String {
+    let mut output = String::new();
+
+    for node_idx in sorted_nodes(graph) {
+        let node = graph.node_weight(node_idx).unwrap();
+        if let Some(span) = node.span {
+            writeln!(output, "// line {}", line_index.line_col(span.start).0).unwrap();
+        }
+        write!(output, "{}", node).unwrap();
+
+        for edge in graph.edges_directed(node_idx, Direction::Outgoing) {
+            let target = &graph.node_weight(edge.target()).unwrap().name;
+            writeln!(output, "-> '{}' if {}", target, edge.weight()).unwrap();
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+/// The nodes in topological order, or in the order they were created if the
+/// graph has loops
+fn sorted_nodes(graph: &GclGraph) -> Vec<NodeIndex> {
+    toposort(graph.deref(), None).unwrap_or_else(|_| graph.node_indices().collect())
+}
@@ -0,0 +1,287 @@
+//! A solver backend which runs a solver program (ex. cvc5 or Bitwuzla) and
+//! sends it SMT-LIB2 commands over stdin
+
+use crate::gcl::GclExpr;
+use crate::ir::IrType;
+use crate::smtlib::{SExpr, Smt2TypeMap};
+use crate::solver::{CheckResult, ModelValue, SolverBackend, SolverError, SolverModel};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub struct ExternalSolver {
+    /// The program and its arguments, so it can be restarted
+    command: Vec<String>,
+    timeout: Option<Duration>,
+    process: SolverProcess,
+    type_map: Smt2TypeMap,
+    /// The variables and facts which have been declared. They are declared
+    /// outside of the checks' scopes, so they can be shared by the checks.
+    declared: HashSet<String>,
+    /// If the last check's assertion is still in scope
+    in_check: bool,
+}
+
+impl ExternalSolver {
+    /// Start the solver program and declare the user-defined types. The
+    /// command is the program followed by its arguments. Checks which take
+    /// longer than the timeout are unknown.
+    pub fn start(
+        command: &[String],
+        types: &[(String, IrType)],
+        timeout: Option<Duration>,
+    ) -> Result<Self, SolverError> {
+        let mut solver = ExternalSolver {
+            command: command.to_vec(),
+            timeout,
+            process: SolverProcess::start(command)?,
+            type_map: Smt2TypeMap::new(types)?,
+            declared: HashSet::new(),
+            in_check: false,
+        };
+        solver.set_up()?;
+
+        Ok(solver)
+    }
+
+    fn set_up(&mut self) -> Result<(), SolverError> {
+        // Every command gets a response, so errors can be matched to commands
+        self.run("(set-option :print-success true)")?;
+        self.run("(set-option :produce-models true)")?;
+        for declaration in self.type_map.declarations().to_vec() {
+            self.run(&declaration)?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace the solver program with a new one, such as when it is stuck on
+    /// a check which timed out
+    fn restart(&mut self) -> Result<(), SolverError> {
+        self.process = SolverProcess::start(&self.command)?;
+        self.declared.clear();
+        self.in_check = false;
+        self.set_up()
+    }
+
+    /// Send a command which is expected to succeed without any output
+    fn run(&mut self, command: &str) -> Result<(), SolverError> {
+        match self.process.send(command)? {
+            SExpr::Atom(response) if response == "success" => Ok(()),
+            response => Err(self.process.unexpected_response(command, &response)),
+        }
+    }
+
+    /// Get the value of the expression in the last check's model. Variables
+    /// which no check has used are not declared, so they are bound to the
+    /// values which Z3 would complete the model with, to match the Z3 backend.
+    fn get_value(&mut self, expr: &GclExpr) -> Result<Option<ModelValue>, SolverError> {
+        let mut bindings = String::new();
+        for (name, ty) in expr.smt2_constants() {
+            if !self.declared.contains(&name) {
+                let value = self.type_map.default_value(ty)?;
+                bindings.push_str(&format!("({} {})", name, value));
+            }
+        }
+        let term = if bindings.is_empty() {
+            expr.as_smt2(&self.type_map)
+        } else {
+            format!("(let ({}) {})", bindings, expr.as_smt2(&self.type_map))
+        };
+
+        // The response looks like `((expr value))`
+        let command = format!("(get-value ({}))", term);
+        let response = self.process.send(&command)?;
+        let value = match &response {
+            SExpr::List(pairs) => match pairs.as_slice() {
+                [SExpr::List(pair)] if pair.len() == 2 => pair[1].to_model_value(),
+                _ => return Err(self.process.unexpected_response(&command, &response)),
+            },
+            SExpr::Atom(_) => return Err(self.process.unexpected_response(&command, &response)),
+        };
+
+        Ok(value)
+    }
+}
+
+impl SolverBackend for ExternalSolver {
+    fn check(&mut self, predicate: &GclExpr) -> Result<CheckResult<'_>, SolverError> {
+        if self.in_check {
+            self.run("(pop 1)")?;
+            self.in_check = false;
+        }
+
+        for (name, declaration) in predicate.smt2_declarations(&self.type_map)? {
+            if !self.declared.contains(&name) {
+                self.run(&declaration)?;
+                self.declared.insert(name);
+            }
+        }
+
+        self.run("(push 1)")?;
+        self.in_check = true;
+        self.run(&format!("(assert {})", predicate.as_smt2(&self.type_map)))?;
+
+        let command = "(check-sat)";
+        match self.process.send_with_timeout(command, self.timeout)? {
+            Some(SExpr::Atom(result)) if result == "sat" => {
+                Ok(CheckResult::Sat(Box::new(ExternalModel {
+                    solver: RefCell::new(self),
+                })))
+            }
+            Some(SExpr::Atom(result)) if result == "unsat" => Ok(CheckResult::Unsat),
+            Some(SExpr::Atom(result)) if result == "unknown" => Ok(CheckResult::Unknown),
+            Some(response) => Err(self.process.unexpected_response(command, &response)),
+            None => {
+                log::debug!("{} timed out", self.process.program);
+                self.restart()?;
+                Ok(CheckResult::Unknown)
+            }
+        }
+    }
+}
+
+/// A running solver program
+struct SolverProcess {
+    /// The name of the program, for error messages
+    program: String,
+    child: Child,
+    stdin: ChildStdin,
+    /// The lines written by the program. They are read on another thread, so
+    /// waiting for a response can time out.
+    stdout: Receiver<String>,
+}
+
+impl SolverProcess {
+    fn start(command: &[String]) -> Result<Self, SolverError> {
+        let (program, args) = command
+            .split_first()
+            .ok_or_else(|| SolverError("The solver command is empty".to_string()))?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|error| SolverError(format!("Unable to start {}: {}", program, error)))?;
+
+        // The thread stops when the program exits and closes its stdout
+        let (sender, receiver) = mpsc::channel();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        thread::spawn(move || {
+            for line in stdout.lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(SolverProcess {
+            program: program.clone(),
+            stdin: child.stdin.take().unwrap(),
+            child,
+            stdout: receiver,
+        })
+    }
+
+    /// Send a command and read the program's response to it
+    fn send(&mut self, command: &str) -> Result<SExpr, SolverError> {
+        let response = self.send_with_timeout(command, None)?;
+        Ok(response.expect("Responses only time out if there is a timeout"))
+    }
+
+    /// Send a command and read the program's response to it, or return `None`
+    /// if there is no response before the timeout
+    fn send_with_timeout(
+        &mut self,
+        command: &str,
+        timeout: Option<Duration>,
+    ) -> Result<Option<SExpr>, SolverError> {
+        log::trace!("Sending to {}: {}", self.program, command);
+        writeln!(self.stdin, "{}", command)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|error| {
+                SolverError(format!(
+                    "Unable to send a command to {}: {}",
+                    self.program, error
+                ))
+            })?;
+
+        // Responses can be split across lines
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut response = String::new();
+        loop {
+            let line = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    match self.stdout.recv_timeout(remaining) {
+                        Ok(line) => Some(line),
+                        Err(RecvTimeoutError::Timeout) => return Ok(None),
+                        Err(RecvTimeoutError::Disconnected) => None,
+                    }
+                }
+                None => self.stdout.recv().ok(),
+            };
+            let line = line.ok_or_else(|| {
+                SolverError(format!(
+                    "{} exited while running `{}`",
+                    self.program, command
+                ))
+            })?;
+
+            response.push_str(&line);
+            response.push('\n');
+            if let Some(sexpr) = SExpr::parse(&response) {
+                return Ok(Some(sexpr));
+            }
+        }
+    }
+
+    fn unexpected_response(&self, command: &str, response: &SExpr) -> SolverError {
+        // Errors look like `(error "message")`
+        let message = match response {
+            SExpr::List(items) => match items.as_slice() {
+                [SExpr::Atom(error), SExpr::Atom(message)] if error == "error" => {
+                    message.trim_matches('"').to_string()
+                }
+                _ => response.to_string(),
+            },
+            SExpr::Atom(_) => response.to_string(),
+        };
+
+        SolverError(format!(
+            "{} failed to run `{}`: {}",
+            self.program, command, message
+        ))
+    }
+}
+
+impl Drop for SolverProcess {
+    fn drop(&mut self) {
+        // The program may be waiting for more commands (or may have already
+        // exited), so it is stopped instead of being asked to exit
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The model of the solver's last check. Values are read from the solver as
+/// they are needed.
+struct ExternalModel<'a> {
+    solver: RefCell<&'a mut ExternalSolver>,
+}
+
+impl SolverModel for ExternalModel<'_> {
+    fn eval(&self, expr: &GclExpr) -> Option<ModelValue> {
+        self.solver
+            .borrow_mut()
+            .get_value(expr)
+            .unwrap_or_else(|error| {
+                log::warn!("{}", error);
+                None
+            })
+    }
+}
@@ -1,9 +1,13 @@
 //! Guarded Command Language
 
-use crate::ir::{IrType, VariableId};
+use crate::ast::{BinOp, UnOp};
+use crate::ir::{IrActionDecl, IrBaseType, IrTableDecl, IrType, VariableId};
+use crate::span::Span;
+use num_bigint::BigInt;
 use petgraph::graph::NodeIndex;
 use petgraph::stable_graph::{StableDiGraph, StableGraph};
-use std::collections::HashMap;
+use petgraph::visit::NodeIndexable;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter, Write};
 use std::ops::{Deref, DerefMut};
@@ -11,8 +15,20 @@ use std::ops::{Deref, DerefMut};
 pub struct GclGraph {
     inner: StableDiGraph<GclNode, GclExpr>,
     next_id_counter: usize,
-    functions: HashMap<VariableId, GclNodeRange>,
+    actions: HashMap<VariableId, IrActionDecl>,
+    tables: HashMap<VariableId, IrTableDecl>,
     var_locations: HashMap<VariableId, MemoryLocation>,
+    aliased_vars: HashSet<VariableId>,
+    table_choices: HashMap<MemoryLocation, TableChoice>,
+    /// The names of the blocks (ex. actions) which nodes were created for
+    blocks: Vec<String>,
+    node_blocks: HashMap<NodeIndex, usize>,
+}
+
+impl Default for GclGraph {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GclGraph {
@@ -20,8 +36,13 @@ impl GclGraph {
         GclGraph {
             inner: StableGraph::new(),
             next_id_counter: 0,
-            functions: HashMap::new(),
+            actions: HashMap::new(),
+            tables: HashMap::new(),
             var_locations: HashMap::new(),
+            aliased_vars: HashSet::new(),
+            table_choices: HashMap::new(),
+            blocks: Vec::new(),
+            node_blocks: HashMap::new(),
         }
     }
 
@@ -38,14 +59,24 @@ impl GclGraph {
         loc
     }
 
-    /// Register the node range for a function. This is used later when calling
-    /// it to set up edges.
-    pub fn register_function(&mut self, id: VariableId, range: GclNodeRange) {
-        self.functions.insert(id, range);
+    /// Register an action. Its GCL is created at each call, so the calls don't
+    /// share nodes (which would form a cycle between them).
+    pub fn register_action(&mut self, action: IrActionDecl) {
+        self.actions.insert(action.id.clone(), action);
+    }
+
+    pub fn get_action(&self, id: &VariableId) -> Option<&IrActionDecl> {
+        self.actions.get(id)
+    }
+
+    /// Register a table. Its GCL is created each time it is applied, so each
+    /// application gets its own action data.
+    pub fn register_table(&mut self, table: IrTableDecl) {
+        self.tables.insert(table.id.clone(), table);
     }
 
-    pub fn get_function(&self, id: &VariableId) -> Option<GclNodeRange> {
-        self.functions.get(id).copied()
+    pub fn get_table(&self, id: &VariableId) -> Option<&IrTableDecl> {
+        self.tables.get(id)
     }
 
     pub fn get_var_location(&mut self, var: &VariableId) -> MemoryLocation {
@@ -57,6 +88,78 @@ impl GclGraph {
         self.var_locations.insert(var.clone(), loc.clone());
         loc
     }
+
+    /// Make the variable share the memory location of another variable. This
+    /// is used to pass values between blocks (ex. from a parser to a control).
+    pub fn alias_var_location(&mut self, var: VariableId, other: &VariableId) {
+        let loc = self.get_var_location(other);
+        self.var_locations.insert(var.clone(), loc);
+        self.aliased_vars.insert(var);
+    }
+
+    /// Check if the variable shares its memory location with another variable
+    pub fn is_var_aliased(&self, var: &VariableId) -> bool {
+        self.aliased_vars.contains(var)
+    }
+
+    /// Get each variable and its memory location. Aliased variables are
+    /// skipped, so each location belongs to a single variable.
+    pub fn variables(&self) -> impl Iterator<Item = (&VariableId, &MemoryLocation)> {
+        self.var_locations
+            .iter()
+            .filter(move |(var, _)| !self.aliased_vars.contains(var))
+    }
+
+    /// Find the variable which the memory location belongs to
+    pub fn location_var(&self, loc: &MemoryLocation) -> Option<&VariableId> {
+        self.variables()
+            .find(|(_, var_loc)| *var_loc == loc)
+            .map(|(var, _)| var)
+    }
+
+    /// Register the location which holds the action chosen by a table
+    /// application
+    pub fn register_table_choice(&mut self, loc: MemoryLocation, choice: TableChoice) {
+        self.table_choices.insert(loc, choice);
+    }
+
+    pub fn get_table_choice(&self, loc: &MemoryLocation) -> Option<&TableChoice> {
+        self.table_choices.get(loc)
+    }
+
+    /// Get a marker for the nodes created after this point, to be passed to
+    /// `add_block` once the block's nodes are created
+    pub fn block_start(&self) -> usize {
+        self.inner.node_bound()
+    }
+
+    /// Put the nodes created since `block_start` into a block with the given
+    /// name (ex. "action forward"). Nodes which are already in a block (ex.
+    /// the nodes of an action inside of a control) are left alone.
+    pub fn add_block(&mut self, name: String, block_start: usize) {
+        let block_id = self.blocks.len();
+        self.blocks.push(name);
+
+        for node_idx in block_start..self.inner.node_bound() {
+            let node_idx = NodeIndex::new(node_idx);
+            if self.inner.contains_node(node_idx) {
+                self.node_blocks.entry(node_idx).or_insert(block_id);
+            }
+        }
+    }
+
+    /// Put the node into the same block as another node
+    pub fn copy_node_block(&mut self, node_idx: NodeIndex, other_idx: NodeIndex) {
+        if let Some(block_id) = self.node_blocks.get(&other_idx).copied() {
+            self.node_blocks.insert(node_idx, block_id);
+        }
+    }
+
+    /// Get the name of the block which the node was created for, if any
+    pub fn node_block(&self, node_idx: NodeIndex) -> Option<&str> {
+        let block_id = self.node_blocks.get(&node_idx)?;
+        Some(&self.blocks[*block_id])
+    }
 }
 
 impl Deref for GclGraph {
@@ -73,15 +176,45 @@ impl DerefMut for GclGraph {
     }
 }
 
+/// The actions which a table application can run. The control plane's choice
+/// is stored in a memory location as the index of the action, and the last
+/// action is the default action.
+#[derive(Clone, Debug)]
+pub struct TableChoice {
+    pub table: String,
+    pub actions: Vec<String>,
+    /// The table's declaration
+    pub span: Span,
+    /// The `apply` call which made the choice
+    pub call_span: Span,
+    /// The loop iteration which made the choice, counting from 0. Outside of
+    /// loops it is always 0.
+    pub iteration: usize,
+}
+
 #[derive(Debug)]
 pub struct GclNode {
     pub name: String,
     pub commands: Vec<GclCommand>,
+    /// The code which the node was created from, if any
+    pub span: Option<Span>,
 }
 
 impl GclNode {
     pub fn is_bug(&self) -> bool {
-        self.commands.contains(&GclCommand::Bug)
+        self.bug().is_some()
+    }
+
+    /// Get the bug this node represents, if any
+    pub fn bug(&self) -> Option<&GclBug> {
+        self.commands.iter().find_map(|cmd| match cmd {
+            GclCommand::Bug(bug) => Some(bug),
+            _ => None,
+        })
+    }
+
+    pub fn is_unroll_bound_exceeded(&self) -> bool {
+        self.commands.contains(&GclCommand::UnrollBoundExceeded)
     }
 }
 
@@ -110,8 +243,35 @@ pub enum GclCommand {
     Assignment(GclAssignment),
     AddFact(GclFact),
     RemoveFact(GclFact),
+    /// The fact holds if the (boolean) expression is true
+    SetFact(GclFact, GclExpr),
+    /// Forget whether the fact holds, so it may or may not be true
+    HavocFact(GclFact),
     /// Represents a bug in the program, ex. if an assert fails
-    Bug,
+    Bug(GclBug),
+    /// A loop ran for more iterations than it was unrolled, so the paths
+    /// after this point were not analyzed
+    UnrollBoundExceeded,
+}
+
+impl GclCommand {
+    /// Call the function on each memory location used by this command
+    pub fn visit_locations_mut(&mut self, f: &mut impl FnMut(&mut MemoryLocation)) {
+        match self {
+            GclCommand::Assignment(GclAssignment { lvalue, expr }) => {
+                lvalue.visit_locations_mut(f);
+                expr.visit_locations_mut(f);
+            }
+            GclCommand::AddFact(fact) | GclCommand::RemoveFact(fact) | GclCommand::HavocFact(fact) => {
+                fact.visit_locations_mut(f)
+            }
+            GclCommand::SetFact(fact, expr) => {
+                fact.visit_locations_mut(f);
+                expr.visit_locations_mut(f);
+            }
+            GclCommand::Bug(_) | GclCommand::UnrollBoundExceeded => {}
+        }
+    }
 }
 
 impl Display for GclCommand {
@@ -120,11 +280,51 @@ impl Display for GclCommand {
             GclCommand::Assignment(assignment) => Display::fmt(assignment, f),
             GclCommand::AddFact(fact) => write!(f, "add_fact({})", fact),
             GclCommand::RemoveFact(fact) => write!(f, "remove_fact({})", fact),
-            GclCommand::Bug => f.write_str("bug"),
+            GclCommand::SetFact(fact, expr) => write!(f, "set_fact({}, {})", fact, expr),
+            GclCommand::HavocFact(fact) => write!(f, "havoc_fact({})", fact),
+            GclCommand::Bug(bug) => write!(f, "bug({})", bug),
+            GclCommand::UnrollBoundExceeded => f.write_str("unroll_bound_exceeded"),
         }
     }
 }
 
+/// A bug at a specific place in the program
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GclBug {
+    pub kind: BugKind,
+    /// The variable (or field) involved in the bug, as written in the source
+    pub variable: Option<String>,
+    pub span: Span,
+}
+
+impl Display for GclBug {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let variable = self.variable.as_deref().unwrap_or("<unknown>");
+
+        match self.kind {
+            BugKind::UninitializedRead => write!(f, "read of uninitialized `{}`", variable),
+            BugKind::InvalidHeaderAccess => {
+                write!(f, "access to a field of invalid header `{}`", variable)
+            }
+            BugKind::UnsetOutParam => write!(f, "out parameter `{}` is not set", variable),
+            BugKind::UserAssert => f.write_str("assertion failed"),
+        }
+    }
+}
+
+/// The kinds of bugs which are detected
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BugKind {
+    /// A variable was read before it was given a value
+    UninitializedRead,
+    /// A field of an invalid header was read or written
+    InvalidHeaderAccess,
+    /// An `out` parameter was not given a value before returning
+    UnsetOutParam,
+    /// The condition of an `assert` call was false
+    UserAssert,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct GclAssignment {
     pub lvalue: GclLValue,
@@ -137,17 +337,25 @@ impl Display for GclAssignment {
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub enum GclLValue {
     Var(MemoryLocation),
-    Field(Box<GclLValue>, String),
+    /// A field of the target, which has the given type
+    Field(Box<GclLValue>, IrType, String),
 }
 
 impl GclLValue {
     pub fn mem_location(&self) -> MemoryLocation {
         match self {
             GclLValue::Var(loc) => loc.clone(),
-            GclLValue::Field(target, _) => target.mem_location(),
+            GclLValue::Field(target, _, _) => target.mem_location(),
+        }
+    }
+
+    pub fn visit_locations_mut(&mut self, f: &mut impl FnMut(&mut MemoryLocation)) {
+        match self {
+            GclLValue::Var(loc) => f(loc),
+            GclLValue::Field(target, _, _) => target.visit_locations_mut(f),
         }
     }
 }
@@ -156,7 +364,7 @@ impl Display for GclLValue {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             GclLValue::Var(loc) => Display::fmt(loc, f),
-            GclLValue::Field(target, field) => {
+            GclLValue::Field(target, _, field) => {
                 write!(f, "{}.{}", target, field)
             }
         }
@@ -167,6 +375,13 @@ impl Display for GclLValue {
 pub enum MemoryLocation {
     ReturnVal,
     Var(usize, Option<String>),
+    /// Which of the node's incoming edges was taken to reach it (an integer
+    /// counting from 0). Values which depend on the edge that was taken are
+    /// merged using it.
+    EdgeSelector(NodeIndex),
+    /// The value of a location at a node whose incoming edges disagree on it.
+    /// It is equal to the location's value from the edge which was taken.
+    MergedValue(NodeIndex, Box<MemoryLocation>),
 }
 
 impl Display for MemoryLocation {
@@ -179,6 +394,12 @@ impl Display for MemoryLocation {
             MemoryLocation::Var(id, Some(name)) => {
                 write!(f, "loc_{}_{}", id, name)
             }
+            MemoryLocation::EdgeSelector(node_idx) => {
+                write!(f, "edge_selector_{}", node_idx.index())
+            }
+            MemoryLocation::MergedValue(node_idx, loc) => {
+                write!(f, "merged_{}_{}", node_idx.index(), loc)
+            }
         }
     }
 }
@@ -192,6 +413,7 @@ pub struct GclExpr {
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub enum GclExprData {
     Bool(bool),
+    Int(BigInt),
     String(String),
     Fact(GclFact),
     Var(MemoryLocation),
@@ -216,6 +438,14 @@ impl GclExpr {
         }
     }
 
+    /// Create an integer constant of the given integer type
+    pub fn int(value: BigInt, ty: IrType) -> Self {
+        GclExpr {
+            ty,
+            data: GclExprData::Int(value),
+        }
+    }
+
     pub fn string(s: String) -> Self {
         GclExpr {
             ty: IrType::string(),
@@ -239,7 +469,7 @@ impl GclExpr {
 
     pub fn bin_op(op: GclBinOp, left: GclExpr, right: GclExpr) -> Self {
         GclExpr {
-            ty: op.ty(),
+            ty: op.result_ty(&left.ty),
             data: GclExprData::BinOp(op, Box::new(left), Box::new(right)),
         }
     }
@@ -251,11 +481,66 @@ impl GclExpr {
         }
     }
 
+    pub fn visit_locations_mut(&mut self, f: &mut impl FnMut(&mut MemoryLocation)) {
+        match &mut self.data {
+            GclExprData::Bool(_) | GclExprData::Int(_) | GclExprData::String(_) => {}
+            GclExprData::Fact(fact) => fact.visit_locations_mut(f),
+            GclExprData::Var(loc) => f(loc),
+            GclExprData::BinOp(_, left, right) => {
+                left.visit_locations_mut(f);
+                right.visit_locations_mut(f);
+            }
+            GclExprData::UnOp(_, inner) => inner.visit_locations_mut(f),
+            GclExprData::Struct { fields } => {
+                for (_, field) in fields {
+                    field.visit_locations_mut(f);
+                }
+            }
+            GclExprData::FieldAccess(target, _) => target.visit_locations_mut(f),
+        }
+    }
+
+    /// Call the function on each variable, field of a variable, and fact in
+    /// the expression
+    pub fn visit_variables<'a>(&'a self, f: &mut impl FnMut(&'a GclExpr)) {
+        if self.is_place() {
+            f(self);
+            return;
+        }
+
+        match &self.data {
+            GclExprData::Bool(_) | GclExprData::Int(_) | GclExprData::String(_) => {}
+            GclExprData::Fact(_) => f(self),
+            GclExprData::Var(_) => unreachable!("Variables are places"),
+            GclExprData::BinOp(_, left, right) => {
+                left.visit_variables(f);
+                right.visit_variables(f);
+            }
+            GclExprData::UnOp(_, inner) => inner.visit_variables(f),
+            GclExprData::Struct { fields } => {
+                for (_, field) in fields {
+                    field.visit_variables(f);
+                }
+            }
+            GclExprData::FieldAccess(target, _) => target.visit_variables(f),
+        }
+    }
+
+    /// Check if the expression is a variable or a field of a variable
+    pub fn is_place(&self) -> bool {
+        match &self.data {
+            GclExprData::Var(_) => true,
+            GclExprData::FieldAccess(target, _) => target.is_place(),
+            _ => false,
+        }
+    }
+
     /// Simplify this expression if possible. This is usually only useful for
     /// expressions which are used as predicates (ex. GCL edges).
     pub fn simplify(&mut self) {
         match &mut self.data {
             GclExprData::Bool(_) => {}
+            GclExprData::Int(_) => {}
             GclExprData::String(_) => {}
             GclExprData::Fact(_) => {}
             GclExprData::Var(_) => {}
@@ -288,11 +573,13 @@ impl GclExpr {
                         }
                         _ => {}
                     },
-                    GclBinOp::Equals => {
-                        if left.data == right.data {
-                            self.data = GclExprData::Bool(true);
-                        }
+                    GclBinOp::Equals if left.data == right.data => {
+                        self.data = GclExprData::Bool(true);
+                    }
+                    GclBinOp::NotEquals if left.data == right.data => {
+                        self.data = GclExprData::Bool(false);
                     }
+                    _ => {}
                 }
             }
             GclExprData::UnOp(op, inner) => {
@@ -304,6 +591,7 @@ impl GclExpr {
                             self.data = GclExprData::Bool(!*b);
                         }
                     }
+                    GclUnOp::BitNot | GclUnOp::Minus => {}
                 }
             }
             GclExprData::Struct { .. } => {}
@@ -316,6 +604,11 @@ impl Display for GclExpr {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match &self.data {
             GclExprData::Bool(b) => Display::fmt(b, f),
+            GclExprData::Int(value) => match &self.ty {
+                IrType::Base(IrBaseType::Bit { width }) => write!(f, "{}w{}", width, value),
+                IrType::Base(IrBaseType::SignedInt { width }) => write!(f, "{}s{}", width, value),
+                _ => Display::fmt(value, f),
+            },
             GclExprData::String(s) => Debug::fmt(s, f),
             GclExprData::Fact(fact) => Display::fmt(fact, f),
             GclExprData::Var(loc) => Display::fmt(loc, f),
@@ -352,22 +645,101 @@ pub enum GclBinOp {
     And,
     Or,
     Equals,
+    NotEquals,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    SaturatingAdd,
+    SaturatingSub,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+    LessThan,
+    LessThanEquals,
+    GreaterThan,
+    GreaterThanEquals,
 }
 
 impl Display for GclBinOp {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match self {
-            GclBinOp::And => f.write_str("&&"),
-            GclBinOp::Or => f.write_str("||"),
-            GclBinOp::Equals => f.write_str("=="),
-        }
+        f.write_str(match self {
+            GclBinOp::And => "&&",
+            GclBinOp::Or => "||",
+            GclBinOp::Equals => "==",
+            GclBinOp::NotEquals => "!=",
+            GclBinOp::Add => "+",
+            GclBinOp::Sub => "-",
+            GclBinOp::Mul => "*",
+            GclBinOp::Div => "/",
+            GclBinOp::Mod => "%",
+            GclBinOp::SaturatingAdd => "|+|",
+            GclBinOp::SaturatingSub => "|-|",
+            GclBinOp::BitAnd => "&",
+            GclBinOp::BitOr => "|",
+            GclBinOp::BitXor => "^",
+            GclBinOp::ShiftLeft => "<<",
+            GclBinOp::ShiftRight => ">>",
+            GclBinOp::LessThan => "<",
+            GclBinOp::LessThanEquals => "<=",
+            GclBinOp::GreaterThan => ">",
+            GclBinOp::GreaterThanEquals => ">=",
+        })
     }
 }
 
 impl GclBinOp {
-    pub fn ty(&self) -> IrType {
+    /// Get the type of the result, given the type of the (left) operand
+    pub fn result_ty(&self, operand_ty: &IrType) -> IrType {
         match self {
-            GclBinOp::And | GclBinOp::Or | GclBinOp::Equals => IrType::bool(),
+            GclBinOp::And
+            | GclBinOp::Or
+            | GclBinOp::Equals
+            | GclBinOp::NotEquals
+            | GclBinOp::LessThan
+            | GclBinOp::LessThanEquals
+            | GclBinOp::GreaterThan
+            | GclBinOp::GreaterThanEquals => IrType::bool(),
+            GclBinOp::Add
+            | GclBinOp::Sub
+            | GclBinOp::Mul
+            | GclBinOp::Div
+            | GclBinOp::Mod
+            | GclBinOp::SaturatingAdd
+            | GclBinOp::SaturatingSub
+            | GclBinOp::BitAnd
+            | GclBinOp::BitOr
+            | GclBinOp::BitXor
+            | GclBinOp::ShiftLeft
+            | GclBinOp::ShiftRight => operand_ty.clone(),
+        }
+    }
+}
+
+impl From<BinOp> for GclBinOp {
+    fn from(op: BinOp) -> Self {
+        match op {
+            BinOp::Add => GclBinOp::Add,
+            BinOp::Sub => GclBinOp::Sub,
+            BinOp::Mul => GclBinOp::Mul,
+            BinOp::Div => GclBinOp::Div,
+            BinOp::Mod => GclBinOp::Mod,
+            BinOp::SaturatingAdd => GclBinOp::SaturatingAdd,
+            BinOp::SaturatingSub => GclBinOp::SaturatingSub,
+            BinOp::BitAnd => GclBinOp::BitAnd,
+            BinOp::BitOr => GclBinOp::BitOr,
+            BinOp::BitXor => GclBinOp::BitXor,
+            BinOp::ShiftLeft => GclBinOp::ShiftLeft,
+            BinOp::ShiftRight => GclBinOp::ShiftRight,
+            BinOp::Equals => GclBinOp::Equals,
+            BinOp::NotEquals => GclBinOp::NotEquals,
+            BinOp::LessThan => GclBinOp::LessThan,
+            BinOp::LessThanEquals => GclBinOp::LessThanEquals,
+            BinOp::GreaterThan => GclBinOp::GreaterThan,
+            BinOp::GreaterThanEquals => GclBinOp::GreaterThanEquals,
         }
     }
 }
@@ -375,12 +747,25 @@ impl GclBinOp {
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum GclUnOp {
     Negate,
+    BitNot,
+    Minus,
 }
 
 impl Display for GclUnOp {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             GclUnOp::Negate => f.write_char('!'),
+            GclUnOp::BitNot => f.write_char('~'),
+            GclUnOp::Minus => f.write_char('-'),
+        }
+    }
+}
+
+impl From<UnOp> for GclUnOp {
+    fn from(op: UnOp) -> Self {
+        match op {
+            UnOp::BitNot => GclUnOp::BitNot,
+            UnOp::Minus => GclUnOp::Minus,
         }
     }
 }
@@ -388,15 +773,27 @@ impl Display for GclUnOp {
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub enum GclFact {
     HasValue(MemoryLocation),
+    /// The header is valid
+    IsValid(GclLValue),
 }
 
 impl GclFact {
+    pub fn visit_locations_mut(&mut self, f: &mut impl FnMut(&mut MemoryLocation)) {
+        match self {
+            GclFact::HasValue(loc) => f(loc),
+            GclFact::IsValid(header) => header.visit_locations_mut(f),
+        }
+    }
+
     /// Get the variable name to use for this fact in Z3
     pub fn z3_name(&self) -> String {
         match self {
             GclFact::HasValue(loc) => {
                 format!("has_value__{}", loc)
             }
+            GclFact::IsValid(header) => {
+                format!("is_valid__{}", header)
+            }
         }
     }
 }
@@ -407,6 +804,9 @@ impl Display for GclFact {
             GclFact::HasValue(loc) => {
                 write!(f, "HasValue({})", loc)
             }
+            GclFact::IsValid(header) => {
+                write!(f, "IsValid({})", header)
+            }
         }
     }
 }
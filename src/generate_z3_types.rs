@@ -1,59 +1,62 @@
 use crate::ir::{IrBaseType, IrType};
 use std::collections::HashMap;
-use z3::{Context, DatatypeAccessor, DatatypeBuilder, DatatypeSort, Sort};
+use z3::{DatatypeAccessor, DatatypeBuilder, DatatypeSort, Sort};
 
-pub type Z3TypeMap<'ctx> = HashMap<IrType, DatatypeSort<'ctx>>;
+pub type Z3TypeMap = HashMap<IrType, DatatypeSort>;
 
-/// Translate the user-defined IR types (ex. structs) into Z3 types
-pub fn generate_types<'ctx>(types: &[(String, IrType)], context: &'ctx Context) -> Z3TypeMap<'ctx> {
+/// Translate the user-defined IR types (ex. structs and headers) into Z3 types,
+/// in the current thread's Z3 context
+pub fn generate_types(types: &[(String, IrType)]) -> Z3TypeMap {
     let mut next_id = 0;
     let mut z3_types: Z3TypeMap = HashMap::new();
 
-    // FIXME: Maybe this dance can be avoided, context:
-    //        https://github.com/prove-rs/z3.rs/pull/137
-    let bool_sort = Sort::bool(context);
-    let int_sort = Sort::int(context);
-    let string_sort = Sort::string(context);
-
     for (ty_name, ty) in types {
         match ty {
-            IrType::Base(IrBaseType::Struct { fields }) => {
-                let name = format!("struct_{}_{}", next_id, ty_name);
+            IrType::Base(IrBaseType::Struct { fields })
+            | IrType::Base(IrBaseType::Header { fields }) => {
+                let kind = match ty {
+                    IrType::Base(IrBaseType::Header { .. }) => "header",
+                    _ => "struct",
+                };
+                let name = format!("{}_{}_{}", kind, next_id, ty_name);
                 next_id += 1;
-                let builder = DatatypeBuilder::new(context, name.as_str());
+                let builder = DatatypeBuilder::new(name.as_str());
 
                 let z3_fields = fields
                     .iter()
                     .map(|(field_ty, field_name)| {
                         let datatype_accessor = match field_ty {
-                            IrBaseType::Bool => DatatypeAccessor::Sort(&bool_sort),
-                            IrBaseType::Int => DatatypeAccessor::Sort(&int_sort),
-                            IrBaseType::Bit { .. } => {
-                                todo!()
+                            IrBaseType::Bool => DatatypeAccessor::Sort(Sort::bool()),
+                            IrBaseType::Int => DatatypeAccessor::Sort(Sort::int()),
+                            IrBaseType::Bit { width } | IrBaseType::SignedInt { width } => {
+                                DatatypeAccessor::Sort(Sort::bitvector(*width as u32))
                             }
-                            IrBaseType::String => DatatypeAccessor::Sort(&string_sort),
+                            IrBaseType::String => DatatypeAccessor::Sort(Sort::string()),
                             IrBaseType::Error => {
                                 unimplemented!()
                             }
                             IrBaseType::MatchKind => {
                                 unimplemented!()
                             }
+                            IrBaseType::PacketIn => {
+                                unimplemented!()
+                            }
                             IrBaseType::Enum { .. } => {
                                 todo!()
                             }
-                            IrBaseType::Struct { .. } => {
+                            IrBaseType::Struct { .. } | IrBaseType::Header { .. } => {
                                 let z3_ty = z3_types
                                     .get(&IrType::Base(field_ty.clone()))
                                     .expect("Use of type before it was declared");
 
-                                DatatypeAccessor::Sort(&z3_ty.sort)
-                            }
-                            IrBaseType::Header { .. } => {
-                                todo!()
+                                DatatypeAccessor::Sort(z3_ty.sort.clone())
                             }
                             IrBaseType::TyVar(_) => {
                                 unimplemented!()
                             }
+                            IrBaseType::Poisoned => {
+                                unreachable!("Programs with type errors are not analyzed")
+                            }
                         };
 
                         (field_name.as_str(), datatype_accessor)
@@ -65,6 +68,7 @@ pub fn generate_types<'ctx>(types: &[(String, IrType)], context: &'ctx Context)
             }
             IrType::Base(_) => {}
             IrType::Table => {}
+            IrType::ParserState => {}
             IrType::Function(_) => {}
             IrType::Constructor(_) => {}
         }
@@ -1,8 +1,11 @@
 //! An Intermediate Representation (IR) of P4 code which includes type information
 
-use crate::ast::Direction;
+use crate::ast::{BinOp, Direction, UnOp};
+use crate::pretty::{write_block, CommaSeparated, Indented};
+use crate::span::Span;
+use num_bigint::{BigInt, Sign};
 use std::fmt;
-use std::fmt::{Display, Formatter};
+use std::fmt::{Display, Formatter, Write};
 
 /****************************** IDs ******************************/
 
@@ -26,6 +29,7 @@ impl Display for VariableId {
 pub enum IrType {
     Base(IrBaseType),
     Table,
+    ParserState,
     Function(IrFunctionType),
     #[allow(unused)]
     Constructor(IrConstructorType),
@@ -39,22 +43,73 @@ impl IrType {
     pub fn string() -> Self {
         IrType::Base(IrBaseType::String)
     }
+
+    pub fn int() -> Self {
+        IrType::Base(IrBaseType::Int)
+    }
+
+    /// Check if the type is, or contains, the poisoned type
+    pub fn is_poisoned(&self) -> bool {
+        match self {
+            IrType::Base(ty) => ty.is_poisoned(),
+            IrType::Function(IrFunctionType { result, inputs }) => {
+                result.is_poisoned() || inputs.iter().any(|(_, ty)| ty.is_poisoned())
+            }
+            IrType::Table | IrType::ParserState | IrType::Constructor(_) => false,
+        }
+    }
+}
+
+impl Display for IrType {
+    /// Display the type as it would be written in P4
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IrType::Base(ty) => Display::fmt(ty, f),
+            IrType::Table => f.write_str("table"),
+            IrType::ParserState => f.write_str("parser state"),
+            IrType::Function(IrFunctionType { result, inputs }) => {
+                write!(f, "{} (", result)?;
+
+                for (i, (direction, ty)) in inputs.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(", ")?;
+                    }
+
+                    match direction {
+                        Direction::In => write!(f, "in {}", ty)?,
+                        Direction::Out => write!(f, "out {}", ty)?,
+                        Direction::InOut => write!(f, "inout {}", ty)?,
+                        Direction::Directionless => Display::fmt(ty, f)?,
+                    }
+                }
+
+                f.write_str(")")
+            }
+            IrType::Constructor(_) => f.write_str("constructor"),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 #[allow(unused)]
 pub enum IrBaseType {
-    // TODO: extend these types, e.g. with varbit<> and int<>
+    // TODO: extend these types, e.g. with varbit<>
     Bool,
     Int,
     Bit { width: usize },
+    SignedInt { width: usize },
     String,
     Error,
     MatchKind,
     Enum { name: String, fields: Vec<String> },
     Struct { fields: Vec<(IrBaseType, String)> },
     Header { fields: Vec<(IrBaseType, String)> },
+    /// The built-in `packet_in` extern, which headers are extracted from
+    PacketIn,
     TyVar(TypeVarId),
+    /// The type of something which failed to type check. Errors involving it
+    /// are not reported, since they are caused by the original error.
+    Poisoned,
 }
 
 impl IrBaseType {
@@ -65,6 +120,58 @@ impl IrBaseType {
     pub fn is_void(&self) -> bool {
         matches!(self, IrBaseType::Struct { fields } if fields.is_empty())
     }
+
+    /// Get the width of a fixed-width integer type (bit<N> or int<N>)
+    pub fn bit_width(&self) -> Option<usize> {
+        match self {
+            IrBaseType::Bit { width } | IrBaseType::SignedInt { width } => Some(*width),
+            _ => None,
+        }
+    }
+
+    /// Check if the type is, or contains, the poisoned type
+    pub fn is_poisoned(&self) -> bool {
+        match self {
+            IrBaseType::Poisoned => true,
+            IrBaseType::Struct { fields } | IrBaseType::Header { fields } => {
+                fields.iter().any(|(ty, _)| ty.is_poisoned())
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Display for IrBaseType {
+    /// Display the type as it would be written in P4
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IrBaseType::Bool => f.write_str("bool"),
+            IrBaseType::Int => f.write_str("int"),
+            IrBaseType::Bit { width } => write!(f, "bit<{}>", width),
+            IrBaseType::SignedInt { width } => write!(f, "int<{}>", width),
+            IrBaseType::String => f.write_str("string"),
+            IrBaseType::Error => f.write_str("error"),
+            IrBaseType::MatchKind => f.write_str("match_kind"),
+            IrBaseType::Enum { name, .. } => f.write_str(name),
+            _ if self.is_void() => f.write_str("void"),
+            IrBaseType::Struct { fields } | IrBaseType::Header { fields } => {
+                let keyword = match self {
+                    IrBaseType::Header { .. } => "header",
+                    _ => "struct",
+                };
+                write!(f, "{} {{", keyword)?;
+
+                for (ty, name) in fields {
+                    write!(f, " {} {};", ty, name)?;
+                }
+
+                f.write_str(" }")
+            }
+            IrBaseType::PacketIn => f.write_str("packet_in"),
+            IrBaseType::TyVar(id) => write!(f, "T{}", id.0),
+            IrBaseType::Poisoned => f.write_str("{unknown}"),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
@@ -82,12 +189,25 @@ pub struct IrConstructorType {
 /****************************** Nodes ******************************/
 // Note: node types are sorted alphabetically
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct IrActionDecl {
     pub ty: IrFunctionType,
     pub id: VariableId,
     pub params: Vec<IrParam>,
     pub body: IrBlockStatement,
+    pub span: Span,
+}
+
+impl Display for IrActionDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "action {}({}) {}",
+            self.id.1,
+            CommaSeparated(&self.params),
+            self.body
+        )
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -97,22 +217,77 @@ pub enum IrArgument {
     DontCare,
 }
 
+impl Display for IrArgument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IrArgument::Value(value) => Display::fmt(value, f),
+            IrArgument::Named(param, value) => write!(f, "{} = {}", param.1, value),
+            IrArgument::DontCare => f.write_str("_"),
+        }
+    }
+}
+
+/// A call to the built-in `assert`, which is a bug if the condition is false
+#[derive(Clone, Debug)]
+pub struct IrAssert {
+    pub condition: IrExpr,
+    pub span: Span,
+}
+
+impl Display for IrAssert {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "assert({});", self.condition)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct IrAssignment {
     pub lvalue: IrLValue,
     pub value: IrExpr,
+    pub span: Span,
+}
+
+impl Display for IrAssignment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {};", self.lvalue, self.value)
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct IrBlockStatement(pub Vec<IrStatementOrDecl>);
 
+impl Display for IrBlockStatement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_block(f, &self.0)
+    }
+}
+
 #[derive(Debug)]
 pub struct IrControlDecl {
     // todo: store type ID
-    // pub name: String,
+    pub name: String,
     pub params: Vec<IrParam>,
     pub local_decls: Vec<IrControlLocalDecl>,
     pub apply_body: IrBlockStatement,
+    pub span: Span,
+}
+
+impl Display for IrControlDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "control {}({}) {{",
+            self.name,
+            CommaSeparated(&self.params)
+        )?;
+
+        for local_decl in &self.local_decls {
+            writeln!(Indented::new(&mut *f), "{}", local_decl)?;
+        }
+
+        writeln!(Indented::new(&mut *f), "apply {}", self.apply_body)?;
+        f.write_str("}")
+    }
 }
 
 #[derive(Debug)]
@@ -123,36 +298,150 @@ pub enum IrControlLocalDecl {
     Table(IrTableDecl),
 }
 
+impl Display for IrControlLocalDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IrControlLocalDecl::Variable(var_decl) => Display::fmt(var_decl, f),
+            IrControlLocalDecl::Instantiation(instantiation) => Display::fmt(instantiation, f),
+            IrControlLocalDecl::Action(action) => Display::fmt(action, f),
+            IrControlLocalDecl::Table(table) => Display::fmt(table, f),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum IrDeclaration {
     Control(IrControlDecl),
+    Parser(IrParserDecl),
     Constant(Box<IrVariableDecl>),
     Instantiation(IrInstantiation),
 }
 
+impl Display for IrDeclaration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IrDeclaration::Control(control_decl) => Display::fmt(control_decl, f),
+            IrDeclaration::Parser(parser_decl) => Display::fmt(parser_decl, f),
+            IrDeclaration::Constant(constant) => Display::fmt(constant, f),
+            IrDeclaration::Instantiation(instantiation) => Display::fmt(instantiation, f),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct IrExpr {
     pub ty: IrType,
     pub data: IrExprData,
+    pub span: Span,
+}
+
+impl Display for IrExpr {
+    /// Display the expression as P4 code. Operands which are operations are
+    /// put in parentheses, so the precedence of the operators doesn't matter.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.data {
+            IrExprData::Bool(value) => Display::fmt(value, f),
+            IrExprData::Int(value) => match &self.ty {
+                IrType::Base(IrBaseType::Bit { width }) => write!(f, "{}w{}", width, value),
+                IrType::Base(IrBaseType::SignedInt { width }) if value.sign() == Sign::Minus => {
+                    write!(f, "-{}s{}", width, value.magnitude())
+                }
+                IrType::Base(IrBaseType::SignedInt { width }) => write!(f, "{}s{}", width, value),
+                _ => Display::fmt(value, f),
+            },
+            IrExprData::Var(id) => f.write_str(&id.1),
+            IrExprData::And(left, right) => {
+                write!(f, "{} && {}", IrOperand(left), IrOperand(right))
+            }
+            IrExprData::Or(left, right) => {
+                write!(f, "{} || {}", IrOperand(left), IrOperand(right))
+            }
+            IrExprData::Negation(inner) => write!(f, "!{}", IrOperand(inner)),
+            IrExprData::BinOp(op, left, right) => {
+                write!(f, "{} {} {}", IrOperand(left), op, IrOperand(right))
+            }
+            IrExprData::UnOp(op, inner) => write!(f, "{}{}", op, IrOperand(inner)),
+            IrExprData::FunctionCall(call) => Display::fmt(call, f),
+            IrExprData::MethodCall(call) => Display::fmt(call, f),
+            IrExprData::FieldAccess(target, field) => {
+                write!(f, "{}.{}", IrOperand(target), field)
+            }
+            IrExprData::Struct(fields) => {
+                let fields: Vec<_> = fields
+                    .iter()
+                    .map(|(name, value)| format!("{} = {}", name, value))
+                    .collect();
+                write!(f, "{{ {} }}", CommaSeparated(&fields))
+            }
+        }
+    }
+}
+
+/// An operand of an operator, which is put in parentheses if it is an
+/// operation itself
+struct IrOperand<'a>(&'a IrExpr);
+
+impl Display for IrOperand<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.0.data {
+            IrExprData::And(..)
+            | IrExprData::Or(..)
+            | IrExprData::Negation(_)
+            | IrExprData::BinOp(..)
+            | IrExprData::UnOp(..) => write!(f, "({})", self.0),
+            _ => Display::fmt(self.0, f),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum IrExprData {
     Bool(bool),
+    Int(BigInt),
     Var(VariableId),
     And(Box<IrExpr>, Box<IrExpr>),
     Or(Box<IrExpr>, Box<IrExpr>),
     Negation(Box<IrExpr>),
+    BinOp(BinOp, Box<IrExpr>, Box<IrExpr>),
+    UnOp(UnOp, Box<IrExpr>),
     FunctionCall(IrFunctionCall),
+    MethodCall(IrMethodCall),
     FieldAccess(Box<IrExpr>, String),
     Struct(Vec<(String, IrExpr)>),
 }
 
+impl IrExpr {
+    /// Get the lvalue this expression refers to, if it is a variable or a
+    /// field of one.
+    pub fn as_lvalue(&self) -> Option<IrLValue> {
+        let data = match &self.data {
+            IrExprData::Var(id) => IrLValueData::Var(id.clone()),
+            IrExprData::FieldAccess(target, field) => {
+                IrLValueData::Field(Box::new(target.as_lvalue()?), field.clone())
+            }
+            _ => return None,
+        };
+
+        Some(IrLValue {
+            ty: self.ty.clone(),
+            data,
+            span: self.span,
+        })
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct IrFunctionCall {
     pub result_ty: IrBaseType,
     pub target: VariableId,
     pub arguments: Vec<IrArgument>,
+    pub span: Span,
+}
+
+impl Display for IrFunctionCall {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.target.1, CommaSeparated(&self.arguments))
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -160,6 +449,19 @@ pub struct IrIfStatement {
     pub condition: IrExpr,
     pub then_case: IrBlockStatement,
     pub else_case: Option<IrBlockStatement>,
+    pub span: Span,
+}
+
+impl Display for IrIfStatement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "if ({}) {}", self.condition, self.then_case)?;
+
+        if let Some(else_case) = &self.else_case {
+            write!(f, " else {}", else_case)?;
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -169,16 +471,51 @@ pub struct IrInstantiation {
     pub id: VariableId,
 }
 
-#[derive(Debug)]
+impl Display for IrInstantiation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}({}) {};",
+            self.ty,
+            CommaSeparated(&self.args),
+            self.id.1
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct IrKeyElement {
     pub expr: IrExpr,
     pub match_kind: String,
 }
 
+impl Display for IrKeyElement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {};", self.expr, self.match_kind)
+    }
+}
+
+#[derive(Debug)]
+pub enum IrKeyset {
+    Value(IrExpr),
+    /// Matches any value
+    Default,
+}
+
+impl Display for IrKeyset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IrKeyset::Value(value) => Display::fmt(value, f),
+            IrKeyset::Default => f.write_str("default"),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct IrLValue {
     pub ty: IrType,
     pub data: IrLValueData,
+    pub span: Span,
 }
 
 impl IrLValue {
@@ -190,17 +527,132 @@ impl IrLValue {
     }
 }
 
+impl Display for IrLValue {
+    /// Display the lvalue as it was written in the source code
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.data {
+            IrLValueData::Var(id) => f.write_str(&id.1),
+            IrLValueData::Field(target, field) => write!(f, "{}.{}", target, field),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum IrLValueData {
     Var(VariableId),
     Field(Box<IrLValue>, String),
 }
 
+/// A call to a built-in method
+#[derive(Clone, Debug)]
+pub enum IrMethodCall {
+    /// `header.isValid()`
+    IsValid(IrLValue),
+    /// `header.setValid()`
+    SetValid(IrLValue),
+    /// `header.setInvalid()`
+    SetInvalid(IrLValue),
+    /// `packet.extract(header)`
+    Extract(IrLValue),
+    /// `table.apply()`, and the span of the call
+    Apply(VariableId, Span),
+}
+
+impl Display for IrMethodCall {
+    /// Display the method call as P4 code. The IR doesn't keep the packet
+    /// which headers are extracted from, so extracts are written as calls.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IrMethodCall::IsValid(header) => write!(f, "{}.isValid()", header),
+            IrMethodCall::SetValid(header) => write!(f, "{}.setValid()", header),
+            IrMethodCall::SetInvalid(header) => write!(f, "{}.setInvalid()", header),
+            IrMethodCall::Extract(header) => write!(f, "extract({})", header),
+            IrMethodCall::Apply(table, _) => write!(f, "{}.apply()", table.1),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct IrParam {
     pub direction: Direction,
     pub ty: IrBaseType,
     pub id: VariableId,
+    pub span: Span,
+}
+
+impl Display for IrParam {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.direction {
+            Direction::Directionless => write!(f, "{} {}", self.ty, self.id.1),
+            direction => write!(f, "{} {} {}", direction, self.ty, self.id.1),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct IrParserDecl {
+    pub name: String,
+    pub params: Vec<IrParam>,
+    pub local_decls: Vec<IrParserLocalDecl>,
+    pub states: Vec<IrParserState>,
+    pub start_state: VariableId,
+    pub span: Span,
+}
+
+impl Display for IrParserDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "parser {}({}) {{",
+            self.name,
+            CommaSeparated(&self.params)
+        )?;
+
+        for local_decl in &self.local_decls {
+            writeln!(Indented::new(&mut *f), "{}", local_decl)?;
+        }
+        for state in &self.states {
+            writeln!(Indented::new(&mut *f), "{}", state)?;
+        }
+
+        f.write_str("}")
+    }
+}
+
+#[derive(Debug)]
+pub enum IrParserLocalDecl {
+    Variable(IrVariableDecl),
+    Instantiation(IrInstantiation),
+}
+
+impl Display for IrParserLocalDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IrParserLocalDecl::Variable(var_decl) => Display::fmt(var_decl, f),
+            IrParserLocalDecl::Instantiation(instantiation) => Display::fmt(instantiation, f),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct IrParserState {
+    pub id: VariableId,
+    pub body: IrBlockStatement,
+    pub transition: IrTransition,
+    pub span: Span,
+}
+
+impl Display for IrParserState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "state {} {{", self.id.1)?;
+
+        for statement in &self.body.0 {
+            writeln!(Indented::new(&mut *f), "{}", statement)?;
+        }
+
+        writeln!(Indented::new(&mut *f), "transition {}", self.transition)?;
+        f.write_str("}")
+    }
 }
 
 #[derive(Debug)]
@@ -208,12 +660,70 @@ pub struct IrProgram {
     pub declarations: Vec<IrDeclaration>,
 }
 
+impl Display for IrProgram {
+    /// Display the program as P4 code, with a blank line between declarations
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, declaration) in self.declarations.iter().enumerate() {
+            if i != 0 {
+                f.write_str("\n")?;
+            }
+
+            writeln!(f, "{}", declaration)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct IrSelectCase {
+    pub keysets: Vec<IrKeyset>,
+    pub target: IrTransitionTarget,
+}
+
+impl Display for IrSelectCase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.keysets.as_slice() {
+            [keyset] => write!(f, "{}: {};", keyset, self.target),
+            keysets => write!(f, "({}): {};", CommaSeparated(keysets), self.target),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct IrSelectExpr {
+    pub keys: Vec<IrExpr>,
+    pub cases: Vec<IrSelectCase>,
+}
+
+impl Display for IrSelectExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "select({}) ", CommaSeparated(&self.keys))?;
+        write_block(f, &self.cases)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum IrStatement {
     Block(IrBlockStatement),
     If(IrIfStatement),
     Assignment(IrAssignment),
     FunctionCall(IrFunctionCall),
+    MethodCall(IrMethodCall),
+    Assert(IrAssert),
+}
+
+impl Display for IrStatement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IrStatement::Block(block) => Display::fmt(block, f),
+            IrStatement::If(if_statement) => Display::fmt(if_statement, f),
+            IrStatement::Assignment(assignment) => Display::fmt(assignment, f),
+            IrStatement::FunctionCall(call) => write!(f, "{};", call),
+            IrStatement::MethodCall(call) => write!(f, "{};", call),
+            IrStatement::Assert(assert) => Display::fmt(assert, f),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -223,16 +733,88 @@ pub enum IrStatementOrDecl {
     Instantiation(IrInstantiation),
 }
 
-#[derive(Debug)]
+impl Display for IrStatementOrDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IrStatementOrDecl::Statement(statement) => Display::fmt(statement, f),
+            IrStatementOrDecl::VariableDecl(var_decl) => Display::fmt(var_decl, f),
+            IrStatementOrDecl::Instantiation(instantiation) => Display::fmt(instantiation, f),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct IrTableDecl {
     pub id: VariableId,
     pub properties: Vec<IrTableProperty>,
+    pub span: Span,
 }
 
-#[derive(Debug)]
+impl Display for IrTableDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "table {} ", self.id.1)?;
+        write_block(f, &self.properties)
+    }
+}
+
+#[derive(Clone, Debug)]
 pub enum IrTableProperty {
     Key(Vec<IrKeyElement>),
     Actions(Vec<VariableId>),
+    DefaultAction(IrFunctionCall),
+}
+
+impl Display for IrTableProperty {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IrTableProperty::Key(keys) => {
+                f.write_str("key = ")?;
+                write_block(f, keys)
+            }
+            IrTableProperty::Actions(actions) => {
+                let actions: Vec<_> = actions
+                    .iter()
+                    .map(|action| format!("{};", action.1))
+                    .collect();
+                f.write_str("actions = ")?;
+                write_block(f, &actions)
+            }
+            IrTableProperty::DefaultAction(call) => write!(f, "default_action = {};", call),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum IrTransition {
+    Direct(IrTransitionTarget),
+    Select(IrSelectExpr),
+}
+
+impl Display for IrTransition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IrTransition::Direct(target) => write!(f, "{};", target),
+            IrTransition::Select(select) => Display::fmt(select, f),
+        }
+    }
+}
+
+/// The state a parser transitions to
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum IrTransitionTarget {
+    State(VariableId),
+    Accept,
+    Reject,
+}
+
+impl Display for IrTransitionTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IrTransitionTarget::State(state) => f.write_str(&state.1),
+            IrTransitionTarget::Accept => f.write_str("accept"),
+            IrTransitionTarget::Reject => f.write_str("reject"),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -241,4 +823,20 @@ pub struct IrVariableDecl {
     pub id: VariableId,
     pub value: Option<IrExpr>,
     pub is_const: bool,
+    pub span: Span,
+}
+
+impl Display for IrVariableDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_const {
+            f.write_str("const ")?;
+        }
+        write!(f, "{} {}", self.ty, self.id.1)?;
+
+        if let Some(value) = &self.value {
+            write!(f, " = {}", value)?;
+        }
+
+        f.write_str(";")
+    }
 }
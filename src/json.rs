@@ -0,0 +1,173 @@
+//! Machine-readable JSON versions of analysis results
+
+use crate::analysis::{AnalysisError, AnalysisReport, AnalyzedFile, Bug, Reachability};
+use crate::diagnostics::Diagnostic;
+use crate::gcl::BugKind;
+use crate::span::{LineIndex, Span};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Convert the results of analyzing each file to JSON
+pub fn files_to_json(files: &[AnalyzedFile]) -> Value {
+    json!({ "files": files.iter().map(file_to_json).collect::<Vec<_>>() })
+}
+
+/// Convert the results of analyzing a file to JSON. The source code is used
+/// to find the line and column of each span.
+pub fn file_to_json(file: &AnalyzedFile) -> Value {
+    let line_index = LineIndex::new(&file.source);
+    let mut value = match &file.result {
+        Ok(report) => report_to_json(report, &line_index),
+        Err(error) => error_to_json(error, &line_index),
+    };
+    value["file"] = json!(file.name);
+
+    value
+}
+
+fn report_to_json(report: &AnalysisReport, line_index: &LineIndex) -> Value {
+    let timings = &report.timings;
+
+    json!({
+        "bugs": report
+            .bugs
+            .iter()
+            .map(|bug| bug_to_json(bug, line_index))
+            .collect::<Vec<_>>(),
+        "unroll_bound_exceeded": report.unroll_bound_exceeded,
+        "timings_ms": {
+            "parse": millis(timings.parse),
+            "type_check": millis(timings.type_check),
+            "to_gcl": millis(timings.to_gcl),
+            "unroll": millis(timings.unroll),
+            "optimize_gcl": millis(timings.optimize_gcl),
+            "reachability_predicates": millis(timings.reachability_predicates),
+            "calculate_reachability": millis(timings.calculate_reachability),
+            "total": millis(timings.total),
+        },
+    })
+}
+
+/// The errors which stopped the analysis, along with the stage they
+/// happened in
+fn error_to_json(error: &AnalysisError, line_index: &LineIndex) -> Value {
+    let stage = match error {
+        AnalysisError::Parse(_) => "parse",
+        AnalysisError::TypeCheck(_) => "type_check",
+        AnalysisError::Read(_) => "read",
+        AnalysisError::Solver(_) => "solver",
+    };
+
+    json!({
+        "stage": stage,
+        "errors": error
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic_to_json(diagnostic, line_index))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn bug_to_json(bug: &Bug, line_index: &LineIndex) -> Value {
+    json!({
+        "kind": bug_kind_name(bug.kind),
+        "message": bug.message,
+        "status": bug_status(bug),
+        "variable": bug.variable,
+        "location": span_to_json(bug.span, line_index),
+        "path": bug.path.as_ref().map(|path| {
+            path.iter()
+                .map(|step| {
+                    json!({
+                        "name": step.name,
+                        "location": step.span.map(|span| span_to_json(span, line_index)),
+                    })
+                })
+                .collect::<Vec<_>>()
+        }),
+        "counterexample": {
+            "values": bug
+                .counterexample
+                .values
+                .iter()
+                .map(|entry| json!({ "name": entry.name, "value": entry.value }))
+                .collect::<Vec<_>>(),
+            "table_actions": bug
+                .counterexample
+                .table_actions
+                .iter()
+                .map(|table_action| {
+                    json!({
+                        "table": table_action.table,
+                        "action": table_action.action,
+                        "location": span_to_json(table_action.span, line_index),
+                        "call_location": span_to_json(table_action.call_span, line_index),
+                        "iteration": table_action.iteration,
+                    })
+                })
+                .collect::<Vec<_>>(),
+        },
+    })
+}
+
+fn diagnostic_to_json(diagnostic: &Diagnostic, line_index: &LineIndex) -> Value {
+    json!({
+        "message": diagnostic.message,
+        "location": diagnostic.span.map(|span| span_to_json(span, line_index)),
+        "notes": diagnostic
+            .notes
+            .iter()
+            .map(|note| {
+                json!({
+                    "message": note.message,
+                    "location": note.span.map(|span| span_to_json(span, line_index)),
+                })
+            })
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// The location of a span, as byte offsets and the 1-based line and column
+/// of its start
+fn span_to_json(span: Span, line_index: &LineIndex) -> Value {
+    let (line, column) = line_index.line_col(span.start);
+
+    json!({
+        "start": span.start,
+        "end": span.end,
+        "line": line,
+        "column": column,
+    })
+}
+
+/// A stable identifier for the kind of bug
+pub fn bug_kind_name(kind: BugKind) -> &'static str {
+    match kind {
+        BugKind::UninitializedRead => "uninitialized_read",
+        BugKind::InvalidHeaderAccess => "invalid_header_access",
+        BugKind::UnsetOutParam => "unset_out_param",
+        BugKind::UserAssert => "assertion_failure",
+    }
+}
+
+/// A stable identifier for the result of a reachability check
+/// The bug's reachability, or "possible" if it is reachable but running the
+/// counterexample does not confirm it
+fn bug_status(bug: &Bug) -> &'static str {
+    match bug.reachability {
+        Reachability::Reachable if !bug.is_confirmed() => "possible",
+        reachability => reachability_name(reachability),
+    }
+}
+
+pub fn reachability_name(reachability: Reachability) -> &'static str {
+    match reachability {
+        Reachability::Reachable => "reachable",
+        Reachability::Unreachable => "unreachable",
+        Reachability::Unknown => "unknown",
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
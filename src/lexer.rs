@@ -3,7 +3,9 @@
 //! P4 grammar is ambiguous:
 //! https://p4.org/p4-spec/docs/P4-16-v1.2.1.html#sec-grammar
 
+use crate::ast::IntLiteral;
 use logos::{Lexer, Logos};
+use num_bigint::BigInt;
 use std::cell::RefCell;
 use std::collections::HashSet;
 use std::ops::Range;
@@ -11,6 +13,14 @@ use std::ops::Range;
 /// The lexer state stores the names of previously declared types
 pub type LexerState<'input> = &'input RefCell<HashSet<String>>;
 
+/// Types which are available without being declared
+pub const BUILTIN_TYPES: &[&str] = &["packet_in"];
+
+/// Create the initial lexer state, which knows about the built-in types
+pub fn new_lexer_state() -> RefCell<HashSet<String>> {
+    RefCell::new(BUILTIN_TYPES.iter().map(|ty| ty.to_string()).collect())
+}
+
 #[derive(Logos, Clone, Debug, PartialEq)]
 #[logos(extras = LexerState<'s>)]
 pub enum Token<'input> {
@@ -40,16 +50,68 @@ pub enum Token<'input> {
     AndAnd,
     #[token("||")]
     OrOr,
+    #[token("<")]
+    LessThan,
+    #[token(">")]
+    GreaterThan,
+    #[token("<=")]
+    LessThanEquals,
+    #[token(">=")]
+    GreaterThanEquals,
+    #[token("==")]
+    EqualsEquals,
+    #[token("!=")]
+    NotEquals,
+    #[token("+")]
+    Plus,
+    #[token("-")]
+    Minus,
+    #[token("*")]
+    Star,
+    #[token("/")]
+    Slash,
+    #[token("%")]
+    Percent,
+    #[token("&")]
+    Ampersand,
+    #[token("|")]
+    Pipe,
+    #[token("^")]
+    Caret,
+    #[token("~")]
+    Tilde,
+    #[token("<<")]
+    ShiftLeft,
+    #[token(">>")]
+    ShiftRight,
+    #[token("|+|")]
+    SaturatingPlus,
+    #[token("|-|")]
+    SaturatingMinus,
     #[token("struct")]
     Struct,
+    #[token("header")]
+    Header,
     #[token("control")]
     Control,
+    #[token("parser")]
+    Parser,
+    #[token("state")]
+    State,
+    #[token("transition")]
+    Transition,
+    #[token("select")]
+    Select,
+    #[token("default")]
+    Default,
     #[token("table")]
     Table,
     #[token("key")]
     Key,
     #[token("actions")]
     Actions,
+    #[token("default_action")]
+    DefaultAction,
     #[token("apply")]
     Apply,
     #[token("action")]
@@ -74,6 +136,15 @@ pub enum Token<'input> {
     Bool,
     #[token("string")]
     String,
+    #[token("bit")]
+    Bit,
+    #[token("int")]
+    Int,
+    #[regex(
+        r"([0-9]+[ws]-?)?(0[xX][0-9a-fA-F_]+|0[oO][0-7_]+|0[bB][01_]+|0[dD][0-9_]+|[0-9][0-9_]*)",
+        parse_int_literal
+    )]
+    Integer(IntLiteral),
     // The boolean specifies if the identifier is a type identifier
     #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*", callback = is_type_ident)]
     Identifier((&'input str, bool)),
@@ -92,6 +163,54 @@ fn is_type_ident<'input>(lexer: &mut Lexer<'input, Token<'input>>) -> (&'input s
     (lexer.slice(), lexer.extras.borrow().contains(lexer.slice()))
 }
 
+/// Parse an integer literal, which may have a width and signedness prefix
+/// (ex. `8w` or `16s`) and a base prefix (ex. `0x`).
+fn parse_int_literal<'input>(lexer: &mut Lexer<'input, Token<'input>>) -> Option<IntLiteral> {
+    let mut literal = lexer.slice();
+
+    // Check for a width prefix
+    let prefix_len = literal.find(|c: char| !c.is_ascii_digit());
+    let (width, signed) = match prefix_len.map(|len| (len, literal.as_bytes()[len])) {
+        Some((len, sign @ b'w')) | Some((len, sign @ b's')) => {
+            let width = literal[..len].parse().ok()?;
+            literal = &literal[len + 1..];
+            (Some(width), sign == b's')
+        }
+        _ => (None, false),
+    };
+
+    let is_negative = literal.starts_with('-');
+    let literal = literal.trim_start_matches('-');
+
+    // Check for a base prefix
+    let (radix, digits) = match literal.get(..2) {
+        Some("0x") | Some("0X") => (16, &literal[2..]),
+        Some("0o") | Some("0O") => (8, &literal[2..]),
+        Some("0b") | Some("0B") => (2, &literal[2..]),
+        Some("0d") | Some("0D") => (10, &literal[2..]),
+        _ => (10, literal),
+    };
+    let digits: String = digits.chars().filter(|c| *c != '_').collect();
+    let value = BigInt::parse_bytes(digits.as_bytes(), radix)?;
+
+    Some(IntLiteral {
+        value: if is_negative { -value } else { value },
+        width,
+        signed,
+    })
+}
+
+/// Describe a token name from the grammar (as found in a parser error's list
+/// of expected tokens) in human terms
+pub fn describe_token_name(name: &str) -> String {
+    match name.trim_matches('"') {
+        "IDENT" => "an identifier".to_string(),
+        "TY_IDENT" => "a type name".to_string(),
+        "INTEGER" => "an integer".to_string(),
+        token => format!("`{}`", token),
+    }
+}
+
 pub struct LalrpopLexerIter<'input> {
     pub lexer: Lexer<'input, Token<'input>>,
 }
@@ -106,15 +225,11 @@ impl<'input> Iterator for LalrpopLexerIter<'input> {
     type Item = Result<(usize, Token<'input>, usize), Range<usize>>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.lexer.next() {
-            Some(token) => {
-                let span = self.lexer.span();
-                Some(match token {
-                    Token::Error => Err(span),
-                    token => Ok((span.start, token, span.end)),
-                })
-            }
-            None => None,
-        }
+        // Invalid tokens are passed to the parser as Token::Error, so it can
+        // recover from them like other syntax errors
+        let token = self.lexer.next()?;
+        let span = self.lexer.span();
+
+        Some(Ok((span.start, token, span.end)))
     }
 }
@@ -0,0 +1,52 @@
+//! Static analysis of P4 programs. The program is parsed, type checked and
+//! converted to a GCL-inspired control flow graph, then an SMT solver (Z3 by
+//! default) is used to check if any of the bug nodes in the graph are
+//! reachable.
+//!
+//! Most users only need [`analyze`], which runs the whole pipeline. The
+//! individual stages ([`parse`], [`run_type_checking`], [`ToGcl`] and
+//! [`GclGraph::to_reachability_predicates`](gcl::GclGraph::to_reachability_predicates))
+//! are also available.
+
+#[macro_use]
+extern crate lalrpop_util;
+
+pub mod analysis;
+pub mod ast;
+pub mod diagnostics;
+mod dot;
+pub mod emit;
+mod external_solver;
+pub mod gcl;
+#[cfg(feature = "z3")]
+mod generate_z3_types;
+pub mod ir;
+pub mod json;
+mod lexer;
+pub mod optimizations;
+mod parser;
+mod pretty;
+pub mod sarif;
+mod smtlib;
+pub mod solver;
+pub mod span;
+pub mod to_gcl;
+pub mod to_predicates;
+#[cfg(feature = "z3")]
+mod to_z3;
+pub mod type_checker;
+pub mod unroll;
+#[cfg(feature = "z3")]
+mod z3_solver;
+
+lalrpop_mod!(
+    #[allow(clippy::all)]
+    p4_parser
+);
+
+pub use crate::analysis::{
+    analyze, AnalysisError, AnalysisOptions, AnalysisReport, AnalyzedFile, Bug,
+};
+pub use crate::parser::parse;
+pub use crate::to_gcl::ToGcl;
+pub use crate::type_checker::run_type_checking;
@@ -1,355 +1,474 @@
-#[macro_use]
-extern crate lalrpop_util;
-
-use crate::ast::Program;
-use crate::gcl::{GclExpr, GclGraph, GclNode};
-use crate::generate_z3_types::{generate_types, Z3TypeMap};
-use crate::lexer::{LalrpopLexerIter, Token};
-use crate::optimizations::merge_simple_edges;
-use crate::to_gcl::ToGcl;
-use crate::to_predicates::{PredicateMap, VariableMap};
-use crate::type_checker::run_type_checking;
 use env_logger::Env;
-use lalrpop_util::ParseError;
-use logos::Logos;
-use petgraph::dot::Dot;
-use petgraph::graph::NodeIndex;
-use petgraph::visit::IntoNodeReferences;
-use std::cell::RefCell;
-use std::collections::HashMap;
+use p4_analyzer::analysis::{Counterexample, Reachability};
+use p4_analyzer::diagnostics::Diagnostic;
+use p4_analyzer::emit::{emit, smt2_queries, EmitStage};
+use p4_analyzer::solver::SolverKind;
+use p4_analyzer::span::LineIndex;
+use p4_analyzer::{analyze, AnalysisError, AnalysisOptions, AnalysisReport, AnalyzedFile};
+use p4_analyzer::{json, sarif};
 use std::io::{Read, Write};
-use std::ops::Deref;
-use std::time::Instant;
-use z3::ast::Bool;
-use z3::{Config, Context, Model, SatResult, Solver};
-
-mod ast;
-mod gcl;
-mod generate_z3_types;
-mod ir;
-mod lexer;
-mod optimizations;
-mod to_gcl;
-mod to_predicates;
-mod type_checker;
-mod to_z3;
-
-lalrpop_mod!(
-    #[allow(clippy::all)]
-    p4_parser
-);
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use structopt::StructOpt;
+
+/// Static analysis tool which checks P4 code for bugs
+#[derive(StructOpt)]
+struct Args {
+    /// The P4 programs to analyze. Use "-" (or no files) to read from stdin.
+    #[structopt(parse(from_os_str), value_name = "FILE")]
+    files: Vec<PathBuf>,
+
+    /// Check the reachability of every node instead of just the bug nodes
+    #[structopt(long)]
+    full_reachability: bool,
+
+    /// How many times loops are unrolled [default: 4]
+    #[structopt(long, value_name = "N")]
+    unroll_bound: Option<usize>,
+
+    /// Check reachability with a solver program which reads SMT-LIB2 from
+    /// stdin, instead of the Z3 library. The program is searched for on PATH,
+    /// for example: --solver "cvc5 --incremental"
+    #[structopt(long, value_name = "COMMAND")]
+    solver: Option<String>,
+
+    /// Give up on checking a node after this many milliseconds. Bugs which
+    /// the solver gives up on are reported as possible bugs.
+    #[structopt(long, value_name = "MS")]
+    timeout: Option<u64>,
+
+    /// How many threads check reachability at once [default: 1]
+    #[structopt(short, long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// How the results are shown: text, json or sarif
+    #[structopt(long, default_value = "text", value_name = "FORMAT")]
+    format: OutputFormat,
+
+    /// Write out an intermediate stage instead of checking for bugs: ast, ir,
+    /// gcl, gcl-optimized, predicates, smt2 or dot
+    #[structopt(long, value_name = "STAGE")]
+    emit: Option<EmitStage>,
+
+    /// Write the control flow graph of each program to a Graphviz file
+    #[structopt(long, parse(from_os_str), value_name = "PATH")]
+    dot: Option<PathBuf>,
+
+    /// Group the nodes in the Graphviz file by control, action and parser
+    /// state
+    #[structopt(long, requires = "dot")]
+    dot_clusters: bool,
+
+    /// Where to write the emitted stage [default: stdout]. If the stage is
+    /// smt2 and this is a directory, each script is written to its own file.
+    #[structopt(
+        short,
+        long,
+        parse(from_os_str),
+        value_name = "PATH",
+        requires = "emit"
+    )]
+    output: Option<PathBuf>,
+}
+
+/// How the results of the analysis are shown
+#[derive(Copy, Clone, PartialEq)]
+enum OutputFormat {
+    /// Human-readable logs
+    Text,
+    Json,
+    Sarif,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "sarif" => Ok(OutputFormat::Sarif),
+            _ => Err(format!(
+                "unknown format `{}`, expected text, json or sarif",
+                s
+            )),
+        }
+    }
+}
+
+/// The exit codes, from least to most severe
+const EXIT_CLEAN: i32 = 0;
+const EXIT_BUGS_FOUND: i32 = 1;
+const EXIT_ERROR: i32 = 2;
 
 fn main() {
     env_logger::Builder::from_env(Env::default().default_filter_or("info"))
         .format(|buf, record| writeln!(buf, "[{}] {}", record.level(), record.args()))
         .init();
 
-    let args: Vec<String> = std::env::args().collect();
-    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let args = Args::from_args_safe().unwrap_or_else(|error| {
+        // Help and version messages are not errors
+        if error.use_stderr() {
+            eprintln!("{}", error.message);
+            std::process::exit(EXIT_ERROR);
+        }
 
-    // Only check reachability of bug nodes by default
-    let mut only_bugs = true;
+        println!("{}", error.message);
+        std::process::exit(EXIT_CLEAN);
+    });
 
-    match args.as_slice() {
-        [_, "--full-reachability"] => only_bugs = false,
-        [_] | [] => {}
-        [name, ..] => {
-            eprintln!("Usage: {} [--full-reachability]", name);
-            return;
+    let mut options = AnalysisOptions {
+        only_bugs: !args.full_reachability,
+        ..AnalysisOptions::default()
+    };
+    if let Some(unroll_bound) = args.unroll_bound {
+        options.unroll_bound = unroll_bound;
+    }
+    if let Some(timeout) = args.timeout {
+        options.timeout = Some(Duration::from_millis(timeout));
+    }
+    if let Some(jobs) = args.jobs {
+        options.jobs = jobs;
+    }
+    if let Some(command) = &args.solver {
+        options.solver =
+            SolverKind::External(command.split_whitespace().map(String::from).collect());
+    }
+
+    let mut paths = args.files.clone();
+    if paths.is_empty() {
+        paths.push(PathBuf::from("-"));
+    }
+
+    if let Some(stage) = args.emit {
+        let exit_code = emit_files(&paths, stage, args.output.as_deref(), &options);
+        std::process::exit(exit_code);
+    }
+
+    let mut files = Vec::new();
+    for path in &paths {
+        let file = analyze_file(path, &options);
+
+        // Text reports are shown as soon as they are ready
+        if args.format == OutputFormat::Text {
+            display_file(&file, &options, paths.len() > 1);
         }
+
+        files.push(file);
     }
 
-    // Read P4 program
-    let mut p4_program_str = String::new();
-    std::io::stdin()
-        .read_to_string(&mut p4_program_str)
-        .unwrap();
-
-    // Parse P4
-    let parse_start = Instant::now();
-    let p4_program = parse(&p4_program_str);
-    let time_to_parse = parse_start.elapsed();
-
-    // Type check P4
-    let type_checking_start = Instant::now();
-    let (p4_program_ir, metadata) = run_type_checking(&p4_program).unwrap();
-    let time_to_type_check = type_checking_start.elapsed();
-    log::trace!("After type checking: {:#?}", p4_program_ir);
-
-    // Convert to GCL
-    let gcl_start = Instant::now();
-    let mut graph = GclGraph::new();
-    let gcl_start_node = p4_program_ir.to_gcl(&mut graph, &metadata);
-    let time_to_gcl = gcl_start.elapsed();
-
-    // Optimize GCL
-    let gcl_optimize_start = Instant::now();
-    merge_simple_edges(&mut graph);
-    let time_to_optimize_gcl = gcl_optimize_start.elapsed();
-
-    // Calculate a reachability predicate for each node
-    let reachability_start = Instant::now();
-    let (node_predicates, node_variables) = graph.to_reachability_predicates();
-    let time_to_reachability = reachability_start.elapsed();
-    display_node_vars(&graph, &node_variables);
-    display_reachability(&graph, &node_predicates);
-
-    // Convert predicates to Z3
-    let z3_convert_start = Instant::now();
-    let z3_config = Config::new();
-    let z3_context = Context::new(&z3_config);
-    let z3_types = generate_types(&metadata.types_in_order, &z3_context);
-    let z3_predicates = convert_to_z3(&graph, &node_predicates, &z3_context, &z3_types, only_bugs);
-    let time_to_convert_z3 = z3_convert_start.elapsed();
-
-    // Calculate reachability
-    let reachable_start = Instant::now();
-    let is_reachable = calculate_reachable(z3_predicates, &z3_context);
-    let time_to_reachable = reachable_start.elapsed();
-
-    // Print out the graphviz representation
-    let graphviz = make_graphviz(&graph, &is_reachable);
-    log::info!("{}", graphviz);
+    match args.format {
+        OutputFormat::Text => {}
+        OutputFormat::Json => println!("{}", json::files_to_json(&files)),
+        OutputFormat::Sarif => println!("{}", sarif::files_to_sarif(&files)),
+    }
 
-    // Show all reachable bugs
-    display_bugs(&graph, &is_reachable, gcl_start_node);
+    let mut exit_code = files
+        .iter()
+        .map(|file| match &file.result {
+            Ok(report) if report.bugs.is_empty() => EXIT_CLEAN,
+            Ok(_) => EXIT_BUGS_FOUND,
+            Err(_) => EXIT_ERROR,
+        })
+        .max()
+        .unwrap_or(EXIT_CLEAN);
+
+    if let Some(dot_path) = &args.dot {
+        // Each program gets its own graph in the file
+        let dot: String = files
+            .iter()
+            .filter_map(|file| {
+                let report = file.result.as_ref().ok()?;
+                Some(report.graphviz(&file.source, args.dot_clusters))
+            })
+            .collect();
+
+        if let Err(error) = std::fs::write(dot_path, dot) {
+            log::error!("Unable to write to {}: {}", dot_path.display(), error);
+            exit_code = EXIT_ERROR;
+        }
+    }
 
-    log::info!(
-        "Time to parse P4: {}ms\n\
-         Time to type check: {}ms\n\
-         Time to convert to GCL: {}ms\n\
-         Time to optimize GCL: {}ms\n\
-         Time to build reachability predicates: {}ms\n\
-         Time to convert to Z3: {}ms\n\
-         Time to calculate reachability: {}ms\n\
-         Total time: {}ms",
-        time_to_parse.as_millis(),
-        time_to_type_check.as_millis(),
-        time_to_gcl.as_millis(),
-        time_to_optimize_gcl.as_millis(),
-        time_to_reachability.as_millis(),
-        time_to_convert_z3.as_millis(),
-        time_to_reachable.as_millis(),
-        parse_start.elapsed().as_millis()
-    );
+    std::process::exit(exit_code);
 }
 
-fn display_reachability(graph: &GclGraph, node_preds: &PredicateMap) {
-    log::debug!("Reachability Predicates:");
-    for (node_idx, pred) in node_preds {
-        let node_name = &graph.node_weight(*node_idx).unwrap().name;
+/// Read the program at the path ("-" is stdin), returning the file's name
+/// and its contents
+fn read_file(path: &Path) -> (String, Result<String, AnalysisError>) {
+    let (name, source) = if path == Path::new("-") {
+        let mut source = String::new();
+        let read_result = std::io::stdin().read_to_string(&mut source);
+        ("<stdin>".to_string(), read_result.map(|_| source))
+    } else {
+        (path.display().to_string(), std::fs::read_to_string(path))
+    };
 
-        log::debug!("Node '{}': {}", node_name, pred);
-    }
+    let source = source.map_err(|error| {
+        let message = format!("unable to read `{}`: {}", name, error);
+        AnalysisError::Read(Diagnostic::error(message, None))
+    });
+
+    (name, source)
 }
 
-fn display_node_vars(graph: &GclGraph, node_vars: &VariableMap) {
-    log::trace!("Node Variables:");
-    let mut node_vars: Vec<_> = node_vars
-        .iter()
-        .map(|(node_idx, values)| (graph.node_weight(*node_idx).unwrap().name.as_str(), values))
-        .collect();
-    node_vars.sort_by_key(|(name, _)| *name);
-
-    for (node_name, vars) in node_vars {
-        log::trace!("Node '{}':", node_name);
-        for (var, values) in vars {
-            log::trace!(
-                "    {} = [{}]",
-                var,
-                values
-                    .iter()
-                    .map(|v| format!("{}", v))
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            );
+/// Read and analyze the program at the path
+fn analyze_file(path: &Path, options: &AnalysisOptions) -> AnalyzedFile {
+    let (name, source) = read_file(path);
+    let (source, result) = match source {
+        Ok(source) => {
+            let result = analyze(&source, options);
+            (source, result)
         }
+        Err(error) => (String::new(), Err(error)),
+    };
+
+    AnalyzedFile {
+        name,
+        source,
+        result,
     }
 }
 
-fn convert_to_z3<'ctx>(
-    graph: &GclGraph,
-    node_predicates: &HashMap<NodeIndex, GclExpr>,
-    context: &'ctx Context,
-    type_map: &Z3TypeMap<'ctx>,
-    only_bugs: bool,
-) -> HashMap<NodeIndex, Bool<'ctx>> {
-    graph
-        .node_references()
-        .filter_map(|(node_idx, node)| {
-            if only_bugs && !node.is_bug() {
-                return None;
+/// Write out the stage of each program to the output file (or stdout),
+/// returning the exit code. SMT-LIB2 scripts are written to separate files
+/// if the output is a directory.
+fn emit_files(
+    paths: &[PathBuf],
+    stage: EmitStage,
+    output_path: Option<&Path>,
+    options: &AnalysisOptions,
+) -> i32 {
+    if stage == EmitStage::Smt2 {
+        if let Some(output_dir) = output_path.filter(|path| path.is_dir()) {
+            return emit_smt2_files(paths, output_dir, options);
+        }
+    }
+
+    let mut output = String::new();
+    let mut exit_code = EXIT_CLEAN;
+
+    for path in paths {
+        match run_on_file(path, |source| emit(source, stage, options)) {
+            Some(stage_output) => output.push_str(&stage_output),
+            None => exit_code = EXIT_ERROR,
+        }
+    }
+
+    match output_path {
+        Some(output_path) => {
+            if let Err(error) = std::fs::write(output_path, output) {
+                log::error!("Unable to write to {}: {}", output_path.display(), error);
+                return EXIT_ERROR;
             }
+        }
+        None => print!("{}", output),
+    }
 
-            let pred = node_predicates.get(&node_idx).unwrap();
-            Some((
-                node_idx,
-                pred.as_z3_ast(&context, &type_map).as_bool().unwrap(),
-            ))
-        })
-        .collect()
+    exit_code
 }
 
-fn calculate_reachable<'ctx>(
-    z3_predicates: HashMap<NodeIndex, Bool<'ctx>>,
-    context: &'ctx Context,
-) -> HashMap<NodeIndex, Option<Model<'ctx>>> {
-    let solver = Solver::new(&context);
-
-    z3_predicates
-        .into_iter()
-        .map(|(node_idx, z3_pred)| {
-            let z3_result = solver.check_assumptions(&[z3_pred]);
-            if z3_result == SatResult::Sat {
-                let model = solver.get_model().unwrap();
-
-                (node_idx, Some(model))
-            } else {
-                (node_idx, None)
+/// Write each program's SMT-LIB2 scripts to the directory, one file per
+/// node (ex. `table.bug_51.smt2`), returning the exit code
+fn emit_smt2_files(paths: &[PathBuf], output_dir: &Path, options: &AnalysisOptions) -> i32 {
+    let mut exit_code = EXIT_CLEAN;
+
+    for path in paths {
+        let queries = match run_on_file(path, |source| smt2_queries(source, options)) {
+            Some(queries) => queries,
+            None => {
+                exit_code = EXIT_ERROR;
+                continue;
             }
-        })
-        .collect()
+        };
+        let file_stem = match path.file_stem() {
+            Some(file_stem) if path != Path::new("-") => file_stem.to_string_lossy(),
+            _ => "stdin".into(),
+        };
+
+        for query in queries {
+            let query_path = output_dir.join(format!("{}.{}.smt2", file_stem, query.node_name));
+            if let Err(error) = std::fs::write(&query_path, query.script) {
+                log::error!("Unable to write to {}: {}", query_path.display(), error);
+                exit_code = EXIT_ERROR;
+            }
+        }
+    }
+
+    exit_code
 }
 
-fn make_graphviz(graph: &GclGraph, is_reachable: &HashMap<NodeIndex, Option<Model>>) -> String {
-    let get_node_attributes = |_graph, (node_idx, node): (NodeIndex, &GclNode)| {
-        let color = match (node.is_bug(), is_reachable.get(&node_idx)) {
-            (true, Some(Some(_))) => "red",
-            (false, Some(Some(_))) => "green",
-            (_, Some(None)) => "grey",
-            (_, None) => "black",
-        };
+/// Read the program at the path and run the function on it. If either step
+/// fails, the errors are shown and `None` is returned.
+fn run_on_file<T>(path: &Path, f: impl FnOnce(&str) -> Result<T, AnalysisError>) -> Option<T> {
+    let (name, source) = read_file(path);
+    let result = source
+        .map_err(|error| (error, String::new()))
+        .and_then(|source| f(&source).map_err(|error| (error, source)));
+
+    match result {
+        Ok(value) => Some(value),
+        Err((error, source)) => {
+            display_errors(&error, &name, &source);
+            None
+        }
+    }
+}
+
+/// Show the results of analyzing the file as log messages. The file name is
+/// shown if there are multiple files.
+fn display_file(file: &AnalyzedFile, options: &AnalysisOptions, show_name: bool) {
+    if show_name {
+        log::info!("Analyzing {}", file.name);
+    }
 
-        format!("shape = box, color = {}", color)
+    let report = match &file.result {
+        Ok(report) => report,
+        Err(error) => {
+            display_errors(error, &file.name, &file.source);
+            return;
+        }
     };
-    let graphviz_graph = Dot::with_attr_getters(
-        graph.deref(),
-        &[],
-        &|_graph, _edge| String::new(),
-        &get_node_attributes,
-    );
 
-    graphviz_graph.to_string()
-}
+    // Show all reachable bugs
+    display_bugs(report, file);
+    display_unroll_bound_exceeded(report, options.unroll_bound);
 
-fn display_bugs(
-    graph: &GclGraph,
-    is_reachable: &HashMap<NodeIndex, Option<Model>>,
-    start_idx: NodeIndex,
-) {
-    let mut found_bug = false;
+    let timings = &report.timings;
+    log::info!(
+        "Time to parse P4: {}ms\n\
+         Time to type check: {}ms\n\
+         Time to convert to GCL: {}ms\n\
+         Time to unroll loops: {}ms\n\
+         Time to optimize GCL: {}ms\n\
+         Time to build reachability predicates: {}ms\n\
+         Time to calculate reachability: {}ms\n\
+         Total time: {}ms",
+        timings.parse.as_millis(),
+        timings.type_check.as_millis(),
+        timings.to_gcl.as_millis(),
+        timings.unroll.as_millis(),
+        timings.optimize_gcl.as_millis(),
+        timings.reachability_predicates.as_millis(),
+        timings.calculate_reachability.as_millis(),
+        timings.total.as_millis()
+    );
+}
 
-    for (node_idx, node) in graph.node_references() {
-        if !node.is_bug() {
+fn display_bugs(report: &AnalysisReport, file: &AnalyzedFile) {
+    let line_index = LineIndex::new(&file.source);
+
+    for bug in &report.bugs {
+        if bug.reachability == Reachability::Unknown {
+            log::warn!(
+                "Possible bug at {}:{}:{}: {} (could not be proven unreachable)",
+                file.name,
+                bug.line,
+                bug.column,
+                bug.message
+            );
             continue;
         }
 
-        let model = match is_reachable.get(&node_idx).unwrap_or(&None) {
-            Some(model) => model,
-            None => continue,
+        // The solver's inputs don't reach the bug when the program is run, so
+        // they aren't shown as a witness
+        let path = match &bug.path {
+            Some(path) => path,
+            None => {
+                log::warn!(
+                    "Possible bug at {}:{}:{}: {} (the counterexample does not reach it)\n\
+                     Possible counterexample:{}",
+                    file.name,
+                    bug.line,
+                    bug.column,
+                    bug.message,
+                    format_counterexample(&bug.counterexample, &line_index)
+                );
+                continue;
+            }
         };
 
-        found_bug = true;
-        let path = path_to(graph, start_idx, node_idx).map(|path| {
-            // Get the name of each node
-            path.into_iter()
-                .map(|node_idx| graph.node_weight(node_idx).unwrap().name.as_str())
-                .collect::<Vec<_>>()
-        });
+        // Get the name of each node, and its line if it has one
+        let path: Vec<_> = path
+            .iter()
+            .map(|step| match step.span {
+                Some(span) => format!("{}:{}", step.name, line_index.line_col(span.start).0),
+                None => step.name.clone(),
+            })
+            .collect();
         log::info!(
-            "Found bug: {:?}\nPath = {:?}\nModel = {}",
-            node,
+            "Found bug at {}:{}:{}: {}\nPath = {:?}\nCounterexample:{}",
+            file.name,
+            bug.line,
+            bug.column,
+            bug.message,
             path,
-            model
+            format_counterexample(&bug.counterexample, &line_index)
         );
     }
 
-    if !found_bug {
+    if report.bugs.is_empty() {
         log::info!("No bugs found!");
     }
 }
 
-fn path_to(graph: &GclGraph, start_idx: NodeIndex, node_idx: NodeIndex) -> Option<Vec<NodeIndex>> {
-    petgraph::algo::all_simple_paths(graph.deref(), start_idx, node_idx, 0, None).next()
+/// Format the counterexample with one input per line. Table actions are tagged
+/// with the line of the `apply` call, and the loop iteration if there is one.
+fn format_counterexample(counterexample: &Counterexample, line_index: &LineIndex) -> String {
+    let values = counterexample
+        .values
+        .iter()
+        .map(|entry| format!("\n    {} = {}", entry.name, entry.value));
+    let table_actions = counterexample.table_actions.iter().map(|table_action| {
+        let (line, _) = line_index.line_col(table_action.call_span.start);
+        let iteration = match table_action.iteration {
+            0 => String::new(),
+            iteration => format!(", iteration {}", iteration + 1),
+        };
+        format!(
+            "\n    table {} runs {} (applied at line {}{})",
+            table_action.table, table_action.action, line, iteration
+        )
+    });
+    let lines: String = values.chain(table_actions).collect();
+
+    if lines.is_empty() {
+        " any input".to_string()
+    } else {
+        lines
+    }
 }
 
-/// Parse the P4 program. If there are errors during parsing, the program will
-/// exit.
-fn parse(p4_program_str: &str) -> Program {
-    let lexer_state = RefCell::default();
-    let lexer = Token::lexer_with_extras(p4_program_str, &lexer_state);
-    let lexer_iter = LalrpopLexerIter::new(lexer);
-
-    match p4_parser::ProgramParser::new().parse(p4_program_str, &lexer_state, lexer_iter) {
-        Ok(parsed_ast) => {
-            log::trace!("Parsed AST: {:#?}\n", parsed_ast);
-            parsed_ast
-        }
-        Err(ParseError::InvalidToken { location }) => {
-            let (line, col) = index_to_line_col(p4_program_str, location);
-            log::error!("Invalid token at line {}, column {}", line, col);
-            std::process::exit(1);
-        }
-        Err(ParseError::UnrecognizedToken {
-            token: (lspan, token, _rspan),
-            expected,
-        }) => {
-            let (line, col) = index_to_line_col(p4_program_str, lspan);
-            log::error!(
-                "Unrecognized token '{:?}' at line {}, column {}, expected [{}]",
-                token,
-                line,
-                col,
-                expected.join(", ")
-            );
-            std::process::exit(1);
-        }
-        Err(ParseError::UnrecognizedEOF { location, expected }) => {
-            let (line, col) = index_to_line_col(p4_program_str, location);
-            log::error!(
-                "Unexpected EOF at line {}, column {}, expected [{}]",
-                line,
-                col,
-                expected.join(", ")
-            );
-            std::process::exit(1);
-        }
-        Err(ParseError::ExtraToken {
-            token: (lspan, token, _rspan),
-        }) => {
-            let (line, col) = index_to_line_col(p4_program_str, lspan);
-            log::error!(
-                "Unexpected extra token '{:?}' at line {}, column {}",
-                token,
-                line,
-                col
-            );
-            std::process::exit(1);
-        }
-        Err(ParseError::User { error }) => {
-            let token = &p4_program_str[error.clone()];
-            let (line, col) = index_to_line_col(p4_program_str, error.start);
-            log::error!("Invalid token '{}' at line {}, column {}", token, line, col);
-            std::process::exit(1);
-        }
+/// Show each error with the code which caused it, and which part of the
+/// analysis failed
+fn display_errors(error: &AnalysisError, file_name: &str, source: &str) {
+    let stage = match error {
+        AnalysisError::Parse(_) => "Parsing",
+        AnalysisError::TypeCheck(_) => "Type checking",
+        AnalysisError::Read(_) => "Reading",
+        AnalysisError::Solver(_) => "Checking reachability",
+    };
+    let diagnostics = error.diagnostics();
+
+    for diagnostic in diagnostics {
+        eprintln!("{}", diagnostic.render(file_name, source));
     }
+
+    log::error!(
+        "{} failed with {} error{}",
+        stage,
+        diagnostics.len(),
+        if diagnostics.len() == 1 { "" } else { "s" }
+    );
 }
 
-/// Convert an index of the file into a line and column index
-fn index_to_line_col(file_str: &str, index: usize) -> (usize, usize) {
-    let line = file_str
-        .chars()
-        .enumerate()
-        .take_while(|(i, _)| *i != index)
-        .filter(|(_, c)| *c == '\n')
-        .count()
-        + 1;
-    let column = file_str[0..index]
-        .chars()
-        .rev()
-        .take_while(|c| *c != '\n')
-        .count()
-        + 1;
-
-    (line, column)
+/// Warn if the analysis is incomplete because a loop was not unrolled enough
+fn display_unroll_bound_exceeded(report: &AnalysisReport, unroll_bound: usize) {
+    if report.unroll_bound_exceeded {
+        log::warn!(
+            "A loop can run more than the unroll bound ({}) allows, so some bugs may not have \
+             been found. Use --unroll-bound to increase it.",
+            unroll_bound
+        );
+    }
 }
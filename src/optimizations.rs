@@ -63,6 +63,7 @@ pub fn merge_simple_edges(graph: &mut GclGraph) {
         // Update the first node with the second node's commands
         let node = graph.node_weight_mut(node_idx).unwrap();
         node.commands.extend(target_node.commands);
+        node.span = node.span.or(target_node.span);
 
         // Add this node back to the front of the queue because the out edges
         // it gained may also qualify for merging.
@@ -0,0 +1,106 @@
+//! Parsing of P4 source code into an AST
+
+use crate::ast::Program;
+use crate::diagnostics::Diagnostic;
+use crate::lexer::{describe_token_name, new_lexer_state, LalrpopLexerIter, Token};
+use crate::p4_parser;
+use crate::span::Span;
+use lalrpop_util::ParseError;
+use logos::Logos;
+use std::ops::Range;
+
+/// Parse the P4 program. The parser recovers from syntax errors, so every
+/// syntax error in the program is returned.
+pub fn parse(p4_program_str: &str) -> Result<Program, Vec<Diagnostic>> {
+    let lexer_state = new_lexer_state();
+    let lexer = Token::lexer_with_extras(p4_program_str, &lexer_state);
+    let lexer_iter = LalrpopLexerIter::new(lexer);
+    let mut recovered_errors = Vec::new();
+
+    let result = p4_parser::ProgramParser::new().parse(
+        p4_program_str,
+        &lexer_state,
+        &mut recovered_errors,
+        lexer_iter,
+    );
+    let mut errors: Vec<_> = recovered_errors
+        .into_iter()
+        .map(|recovery| recovery.error)
+        .collect();
+
+    match result {
+        Ok(parsed_ast) if errors.is_empty() => {
+            log::trace!("Parsed AST: {:#?}\n", parsed_ast);
+            Ok(parsed_ast)
+        }
+        result => {
+            errors.extend(result.err());
+            Err(errors
+                .into_iter()
+                .map(|error| parse_error_diagnostic(error, p4_program_str))
+                .collect())
+        }
+    }
+}
+
+/// Describe a syntax error, using the source code to show the tokens
+fn parse_error_diagnostic(
+    error: ParseError<usize, Token, Range<usize>>,
+    p4_program_str: &str,
+) -> Diagnostic {
+    match error {
+        ParseError::InvalidToken { location } => {
+            Diagnostic::error("invalid token", Some(Span::new(location, location)))
+        }
+        ParseError::UnrecognizedToken {
+            token: (l, Token::Error, r),
+            ..
+        } => Diagnostic::error(
+            format!("invalid token `{}`", &p4_program_str[l..r]),
+            Some(Span::new(l, r)),
+        ),
+        ParseError::UnrecognizedToken {
+            token: (l, _token, r),
+            expected,
+        } => Diagnostic::error(
+            format!(
+                "unexpected `{}`, {}",
+                &p4_program_str[l..r],
+                describe_expected(&expected)
+            ),
+            Some(Span::new(l, r)),
+        ),
+        ParseError::UnrecognizedEOF { location, expected } => Diagnostic::error(
+            format!("unexpected end of file, {}", describe_expected(&expected)),
+            Some(Span::new(location, location)),
+        ),
+        ParseError::ExtraToken {
+            token: (l, _token, r),
+        } => Diagnostic::error(
+            format!("unexpected extra token `{}`", &p4_program_str[l..r]),
+            Some(Span::new(l, r)),
+        ),
+        ParseError::User { error } => Diagnostic::error(
+            format!(
+                "invalid width `{}`, expected a plain integer",
+                &p4_program_str[error.clone()]
+            ),
+            Some(Span::new(error.start, error.end)),
+        ),
+    }
+}
+
+/// Describe the tokens which the parser expected, such as "expected one of
+/// `;` or an identifier"
+fn describe_expected(expected: &[String]) -> String {
+    let expected: Vec<_> = expected
+        .iter()
+        .map(|token_name| describe_token_name(token_name))
+        .collect();
+
+    match expected.as_slice() {
+        [] => "expected nothing".to_string(),
+        [token] => format!("expected {}", token),
+        [rest @ .., last] => format!("expected one of {} or {}", rest.join(", "), last),
+    }
+}
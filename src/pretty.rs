@@ -0,0 +1,64 @@
+//! Helpers for writing out programs as P4-like source code
+
+use std::fmt;
+use std::fmt::{Display, Formatter, Write};
+
+/// Writes to the inner writer with each line indented by one level, for the
+/// contents of a block
+pub(crate) struct Indented<W> {
+    inner: W,
+    at_line_start: bool,
+}
+
+impl<W: Write> Indented<W> {
+    pub fn new(inner: W) -> Self {
+        Indented {
+            inner,
+            at_line_start: true,
+        }
+    }
+}
+
+impl<W: Write> Write for Indented<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for line in s.split_inclusive('\n') {
+            // Empty lines are left empty
+            if self.at_line_start && line != "\n" {
+                self.inner.write_str("    ")?;
+            }
+
+            self.inner.write_str(line)?;
+            self.at_line_start = line.ends_with('\n');
+        }
+
+        Ok(())
+    }
+}
+
+/// Displays the items separated by commas (ex. the arguments of a call)
+pub(crate) struct CommaSeparated<'a, T>(pub &'a [T]);
+
+impl<T: Display> Display for CommaSeparated<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, item) in self.0.iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+
+            Display::fmt(item, f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Write the items as the indented lines of a block, surrounded by braces
+pub(crate) fn write_block<T: Display>(f: &mut Formatter<'_>, items: &[T]) -> fmt::Result {
+    f.write_str("{\n")?;
+
+    for item in items {
+        writeln!(Indented::new(&mut *f), "{}", item)?;
+    }
+
+    f.write_str("}")
+}
@@ -0,0 +1,201 @@
+//! [SARIF] versions of analysis results, so bugs can be shown by code
+//! scanning tools
+//!
+//! [SARIF]: https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html
+
+use crate::analysis::{AnalyzedFile, Bug, Reachability};
+use crate::diagnostics::Diagnostic;
+use crate::gcl::BugKind;
+use crate::json::bug_kind_name;
+use crate::span::{LineIndex, Span};
+use serde_json::{json, Value};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// The bug kinds, in the order their rules are listed
+const RULES: [BugKind; 4] = [
+    BugKind::UninitializedRead,
+    BugKind::InvalidHeaderAccess,
+    BugKind::UnsetOutParam,
+    BugKind::UserAssert,
+];
+
+/// Convert the results of analyzing each file to a SARIF log with a single
+/// run. Each bug becomes a result, with the path to the bug as its code flow.
+/// Files which could not be analyzed are reported as tool notifications, and
+/// the file names are used as the URIs of the artifacts.
+pub fn files_to_sarif(files: &[AnalyzedFile]) -> Value {
+    let mut results = Vec::new();
+    let mut notifications = Vec::new();
+
+    for (file_idx, file) in files.iter().enumerate() {
+        let artifact = Artifact {
+            uri: &file.name,
+            index: file_idx,
+            line_index: LineIndex::new(&file.source),
+        };
+
+        match &file.result {
+            Ok(report) => {
+                results.extend(report.bugs.iter().map(|bug| bug_to_result(bug, &artifact)));
+
+                if report.unroll_bound_exceeded {
+                    notifications.push(json!({
+                        "level": "warning",
+                        "message": {
+                            "text": "A loop can run more than the unroll bound allows, so some \
+                                     bugs may not have been found"
+                        },
+                        "locations": [{
+                            "physicalLocation": {
+                                "artifactLocation": artifact.artifact_location(),
+                            }
+                        }],
+                    }));
+                }
+            }
+            Err(error) => notifications.extend(
+                error
+                    .diagnostics()
+                    .iter()
+                    .map(|diagnostic| diagnostic_to_notification(diagnostic, &artifact)),
+            ),
+        }
+    }
+
+    let successful = files.iter().all(|file| file.result.is_ok());
+    let artifacts: Vec<_> = files
+        .iter()
+        .map(|file| json!({ "location": { "uri": file.name } }))
+        .collect();
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": RULES.iter().copied().map(rule).collect::<Vec<_>>(),
+                }
+            },
+            "columnKind": "unicodeCodePoints",
+            "artifacts": artifacts,
+            "invocations": [{
+                "executionSuccessful": successful,
+                "toolExecutionNotifications": notifications,
+            }],
+            "results": results,
+        }],
+    })
+}
+
+/// A file in the SARIF log
+struct Artifact<'a> {
+    uri: &'a str,
+    /// The index of the file in the run's list of artifacts
+    index: usize,
+    line_index: LineIndex<'a>,
+}
+
+impl Artifact<'_> {
+    fn artifact_location(&self) -> Value {
+        json!({ "uri": self.uri, "index": self.index })
+    }
+
+    /// A SARIF location covering the span. SARIF regions end after the last
+    /// character, which is the position of the span's end offset.
+    fn location(&self, span: Span) -> Value {
+        let (start_line, start_column) = self.line_index.line_col(span.start);
+        let (end_line, end_column) = self.line_index.line_col(span.end.max(span.start));
+
+        json!({
+            "physicalLocation": {
+                "artifactLocation": self.artifact_location(),
+                "region": {
+                    "startLine": start_line,
+                    "startColumn": start_column,
+                    "endLine": end_line,
+                    "endColumn": end_column,
+                },
+            }
+        })
+    }
+}
+
+fn rule(kind: BugKind) -> Value {
+    let description = match kind {
+        BugKind::UninitializedRead => "A variable is read before it is given a value",
+        BugKind::InvalidHeaderAccess => "A field of an invalid header is read or written",
+        BugKind::UnsetOutParam => "An out parameter is not given a value before returning",
+        BugKind::UserAssert => "The condition of an assert call can be false",
+    };
+
+    json!({
+        "id": bug_kind_name(kind),
+        "shortDescription": { "text": description },
+        "defaultConfiguration": { "level": "error" },
+    })
+}
+
+fn bug_to_result(bug: &Bug, artifact: &Artifact) -> Value {
+    // Bugs which the solver gave up on, or which the counterexample doesn't
+    // reach, are only warnings
+    let (level, message) = match bug.reachability {
+        Reachability::Unknown => (
+            "warning",
+            format!("{} (could not be proven unreachable)", bug.message),
+        ),
+        Reachability::Reachable if !bug.is_confirmed() => (
+            "warning",
+            format!("{} (the counterexample does not reach it)", bug.message),
+        ),
+        _ => ("error", bug.message.clone()),
+    };
+    let mut result = json!({
+        "ruleId": bug_kind_name(bug.kind),
+        "ruleIndex": RULES.iter().position(|kind| *kind == bug.kind),
+        "level": level,
+        "message": { "text": message },
+        "locations": [artifact.location(bug.span)],
+    });
+
+    if let Some(path) = &bug.path {
+        // Steps without a span (such as the start node) only get a message
+        let thread_flow_locations: Vec<_> = path
+            .iter()
+            .map(|step| {
+                let mut step_location = match step.span {
+                    Some(span) => artifact.location(span),
+                    None => json!({}),
+                };
+                step_location["message"] = json!({ "text": step.name });
+
+                json!({ "location": step_location })
+            })
+            .collect();
+
+        result["codeFlows"] = json!([{
+            "threadFlows": [{ "locations": thread_flow_locations }],
+        }]);
+    }
+
+    result
+}
+
+fn diagnostic_to_notification(diagnostic: &Diagnostic, artifact: &Artifact) -> Value {
+    // Errors without a span (such as an unreadable file) still point at the file
+    let location = match diagnostic.span {
+        Some(span) => artifact.location(span),
+        None => json!({
+            "physicalLocation": { "artifactLocation": artifact.artifact_location() }
+        }),
+    };
+
+    json!({
+        "level": "error",
+        "message": { "text": diagnostic.message },
+        "locations": [location],
+    })
+}
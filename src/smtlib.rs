@@ -0,0 +1,598 @@
+//! SMT-LIB2 versions of the reachability predicates, so they can be checked
+//! by solver programs, and a reader for the solvers' responses
+
+use crate::gcl::{GclBinOp, GclExpr, GclExprData, GclUnOp};
+use crate::ir::{IrBaseType, IrType};
+use crate::solver::{ModelValue, SolverError};
+use num_bigint::BigInt;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// The SMT-LIB2 datatypes of the user-defined IR types (ex. structs and
+/// headers)
+pub struct Smt2TypeMap {
+    datatypes: HashMap<IrType, Smt2Datatype>,
+    /// The `declare-datatypes` commands, in the order they must be run
+    declarations: Vec<String>,
+}
+
+struct Smt2Datatype {
+    name: String,
+    constructor: String,
+    /// The accessor of each field. Accessors are global functions, so they
+    /// include the datatype's name to keep them unique.
+    accessors: Vec<String>,
+}
+
+impl Smt2TypeMap {
+    /// Translate the user-defined IR types into SMT-LIB2 datatypes
+    pub fn new(types: &[(String, IrType)]) -> Result<Self, SolverError> {
+        let mut type_map = Smt2TypeMap {
+            datatypes: HashMap::new(),
+            declarations: Vec::new(),
+        };
+
+        for (ty_name, ty) in types {
+            let (kind, fields) = match ty {
+                IrType::Base(IrBaseType::Struct { fields }) => ("struct", fields),
+                IrType::Base(IrBaseType::Header { fields }) => ("header", fields),
+                _ => continue,
+            };
+            let name = format!("{}_{}_{}", kind, type_map.datatypes.len(), ty_name);
+            let datatype = Smt2Datatype {
+                constructor: format!("mk_{}", name),
+                accessors: fields
+                    .iter()
+                    .map(|(_, field_name)| format!("{}.{}", name, field_name))
+                    .collect(),
+                name,
+            };
+
+            let field_decls = fields
+                .iter()
+                .zip(&datatype.accessors)
+                .map(|((field_ty, _), accessor)| {
+                    let field_sort = type_map.sort(&IrType::Base(field_ty.clone()))?;
+                    Ok(format!(" ({} {})", symbol(accessor), field_sort))
+                })
+                .collect::<Result<String, SolverError>>()?;
+            type_map.declarations.push(format!(
+                "(declare-datatypes (({} 0)) ((({}{}))))",
+                symbol(&datatype.name),
+                symbol(&datatype.constructor),
+                field_decls
+            ));
+            type_map.datatypes.insert(ty.clone(), datatype);
+        }
+
+        Ok(type_map)
+    }
+
+    pub fn declarations(&self) -> &[String] {
+        &self.declarations
+    }
+
+    /// Get the SMT-LIB2 sort of the type. Types without a value the solver
+    /// could reason about (ex. `packet_in`) have no sort.
+    pub fn sort(&self, ty: &IrType) -> Result<String, SolverError> {
+        let sort = match ty {
+            IrType::Base(IrBaseType::Bool) => "Bool".to_string(),
+            IrType::Base(IrBaseType::Int) => "Int".to_string(),
+            IrType::Base(IrBaseType::String) => "String".to_string(),
+            IrType::Base(IrBaseType::Bit { width })
+            | IrType::Base(IrBaseType::SignedInt { width }) => format!("(_ BitVec {})", width),
+            IrType::Base(IrBaseType::Struct { .. }) | IrType::Base(IrBaseType::Header { .. }) => {
+                let datatype = self
+                    .datatypes
+                    .get(ty)
+                    .expect("Use of type before it was declared");
+                symbol(&datatype.name)
+            }
+            _ => {
+                return Err(SolverError(format!(
+                    "The type {} is not supported by the solver",
+                    ty
+                )))
+            }
+        };
+
+        Ok(sort)
+    }
+
+    /// Get the value which Z3 gives to a constant of the type when it
+    /// completes a model: false, zero, the empty string, or a struct or header
+    /// whose fields have these values
+    pub fn default_value(&self, ty: &IrType) -> Result<String, SolverError> {
+        let value = match ty {
+            IrType::Base(IrBaseType::Bool) => "false".to_string(),
+            IrType::Base(IrBaseType::Int) => "0".to_string(),
+            IrType::Base(IrBaseType::String) => "\"\"".to_string(),
+            IrType::Base(IrBaseType::Bit { width })
+            | IrType::Base(IrBaseType::SignedInt { width }) => bv_literal(&BigInt::from(0), *width),
+            IrType::Base(IrBaseType::Struct { fields })
+            | IrType::Base(IrBaseType::Header { fields }) => {
+                let datatype = self
+                    .datatypes
+                    .get(ty)
+                    .expect("Use of type before it was declared");
+                let field_values = fields
+                    .iter()
+                    .map(|(field_ty, _)| {
+                        Ok(format!(
+                            " {}",
+                            self.default_value(&IrType::Base(field_ty.clone()))?
+                        ))
+                    })
+                    .collect::<Result<String, SolverError>>()?;
+                if fields.is_empty() {
+                    symbol(&datatype.constructor)
+                } else {
+                    format!("({}{})", symbol(&datatype.constructor), field_values)
+                }
+            }
+            _ => {
+                return Err(SolverError(format!(
+                    "The type {} is not supported by the solver",
+                    ty
+                )))
+            }
+        };
+
+        Ok(value)
+    }
+}
+
+impl GclExpr {
+    /// Convert the GCL predicate into an SMT-LIB2 term
+    pub fn as_smt2(&self, type_map: &Smt2TypeMap) -> String {
+        match &self.data {
+            GclExprData::Bool(value) => value.to_string(),
+            GclExprData::Int(value) => int_to_smt2(value, &self.ty),
+            GclExprData::String(value) => format!("\"{}\"", value.replace('"', "\"\"")),
+            GclExprData::Fact(fact) => symbol(&fact.z3_name()),
+            GclExprData::Var(name) => symbol(&name.to_string()),
+            GclExprData::UnOp(op, inner) => {
+                let inner_smt2 = inner.as_smt2(type_map);
+
+                match op {
+                    GclUnOp::Negate => format!("(not {})", inner_smt2),
+                    GclUnOp::BitNot => format!("(bvnot {})", inner_smt2),
+                    GclUnOp::Minus => match bit_width(&inner.ty) {
+                        Some(_) => format!("(bvneg {})", inner_smt2),
+                        None => format!("(- {})", inner_smt2),
+                    },
+                }
+            }
+            GclExprData::BinOp(op, left, right) => {
+                let left_smt2 = left.as_smt2(type_map);
+                let right_smt2 = right.as_smt2(type_map);
+
+                match op {
+                    GclBinOp::And => format!("(and {} {})", left_smt2, right_smt2),
+                    GclBinOp::Or => format!("(or {} {})", left_smt2, right_smt2),
+                    GclBinOp::Equals => format!("(= {} {})", left_smt2, right_smt2),
+                    GclBinOp::NotEquals => format!("(distinct {} {})", left_smt2, right_smt2),
+                    _ => match bit_width(&left.ty) {
+                        Some(width) => {
+                            let is_signed =
+                                matches!(left.ty, IrType::Base(IrBaseType::SignedInt { .. }));
+                            bv_bin_op(*op, &left_smt2, &right_smt2, &right.ty, width, is_signed)
+                        }
+                        None => int_bin_op(*op, &left_smt2, &right_smt2),
+                    },
+                }
+            }
+            GclExprData::Struct { fields } => {
+                let constructor = symbol(&type_map.datatypes[&self.ty].constructor);
+                if fields.is_empty() {
+                    return constructor;
+                }
+
+                let fields_smt2: Vec<_> = fields
+                    .iter()
+                    .map(|(_, field)| field.as_smt2(type_map))
+                    .collect();
+                format!("({} {})", constructor, fields_smt2.join(" "))
+            }
+            GclExprData::FieldAccess(target, field) => {
+                let datatype = &type_map.datatypes[&target.ty];
+                let field_idx = match &target.ty {
+                    IrType::Base(IrBaseType::Struct { fields })
+                    | IrType::Base(IrBaseType::Header { fields }) => {
+                        fields.iter().position(|(_, name)| name == field).unwrap()
+                    }
+                    _ => panic!(
+                        "Type error in SMT-LIB2 gen: cannot access field of ty: {:?}",
+                        target.ty
+                    ),
+                };
+
+                format!(
+                    "({} {})",
+                    symbol(&datatype.accessors[field_idx]),
+                    target.as_smt2(type_map)
+                )
+            }
+        }
+    }
+
+    /// Get the name and `declare-fun` command of each variable and fact in
+    /// the expression
+    pub fn smt2_declarations(
+        &self,
+        type_map: &Smt2TypeMap,
+    ) -> Result<Vec<(String, String)>, SolverError> {
+        self.smt2_constants()
+            .into_iter()
+            .map(|(name, ty)| {
+                let declaration = format!("(declare-fun {} () {})", name, type_map.sort(ty)?);
+                Ok((name, declaration))
+            })
+            .collect()
+    }
+
+    /// Get the SMT-LIB2 name and type of each variable and fact in the
+    /// expression, without repeats
+    pub fn smt2_constants(&self) -> Vec<(String, &IrType)> {
+        let mut constants = Vec::new();
+        self.visit_constants(&mut |name, ty| {
+            if !constants.iter().any(|(other, _)| *other == name) {
+                constants.push((name, ty));
+            }
+        });
+        constants
+    }
+
+    /// Call the function with the SMT-LIB2 name and type of each variable and
+    /// fact in the expression
+    fn visit_constants<'a>(&'a self, f: &mut impl FnMut(String, &'a IrType)) {
+        match &self.data {
+            GclExprData::Bool(_) | GclExprData::Int(_) | GclExprData::String(_) => {}
+            GclExprData::Fact(fact) => f(symbol(&fact.z3_name()), &self.ty),
+            GclExprData::Var(name) => f(symbol(&name.to_string()), &self.ty),
+            GclExprData::BinOp(_, left, right) => {
+                left.visit_constants(f);
+                right.visit_constants(f);
+            }
+            GclExprData::UnOp(_, inner) => inner.visit_constants(f),
+            GclExprData::Struct { fields } => {
+                for (_, field) in fields {
+                    field.visit_constants(f);
+                }
+            }
+            GclExprData::FieldAccess(target, _) => target.visit_constants(f),
+        }
+    }
+}
+
+fn bit_width(ty: &IrType) -> Option<usize> {
+    match ty {
+        IrType::Base(base_ty) => base_ty.bit_width(),
+        _ => None,
+    }
+}
+
+/// Write a symbol, quoting it if it has characters which are not allowed in
+/// simple symbols
+fn symbol(name: &str) -> String {
+    let is_simple = !name.starts_with(|c: char| c.is_ascii_digit())
+        && !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "~!@$%^&*_-+=<>.?/".contains(c));
+
+    if is_simple {
+        name.to_string()
+    } else {
+        format!("|{}|", name)
+    }
+}
+
+/// Convert an integer constant into an SMT-LIB2 integer or bit-vector,
+/// depending on its type
+fn int_to_smt2(value: &BigInt, ty: &IrType) -> String {
+    match ty {
+        IrType::Base(IrBaseType::Int) if value.sign() == num_bigint::Sign::Minus => {
+            format!("(- {})", -value)
+        }
+        IrType::Base(IrBaseType::Int) => value.to_string(),
+        IrType::Base(IrBaseType::Bit { width }) | IrType::Base(IrBaseType::SignedInt { width }) => {
+            bv_literal(value, *width)
+        }
+        _ => panic!("Type error in SMT-LIB2 gen: integer with type {:?}", ty),
+    }
+}
+
+/// A bit-vector constant, using the two's complement representation of
+/// negative values
+fn bv_literal(value: &BigInt, width: usize) -> String {
+    let modulus = BigInt::from(1) << width;
+    let value = ((value % &modulus) + &modulus) % &modulus;
+
+    format!("(_ bv{} {})", value, width)
+}
+
+/// Convert an arithmetic, bitwise or comparison operator on bit-vectors
+fn bv_bin_op(
+    op: GclBinOp,
+    left: &str,
+    right: &str,
+    right_ty: &IrType,
+    width: usize,
+    is_signed: bool,
+) -> String {
+    let function = match op {
+        GclBinOp::ShiftLeft | GclBinOp::ShiftRight => {
+            return bv_shift(op, left, right, right_ty, width, is_signed)
+        }
+        GclBinOp::SaturatingAdd => return bv_saturating_op("bvadd", left, right, width, is_signed),
+        GclBinOp::SaturatingSub => return bv_saturating_op("bvsub", left, right, width, is_signed),
+        GclBinOp::Add => "bvadd",
+        GclBinOp::Sub => "bvsub",
+        GclBinOp::Mul => "bvmul",
+        GclBinOp::Div if is_signed => "bvsdiv",
+        GclBinOp::Div => "bvudiv",
+        GclBinOp::Mod if is_signed => "bvsrem",
+        GclBinOp::Mod => "bvurem",
+        GclBinOp::BitAnd => "bvand",
+        GclBinOp::BitOr => "bvor",
+        GclBinOp::BitXor => "bvxor",
+        GclBinOp::LessThan if is_signed => "bvslt",
+        GclBinOp::LessThan => "bvult",
+        GclBinOp::LessThanEquals if is_signed => "bvsle",
+        GclBinOp::LessThanEquals => "bvule",
+        GclBinOp::GreaterThan if is_signed => "bvsgt",
+        GclBinOp::GreaterThan => "bvugt",
+        GclBinOp::GreaterThanEquals if is_signed => "bvsge",
+        GclBinOp::GreaterThanEquals => "bvuge",
+        _ => panic!(
+            "Type error in SMT-LIB2 gen: {} is not a bit-vector operator",
+            op
+        ),
+    };
+
+    format!("({} {} {})", function, left, right)
+}
+
+/// Convert a saturating addition or subtraction. The operation is done with
+/// two extra bits so it can't overflow, then the result is clamped to the
+/// range of the type.
+fn bv_saturating_op(
+    function: &str,
+    left: &str,
+    right: &str,
+    width: usize,
+    is_signed: bool,
+) -> String {
+    let (extend, min, max) = if is_signed {
+        let half = BigInt::from(1) << (width - 1);
+        ("sign_extend", -&half, half - 1)
+    } else {
+        let max = (BigInt::from(1) << width) - 1;
+        ("zero_extend", BigInt::from(0), max)
+    };
+
+    format!(
+        "(let ((result ({function} ((_ {extend} 2) {left}) ((_ {extend} 2) {right})))) \
+         (ite (bvsgt result {wide_max}) {max} \
+         (ite (bvslt result {wide_min}) {min} ((_ extract {high} 0) result))))",
+        function = function,
+        extend = extend,
+        left = left,
+        right = right,
+        wide_max = bv_literal(&max, width + 2),
+        wide_min = bv_literal(&min, width + 2),
+        max = bv_literal(&max, width),
+        min = bv_literal(&min, width),
+        high = width - 1
+    )
+}
+
+/// Convert a shift of a bit-vector. The shift amount may be an integer or a
+/// bit-vector of any width.
+fn bv_shift(
+    op: GclBinOp,
+    value: &str,
+    amount: &str,
+    amount_ty: &IrType,
+    width: usize,
+    is_signed: bool,
+) -> String {
+    let (amount, amount_width) = match bit_width(amount_ty) {
+        Some(amount_width) => (amount.to_string(), amount_width),
+        // Shifting by the width or more has the same result, so clamp the
+        // amount to make sure it fits in the bit-vector.
+        None => (
+            format!(
+                "((_ int2bv {width}) (let ((amount {amount})) \
+                 (ite (> amount {width}) {width} amount)))",
+                width = width,
+                amount = amount
+            ),
+            width,
+        ),
+    };
+
+    // Extend the operands to the same width, so large shift amounts are not
+    // truncated.
+    let shift_width = width.max(amount_width);
+    let extend = if is_signed {
+        "sign_extend"
+    } else {
+        "zero_extend"
+    };
+    let function = match op {
+        GclBinOp::ShiftLeft => "bvshl",
+        GclBinOp::ShiftRight if is_signed => "bvashr",
+        GclBinOp::ShiftRight => "bvlshr",
+        _ => panic!("Type error in SMT-LIB2 gen: {} is not a shift operator", op),
+    };
+
+    format!(
+        "((_ extract {} 0) ({} ((_ {} {}) {}) ((_ zero_extend {}) {})))",
+        width - 1,
+        function,
+        extend,
+        shift_width - width,
+        value,
+        shift_width - amount_width,
+        amount
+    )
+}
+
+/// Convert an arithmetic or comparison operator on arbitrary-precision integers
+fn int_bin_op(op: GclBinOp, left: &str, right: &str) -> String {
+    match op {
+        // Powers are reals, but the shift amount is never negative
+        GclBinOp::ShiftLeft => return format!("(* {} (to_int (^ 2 {})))", left, right),
+        GclBinOp::ShiftRight => return format!("(div {} (to_int (^ 2 {})))", left, right),
+        _ => {}
+    }
+
+    let function = match op {
+        GclBinOp::Add => "+",
+        GclBinOp::Sub => "-",
+        GclBinOp::Mul => "*",
+        GclBinOp::Div => "div",
+        GclBinOp::Mod => "mod",
+        GclBinOp::LessThan => "<",
+        GclBinOp::LessThanEquals => "<=",
+        GclBinOp::GreaterThan => ">",
+        GclBinOp::GreaterThanEquals => ">=",
+        _ => panic!(
+            "Type error in SMT-LIB2 gen: {} is not an integer operator",
+            op
+        ),
+    };
+
+    format!("({} {} {})", function, left, right)
+}
+
+/// An S-expression, such as a solver's response to a command
+#[derive(Debug)]
+pub enum SExpr {
+    /// A symbol, keyword or literal. String literals keep their quotes.
+    Atom(String),
+    List(Vec<SExpr>),
+}
+
+impl SExpr {
+    /// Parse the first S-expression in the text. `None` is returned if the
+    /// text does not have a complete S-expression (ex. a list is unclosed).
+    pub fn parse(text: &str) -> Option<SExpr> {
+        parse_sexpr(&mut text.chars().peekable())
+    }
+
+    /// Get the value of a constant in a model (ex. `#b0101` or `(- 5)`)
+    pub fn to_model_value(&self) -> Option<ModelValue> {
+        match self {
+            SExpr::Atom(atom) => {
+                if let Some(digits) = atom.strip_prefix("#b") {
+                    return BigInt::parse_bytes(digits.as_bytes(), 2).map(ModelValue::BitVector);
+                }
+                if let Some(digits) = atom.strip_prefix("#x") {
+                    return BigInt::parse_bytes(digits.as_bytes(), 16).map(ModelValue::BitVector);
+                }
+                if let Some(string) = atom.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+                    return Some(ModelValue::String(string.replace("\"\"", "\"")));
+                }
+
+                match atom.as_str() {
+                    "true" => Some(ModelValue::Bool(true)),
+                    "false" => Some(ModelValue::Bool(false)),
+                    _ => atom.parse().ok().map(ModelValue::Int),
+                }
+            }
+            SExpr::List(items) => match items.as_slice() {
+                [SExpr::Atom(minus), value] if minus == "-" => match value.to_model_value()? {
+                    ModelValue::Int(value) => Some(ModelValue::Int(-value)),
+                    _ => None,
+                },
+                [SExpr::Atom(underscore), SExpr::Atom(value), SExpr::Atom(_)]
+                    if underscore == "_" && value.starts_with("bv") =>
+                {
+                    value[2..].parse().ok().map(ModelValue::BitVector)
+                }
+                _ => None,
+            },
+        }
+    }
+}
+
+impl Display for SExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SExpr::Atom(atom) => f.write_str(atom),
+            SExpr::List(items) => {
+                f.write_str("(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+fn parse_sexpr(chars: &mut Peekable<Chars>) -> Option<SExpr> {
+    skip_whitespace(chars);
+
+    match chars.next()? {
+        '(' => {
+            let mut items = Vec::new();
+            loop {
+                skip_whitespace(chars);
+                if *chars.peek()? == ')' {
+                    chars.next();
+                    return Some(SExpr::List(items));
+                }
+                items.push(parse_sexpr(chars)?);
+            }
+        }
+        ')' => None,
+        quote @ '"' | quote @ '|' => {
+            let mut atom = quote.to_string();
+            loop {
+                let c = chars.next()?;
+                atom.push(c);
+
+                // Quotes in strings are escaped by doubling them
+                if c == quote && !(quote == '"' && chars.peek() == Some(&'"')) {
+                    return Some(SExpr::Atom(atom));
+                } else if c == quote {
+                    atom.push(chars.next()?);
+                }
+            }
+        }
+        c => {
+            let mut atom = c.to_string();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '(' || c == ')' {
+                    break;
+                }
+                atom.push(c);
+                chars.next();
+            }
+
+            Some(SExpr::Atom(atom))
+        }
+    }
+}
+
+/// Skip whitespace and comments
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while let Some(&c) = chars.peek() {
+        if c == ';' {
+            while !matches!(chars.next(), Some('\n') | None) {}
+        } else if c.is_whitespace() {
+            chars.next();
+        } else {
+            break;
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! The solvers which can check if reachability predicates are satisfiable
+
+use crate::gcl::GclExpr;
+use num_bigint::BigInt;
+use std::fmt::{self, Display, Formatter};
+
+/// Checks reachability predicates, one at a time
+pub trait SolverBackend {
+    /// Check if the predicate can be true. If it can, a model which makes it
+    /// true is returned. The model may only be valid until the next check.
+    fn check(&mut self, predicate: &GclExpr) -> Result<CheckResult<'_>, SolverError>;
+}
+
+/// The result of checking a predicate
+pub enum CheckResult<'a> {
+    /// The predicate can be true under the model
+    Sat(Box<dyn SolverModel + 'a>),
+    /// The predicate is always false
+    Unsat,
+    /// The solver gave up (ex. it timed out)
+    Unknown,
+}
+
+/// An assignment of values to the variables of a predicate
+pub trait SolverModel {
+    /// Get the value of an expression with a base type (ex. a bit-vector, not
+    /// a struct) under the model
+    fn eval(&self, expr: &GclExpr) -> Option<ModelValue>;
+}
+
+/// A value from a model
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ModelValue {
+    Bool(bool),
+    Int(BigInt),
+    /// An unsigned bit-vector value. Signed integers use the two's complement
+    /// representation.
+    BitVector(BigInt),
+    String(String),
+}
+
+/// Which solver checks the reachability predicates
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SolverKind {
+    /// The Z3 library, which the analyzer is linked to
+    #[cfg(feature = "z3")]
+    Z3,
+    /// A solver program which reads SMT-LIB2 commands from stdin, given as
+    /// the program (which is searched for on `PATH`) and its arguments. For
+    /// example, `["cvc5", "--incremental"]`.
+    External(Vec<String>),
+}
+
+impl Default for SolverKind {
+    #[cfg(feature = "z3")]
+    fn default() -> Self {
+        SolverKind::Z3
+    }
+
+    /// Without the Z3 library, the Z3 program is used instead
+    #[cfg(not(feature = "z3"))]
+    fn default() -> Self {
+        SolverKind::External(vec!["z3".to_string(), "-in".to_string()])
+    }
+}
+
+/// The solver could not be run, or it gave an unexpected response
+#[derive(Debug)]
+pub struct SolverError(pub String);
+
+impl Display for SolverError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
@@ -0,0 +1,47 @@
+//! Locations in the P4 source code
+
+/// A range of byte offsets into the source code
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+}
+
+/// Maps byte offsets in the source code to line and column numbers
+pub struct LineIndex<'a> {
+    source: &'a str,
+    /// The byte offset of the start of each line
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    /// Convert a byte offset into a line and column. Both start at 1, and
+    /// the column is counted in characters.
+    pub fn line_col(&self, index: usize) -> (usize, usize) {
+        let line = match self.line_starts.binary_search(&index) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+        let line_start = self.line_starts[line];
+        let column = self.source[line_start..index].chars().count() + 1;
+
+        (line + 1, column)
+    }
+}
@@ -2,17 +2,22 @@
 
 use crate::ast::Direction;
 use crate::gcl::{
-    GclAssignment, GclBinOp, GclCommand, GclExpr, GclExprData, GclFact, GclGraph, GclLValue,
-    GclNode, GclNodeRange, MemoryLocation,
+    BugKind, GclAssignment, GclBinOp, GclBug, GclCommand, GclExpr, GclExprData, GclFact, GclGraph,
+    GclLValue, GclNode, GclNodeRange, MemoryLocation, TableChoice,
 };
 use crate::ir::{
-    IrActionDecl, IrAssignment, IrBlockStatement, IrControlDecl, IrControlLocalDecl, IrDeclaration,
-    IrExpr, IrExprData, IrFunctionCall, IrIfStatement, IrInstantiation, IrLValue, IrLValueData,
-    IrProgram, IrStatement, IrStatementOrDecl, IrType, IrVariableDecl,
+    IrActionDecl, IrArgument, IrAssert, IrAssignment, IrBaseType, IrBlockStatement, IrControlDecl,
+    IrControlLocalDecl, IrDeclaration, IrExpr, IrExprData, IrFunctionCall, IrIfStatement,
+    IrInstantiation, IrKeyset, IrLValue, IrLValueData, IrMethodCall, IrParam, IrParserDecl,
+    IrParserLocalDecl, IrProgram, IrStatement, IrStatementOrDecl, IrTableDecl, IrTableProperty,
+    IrTransition, IrTransitionTarget, IrType, IrVariableDecl, VariableId,
 };
+use crate::span::Span;
 use crate::type_checker::ProgramMetadata;
 use either::Either;
+use num_bigint::BigInt;
 use petgraph::graph::NodeIndex;
+use std::collections::HashMap;
 
 /// Trait for converting a P4 AST node into GCL
 pub trait ToGcl {
@@ -29,6 +34,7 @@ impl ToGcl for IrProgram {
         let start_idx = graph.add_node(GclNode {
             name: "start".to_string(),
             commands: Vec::new(),
+            span: None,
         });
 
         let mut commands = Vec::new();
@@ -36,8 +42,8 @@ impl ToGcl for IrProgram {
             start: start_idx,
             end: start_idx,
         };
-        let mut control_idx = None;
 
+        // Set up the top level constants and instantiations before any blocks
         for decl in &self.declarations {
             match decl {
                 IrDeclaration::Constant(const_decl) => {
@@ -46,13 +52,10 @@ impl ToGcl for IrProgram {
                     graph.add_edge(node_range.end, range.start, GclExpr::default());
                     node_range.end = range.end;
                 }
-                // The nodes are added to the graph automatically
-                IrDeclaration::Control(control) => {
-                    control_idx = Some(control.to_gcl(graph, metadata).start);
-                }
                 IrDeclaration::Instantiation(instantiation) => {
                     commands.push(instantiation.to_gcl(graph, metadata))
                 }
+                IrDeclaration::Control(_) | IrDeclaration::Parser(_) => {}
             }
         }
 
@@ -80,10 +83,25 @@ impl ToGcl for IrProgram {
         //     );
         // }
 
-        // FIXME: This is a hard-coded way of connecting start to the control
-        //        block. Instead, we should parse the main decl and use that info.
-        if let Some(idx) = control_idx {
-            graph.add_edge(start_idx, idx, GclExpr::default());
+        // FIXME: This is a hard-coded way of connecting the blocks: they are
+        //        run in the order they are declared. Instead, we should parse
+        //        the main decl and use that info.
+        let mut block_outputs = Vec::new();
+        for decl in &self.declarations {
+            let range = match decl {
+                IrDeclaration::Parser(parser) => {
+                    bind_block_params(graph, &parser.params, &mut block_outputs);
+                    parser.to_gcl(graph, metadata)
+                }
+                IrDeclaration::Control(control) => {
+                    bind_block_params(graph, &control.params, &mut block_outputs);
+                    control.to_gcl(graph, metadata)
+                }
+                IrDeclaration::Constant(_) | IrDeclaration::Instantiation(_) => continue,
+            };
+
+            graph.add_edge(node_range.end, range.start, GclExpr::default());
+            node_range.end = range.end;
         }
 
         start_idx
@@ -92,35 +110,90 @@ impl ToGcl for IrProgram {
     }
 }
 
-impl ToGcl for IrControlDecl {
-    type Output = GclNodeRange;
+/// Blocks pass values to each other through parameters of the same type (ex.
+/// the headers from a parser are passed to the ingress control). Bind each
+/// parameter to a matching output of a previous block, if there is one.
+fn bind_block_params(graph: &mut GclGraph, params: &[IrParam], outputs: &mut Vec<IrParam>) {
+    let mut unbound_outputs = std::mem::take(outputs);
+
+    for param in params {
+        if let Some(output_idx) = unbound_outputs
+            .iter()
+            .position(|output| output.ty == param.ty)
+        {
+            let output = unbound_outputs.remove(output_idx);
+            graph.alias_var_location(param.id.clone(), &output.id);
+        }
 
-    fn to_gcl(&self, graph: &mut GclGraph, metadata: &ProgramMetadata) -> Self::Output {
-        let mut commands = Vec::new();
-        let mut param_init_commands = Vec::new();
-        let mut param_end_commands = Vec::new();
+        if let Direction::Out | Direction::InOut = param.direction {
+            outputs.push(param.clone());
+        }
+    }
 
-        // Add in each param
-        for param in &self.params {
-            let loc = graph.get_var_location(&param.id);
+    // Outputs which were not used are still available to later blocks
+    outputs.extend(unbound_outputs);
+}
+
+/// Create the nodes which set up and tear down the parameters of a block
+fn make_param_nodes(
+    graph: &mut GclGraph,
+    params: &[IrParam],
+    block_kind: &str,
+    block_span: Span,
+) -> GclNodeRange {
+    let mut param_init_commands = Vec::new();
+    let mut param_end_commands = Vec::new();
+
+    for param in params {
+        // The values of bound parameters come from the previous block
+        if graph.is_var_aliased(&param.id) {
+            continue;
+        }
+
+        let loc = graph.get_var_location(&param.id);
+
+        if let Direction::In | Direction::InOut | Direction::Directionless = param.direction {
+            // "in" and "inout" parameters can be read from
+            param_init_commands.push(GclCommand::AddFact(GclFact::HasValue(loc.clone())));
 
-            if let Direction::In | Direction::InOut = param.direction {
-                // "in" and "inout" parameters can be read from
-                param_init_commands.push(GclCommand::AddFact(GclFact::HasValue(loc.clone())));
+            // Their headers may or may not be valid
+            for header in header_lvalues(GclLValue::Var(loc.clone()), &param.ty) {
+                param_init_commands.push(GclCommand::HavocFact(GclFact::IsValid(header)));
             }
+        }
 
+        // "out" and "inout" parameters are passed on to the next block
+        if let Direction::In | Direction::Directionless = param.direction {
             param_end_commands.push(GclCommand::RemoveFact(GclFact::HasValue(loc)));
         }
-        let param_init_node_name = graph.create_name("control_params_init");
-        let param_end_node_name = graph.create_name("control_params_end");
-        let param_init_node_idx = graph.add_node(GclNode {
-            name: param_init_node_name,
-            commands: param_init_commands,
-        });
-        let param_end_node_idx = graph.add_node(GclNode {
-            name: param_end_node_name,
-            commands: param_end_commands,
-        });
+    }
+
+    let param_init_node_name = graph.create_name(&format!("{}_params_init", block_kind));
+    let param_end_node_name = graph.create_name(&format!("{}_params_end", block_kind));
+    let param_init_node_idx = graph.add_node(GclNode {
+        name: param_init_node_name,
+        commands: param_init_commands,
+        span: Some(block_span),
+    });
+    let param_end_node_idx = graph.add_node(GclNode {
+        name: param_end_node_name,
+        commands: param_end_commands,
+        span: Some(block_span),
+    });
+
+    GclNodeRange {
+        start: param_init_node_idx,
+        end: param_end_node_idx,
+    }
+}
+
+impl ToGcl for IrControlDecl {
+    type Output = GclNodeRange;
+
+    fn to_gcl(&self, graph: &mut GclGraph, metadata: &ProgramMetadata) -> Self::Output {
+        let block_start = graph.block_start();
+        let mut commands = Vec::new();
+        let params_range = make_param_nodes(graph, &self.params, "control", self.span);
 
         // Collect all of the top level local declarations (e.g. actions) and
         // local declarations (e.g. variables).
@@ -133,18 +206,12 @@ impl ToGcl for IrControlDecl {
                     commands.push(IrStatementOrDecl::Instantiation(instantiation.clone()))
                 }
                 IrControlLocalDecl::Action(action_decl) => {
-                    let action_range = action_decl.to_gcl(graph, metadata);
-
-                    // Register the action under the namespace of this control block
-                    graph.register_function(
-                        // FIXME: Check if we actually need namespacing
-                        // format!("{}::{}", self.name, action_decl.name),
-                        action_decl.id.clone(),
-                        action_range,
-                    );
+                    // FIXME: Check if we actually need namespacing
+                    // format!("{}::{}", self.name, action_decl.name),
+                    graph.register_action(action_decl.clone());
                 }
-                IrControlLocalDecl::Table(_table_decl) => {
-                    // TODO
+                IrControlLocalDecl::Table(table_decl) => {
+                    graph.register_table(table_decl.clone());
                 }
             }
         }
@@ -154,12 +221,303 @@ impl ToGcl for IrControlDecl {
 
         // Create the block node
         let block_range = IrBlockStatement(commands).to_gcl(graph, metadata);
-        graph.add_edge(param_init_node_idx, block_range.start, GclExpr::default());
-        graph.add_edge(block_range.end, param_end_node_idx, GclExpr::default());
+        let outputs_check_idx = assert_out_params_set(graph, &self.params, params_range.end);
+        graph.add_edge(params_range.start, block_range.start, GclExpr::default());
+        graph.add_edge(block_range.end, outputs_check_idx, GclExpr::default());
+        graph.add_block(format!("control {}", self.name), block_start);
+
+        params_range
+    }
+}
+
+impl IrTableDecl {
+    /// Applying a table runs one of its actions. The control plane decides
+    /// which entry matches, so any of the actions (or the default action) may
+    /// run, with any action data. Each application makes its own choice.
+    fn apply_to_gcl(
+        &self,
+        call_span: Span,
+        graph: &mut GclGraph,
+        metadata: &ProgramMetadata,
+    ) -> GclNodeRange {
+        let start_node = GclNode {
+            name: graph.create_name(&format!("table_apply__{}", self.id)),
+            commands: Vec::new(),
+            span: Some(self.span),
+        };
+        let start_idx = graph.add_node(start_node);
+        let end_node = GclNode {
+            name: graph.create_name(&format!("table_apply_end__{}", self.id)),
+            commands: Vec::new(),
+            span: Some(self.span),
+        };
+        let end_idx = graph.add_node(end_node);
+
+        let mut keys_end = start_idx;
+        let mut action_ids: &[VariableId] = &[];
+        let mut default_action = None;
+        for property in &self.properties {
+            match property {
+                IrTableProperty::Key(keys) => {
+                    // The keys are read in order to match them against the entries
+                    for key in keys {
+                        let (_, key_range) = key.expr.to_gcl(graph, metadata);
+                        graph.add_edge(keys_end, key_range.start, GclExpr::default());
+                        keys_end = key_range.end;
+                    }
+                }
+                IrTableProperty::Actions(ids) => action_ids = ids,
+                IrTableProperty::DefaultAction(action_call) => default_action = Some(action_call),
+            }
+        }
+
+        // The control plane's choice of action is an input to the program
+        let choice_loc = graph.fresh_mem_location(Some(format!("{}_action", self.id.1)));
+        let choice_guard = |index: usize| {
+            GclExpr::bin_op(
+                GclBinOp::Equals,
+                GclExpr::var(choice_loc.clone(), IrType::Base(IrBaseType::Int)),
+                GclExpr::int(BigInt::from(index), IrType::Base(IrBaseType::Int)),
+            )
+        };
+
+        for (action_idx, action_id) in action_ids.iter().enumerate() {
+            let action_params = graph
+                .get_action(action_id)
+                .unwrap_or_else(|| panic!("Unable to find action {}", action_id))
+                .params
+                .clone();
+
+            // The action data comes from the matching entry
+            let mut commands = Vec::new();
+            for param in &action_params {
+                let param_loc = graph.get_var_location(&param.id);
+                let action_data =
+                    graph.fresh_mem_location(Some(format!("{}.{}", action_id.1, param.id.1)));
+
+                commands.push(GclCommand::AddFact(GclFact::HasValue(param_loc.clone())));
+                commands.push(GclCommand::Assignment(GclAssignment {
+                    lvalue: GclLValue::Var(param_loc),
+                    expr: GclExpr::var(action_data, IrType::Base(param.ty.clone())),
+                }));
+            }
+
+            let entry_node = GclNode {
+                name: graph.create_name(&format!("table_entry__{}", action_id)),
+                commands,
+                span: Some(self.span),
+            };
+            let entry_idx = graph.add_node(entry_node);
+            let action_call = IrFunctionCall {
+                result_ty: IrBaseType::void(),
+                target: action_id.clone(),
+                arguments: Vec::new(),
+                span: self.span,
+            };
+            let call_range = action_call.to_gcl(graph, metadata);
+
+            graph.add_edge(keys_end, entry_idx, choice_guard(action_idx));
+            graph.add_edge(entry_idx, call_range.start, GclExpr::default());
+            graph.add_edge(call_range.end, end_idx, GclExpr::default());
+        }
+
+        // If no entry matches, the default action runs (NoAction if there is none)
+        let default_guard = choice_guard(action_ids.len());
+        let default_action_name = match default_action {
+            Some(action_call) => {
+                let call_range = action_call.to_gcl(graph, metadata);
+                graph.add_edge(keys_end, call_range.start, default_guard);
+                graph.add_edge(call_range.end, end_idx, GclExpr::default());
+                action_call.target.1.clone()
+            }
+            None => {
+                graph.add_edge(keys_end, end_idx, default_guard);
+                "NoAction".to_string()
+            }
+        };
+
+        let mut actions: Vec<_> = action_ids.iter().map(|id| id.1.clone()).collect();
+        actions.push(default_action_name);
+        graph.register_table_choice(
+            choice_loc,
+            TableChoice {
+                table: self.id.1.clone(),
+                actions,
+                span: self.span,
+                call_span,
+                iteration: 0,
+            },
+        );
 
         GclNodeRange {
-            start: param_init_node_idx,
-            end: param_end_node_idx,
+            start: start_idx,
+            end: end_idx,
+        }
+    }
+}
+
+impl ToGcl for IrParserDecl {
+    type Output = GclNodeRange;
+
+    fn to_gcl(&self, graph: &mut GclGraph, metadata: &ProgramMetadata) -> Self::Output {
+        let block_start = graph.block_start();
+        let params_range = make_param_nodes(graph, &self.params, "parser", self.span);
+
+        // The local declarations are set up before entering the start state
+        let local_decls = self
+            .local_decls
+            .iter()
+            .map(|local_decl| match local_decl {
+                IrParserLocalDecl::Variable(var_decl) => {
+                    IrStatementOrDecl::VariableDecl(var_decl.clone())
+                }
+                IrParserLocalDecl::Instantiation(instantiation) => {
+                    IrStatementOrDecl::Instantiation(instantiation.clone())
+                }
+            })
+            .collect();
+        let locals_range = IrBlockStatement(local_decls).to_gcl(graph, metadata);
+        graph.add_edge(params_range.start, locals_range.start, GclExpr::default());
+
+        // Accepted packets continue on to the next block, while rejected
+        // packets are dropped.
+        let accept_node = GclNode {
+            name: graph.create_name("parser_accept"),
+            commands: Vec::new(),
+            span: Some(self.span),
+        };
+        let accept_idx = graph.add_node(accept_node);
+        let reject_node = GclNode {
+            name: graph.create_name("parser_reject"),
+            commands: Vec::new(),
+            span: Some(self.span),
+        };
+        let reject_idx = graph.add_node(reject_node);
+        graph.add_edge(accept_idx, params_range.end, GclExpr::default());
+
+        let mut targets = HashMap::new();
+        targets.insert(IrTransitionTarget::Accept, accept_idx);
+        targets.insert(IrTransitionTarget::Reject, reject_idx);
+
+        // Convert the states which are reachable from the start state
+        let mut state_ends = Vec::new();
+        let mut queue = vec![IrTransitionTarget::State(self.start_state.clone())];
+        while let Some(target) = queue.pop() {
+            let state_id = match &target {
+                IrTransitionTarget::State(id) if !targets.contains_key(&target) => id,
+                _ => continue,
+            };
+            let state = self
+                .states
+                .iter()
+                .find(|state| state.id == *state_id)
+                .expect(
+                "Transition to unknown state. This should have been caught during type checking",
+            );
+
+            let state_block_start = graph.block_start();
+            let state_node = GclNode {
+                name: format!("parser_state__{}", state.id),
+                commands: Vec::new(),
+                span: Some(state.span),
+            };
+            let state_idx = graph.add_node(state_node);
+            let body_range = state.body.to_gcl(graph, metadata);
+            graph.add_edge(state_idx, body_range.start, GclExpr::default());
+            graph.add_block(format!("state {}", state.id.1), state_block_start);
+
+            targets.insert(target.clone(), state_idx);
+            state_ends.push((body_range.end, &state.transition));
+            queue.extend(state.transition.targets().into_iter().cloned());
+        }
+
+        // Connect the states now that they all exist
+        let start_target = IrTransitionTarget::State(self.start_state.clone());
+        graph.add_edge(locals_range.end, targets[&start_target], GclExpr::default());
+        for (state_end, transition) in state_ends {
+            transition.add_edges(graph, metadata, state_end, &targets);
+        }
+        graph.add_block(format!("parser {}", self.name), block_start);
+
+        params_range
+    }
+}
+
+impl IrTransition {
+    /// Get the states which this transition may go to
+    fn targets(&self) -> Vec<&IrTransitionTarget> {
+        match self {
+            IrTransition::Direct(target) => vec![target],
+            IrTransition::Select(select) => select.cases.iter().map(|case| &case.target).collect(),
+        }
+    }
+
+    /// Add the edges from the end of a state to the next states. If a select
+    /// has no matching case, the packet is rejected.
+    fn add_edges(
+        &self,
+        graph: &mut GclGraph,
+        metadata: &ProgramMetadata,
+        state_end: NodeIndex,
+        targets: &HashMap<IrTransitionTarget, NodeIndex>,
+    ) {
+        let select = match self {
+            IrTransition::Direct(target) => {
+                graph.add_edge(state_end, targets[target], GclExpr::default());
+                return;
+            }
+            IrTransition::Select(select) => select,
+        };
+
+        // Evaluate the keys
+        let mut current_end = state_end;
+        let mut keys = Vec::new();
+        for key in &select.keys {
+            let (key_loc, key_range) = key.to_gcl(graph, metadata);
+            graph.add_edge(current_end, key_range.start, GclExpr::default());
+            current_end = key_range.end;
+            keys.push(GclExpr::var(key_loc, key.ty.clone()));
+        }
+
+        // Build the predicate for each case. The first matching case is taken.
+        let mut edges = Vec::new();
+        let mut previous_cases_unmatched = GclExpr::bool(true);
+        for case in &select.cases {
+            let mut is_match = GclExpr::bool(true);
+
+            for (keyset, key) in case.keysets.iter().zip(&keys) {
+                if let IrKeyset::Value(value) = keyset {
+                    let (value_loc, value_range) = value.to_gcl(graph, metadata);
+                    graph.add_edge(current_end, value_range.start, GclExpr::default());
+                    current_end = value_range.end;
+
+                    let key_matches = GclExpr::bin_op(
+                        GclBinOp::Equals,
+                        key.clone(),
+                        GclExpr::var(value_loc, value.ty.clone()),
+                    );
+                    is_match = GclExpr::bin_op(GclBinOp::And, is_match, key_matches);
+                }
+            }
+
+            edges.push((
+                targets[&case.target],
+                GclExpr::bin_op(
+                    GclBinOp::And,
+                    previous_cases_unmatched.clone(),
+                    is_match.clone(),
+                ),
+            ));
+            previous_cases_unmatched =
+                GclExpr::bin_op(GclBinOp::And, previous_cases_unmatched, is_match.negate());
+        }
+        edges.push((
+            targets[&IrTransitionTarget::Reject],
+            previous_cases_unmatched,
+        ));
+
+        for (target_idx, predicate) in edges {
+            graph.add_edge(current_end, target_idx, predicate);
         }
     }
 }
@@ -168,23 +526,37 @@ impl ToGcl for IrActionDecl {
     type Output = GclNodeRange;
 
     fn to_gcl(&self, graph: &mut GclGraph, metadata: &ProgramMetadata) -> Self::Output {
+        let block_start = graph.block_start();
         let body_range = self.body.to_gcl(graph, metadata);
+        let start_name = graph.create_name(&format!("action__{}", self.id));
         let start_node_idx = graph.add_node(GclNode {
-            name: format!("action__{}", self.id),
+            name: start_name,
             // FIXME: remove ret hack
             commands: vec![GclCommand::Assignment(GclAssignment {
                 lvalue: GclLValue::Var(MemoryLocation::ReturnVal),
                 expr: GclExpr::bool(true),
             })],
+            span: Some(self.span),
         });
         graph.add_edge(start_node_idx, body_range.start, GclExpr::default());
 
-        // Note: the action is registered as a function with the graph in
-        // ControlDecl::to_gcl so it can be namespaced under the control block.
+        // The action's outputs must be set before it returns
+        let end_node = GclNode {
+            name: graph.create_name(&format!("action_end__{}", self.id)),
+            commands: Vec::new(),
+            span: Some(self.span),
+        };
+        let end_node_idx = graph.add_node(end_node);
+        let outputs_check_idx = assert_out_params_set(graph, &self.params, end_node_idx);
+        graph.add_edge(body_range.end, outputs_check_idx, GclExpr::default());
+        graph.add_block(format!("action {}", self.id.1), block_start);
+
+        // Note: the action is registered with the graph in ControlDecl::to_gcl,
+        // and its GCL is created at each call (see FunctionCall::to_gcl).
 
         GclNodeRange {
             start: start_node_idx,
-            end: body_range.end,
+            end: end_node_idx,
         }
     }
 }
@@ -206,6 +578,7 @@ impl ToGcl for IrBlockStatement {
             let node_idx = graph.add_node(GclNode {
                 name,
                 commands: std::mem::take(current_commands),
+                span: None,
             });
 
             // Hook up the node to the end of the node chain
@@ -295,6 +668,8 @@ impl ToGcl for IrStatement {
             IrStatement::If(if_statement) => if_statement.to_gcl(graph, metadata),
             IrStatement::Assignment(assignment) => assignment.to_gcl(graph, metadata),
             IrStatement::FunctionCall(func_call) => func_call.to_gcl(graph, metadata),
+            IrStatement::MethodCall(method_call) => method_call.to_gcl(graph, metadata),
+            IrStatement::Assert(assert) => assert.to_gcl(graph, metadata),
         }
     }
 }
@@ -309,15 +684,18 @@ impl ToGcl for IrVariableDecl {
         match self.value.as_ref() {
             Some(value) => {
                 let (value_loc, expr_range) = value.to_gcl(graph, metadata);
+                let mut commands = vec![
+                    GclCommand::Assignment(GclAssignment {
+                        lvalue: GclLValue::Var(loc.clone()),
+                        expr: GclExpr::var(value_loc, value.ty.clone()),
+                    }),
+                    GclCommand::AddFact(GclFact::HasValue(loc.clone())),
+                ];
+                commands.extend(copy_validity(graph, metadata, GclLValue::Var(loc), value));
                 let node_idx = graph.add_node(GclNode {
                     name,
-                    commands: vec![
-                        GclCommand::Assignment(GclAssignment {
-                            lvalue: GclLValue::Var(loc.clone()),
-                            expr: GclExpr::var(value_loc, value.ty.clone()),
-                        }),
-                        GclCommand::AddFact(GclFact::HasValue(loc)),
-                    ],
+                    commands,
+                    span: Some(self.span),
                 });
                 graph.add_edge(expr_range.end, node_idx, GclExpr::default());
 
@@ -327,9 +705,21 @@ impl ToGcl for IrVariableDecl {
                 }
             }
             None => {
+                // The declaration may run more than once (ex. in an action
+                // which is called twice), so forget the previous value and
+                // header validity
+                let mut commands = vec![GclCommand::RemoveFact(GclFact::HasValue(loc.clone()))];
+                if let IrType::Base(ty) = &self.ty {
+                    commands.extend(
+                        header_lvalues(GclLValue::Var(loc), ty)
+                            .into_iter()
+                            .map(|header| GclCommand::RemoveFact(GclFact::IsValid(header))),
+                    );
+                }
                 let node_idx = graph.add_node(GclNode {
                     name,
-                    commands: Vec::new(),
+                    commands,
+                    span: Some(self.span),
                 });
 
                 GclNodeRange {
@@ -353,6 +743,37 @@ impl ToGcl for IrInstantiation {
     }
 }
 
+impl ToGcl for IrAssert {
+    type Output = GclNodeRange;
+
+    fn to_gcl(&self, graph: &mut GclGraph, metadata: &ProgramMetadata) -> Self::Output {
+        let (condition_loc, cond_range) = self.condition.to_gcl(graph, metadata);
+        let end_node = GclNode {
+            name: graph.create_name("assert_end"),
+            commands: Vec::new(),
+            span: Some(self.span),
+        };
+        let end_node_idx = graph.add_node(end_node);
+
+        let assert_idx = make_assert_node(
+            graph,
+            GclExpr::var(condition_loc, self.condition.ty.clone()),
+            end_node_idx,
+            GclBug {
+                kind: BugKind::UserAssert,
+                variable: None,
+                span: self.span,
+            },
+        );
+        graph.add_edge(cond_range.end, assert_idx, GclExpr::default());
+
+        GclNodeRange {
+            start: cond_range.start,
+            end: end_node_idx,
+        }
+    }
+}
+
 impl ToGcl for IrAssignment {
     /// The GCL node
     type Output = GclNodeRange;
@@ -362,18 +783,37 @@ impl ToGcl for IrAssignment {
         let (loc, expr_range) = self.value.to_gcl(graph, metadata);
         let node_name = graph.create_name(&format!("assignment__{}", lvalue));
 
+        let mut commands = vec![
+            GclCommand::AddFact(GclFact::HasValue(lvalue.mem_location())),
+            GclCommand::Assignment(GclAssignment {
+                lvalue: lvalue.clone(),
+                expr: GclExpr::var(loc, self.value.ty.clone()),
+            }),
+        ];
+        commands.extend(copy_validity(graph, metadata, lvalue, &self.value));
         let node_idx = graph.add_node(GclNode {
             name: node_name,
-            commands: vec![
-                GclCommand::AddFact(GclFact::HasValue(lvalue.mem_location())),
-                GclCommand::Assignment(GclAssignment {
-                    lvalue,
-                    expr: GclExpr::var(loc, self.value.ty.clone()),
-                }),
-            ],
+            commands,
+            span: Some(self.span),
         });
 
-        graph.add_edge(expr_range.end, node_idx, GclExpr::default());
+        // Writing to a field of an invalid header is a bug
+        let mut assignment_start = node_idx;
+        for header in self.lvalue.accessed_headers().into_iter().rev() {
+            let bug = GclBug {
+                kind: BugKind::InvalidHeaderAccess,
+                variable: Some(header.to_string()),
+                span: self.lvalue.span,
+            };
+            let header = header.to_gcl(graph, metadata);
+            assignment_start = make_assert_node(
+                graph,
+                GclExpr::fact(GclFact::IsValid(header)),
+                assignment_start,
+                bug,
+            );
+        }
+        graph.add_edge(expr_range.end, assignment_start, GclExpr::default());
 
         GclNodeRange {
             start: expr_range.start,
@@ -395,10 +835,27 @@ impl ToGcl for IrLValue {
             IrLValueData::Field(target, field) => {
                 let target_lvalue = target.to_gcl(graph, metadata);
 
-                // TODO: If this is a header, do we need to check validity?
-                //       Or do that somewhere else, like in field access expr?
+                // Note: header validity is checked by the assignment
 
-                GclLValue::Field(Box::new(target_lvalue), field.clone())
+                GclLValue::Field(Box::new(target_lvalue), target.ty.clone(), field.clone())
+            }
+        }
+    }
+}
+
+impl IrLValue {
+    /// Get the headers whose fields are accessed by this lvalue
+    fn accessed_headers(&self) -> Vec<&IrLValue> {
+        match &self.data {
+            IrLValueData::Var(_) => Vec::new(),
+            IrLValueData::Field(target, _) => {
+                let mut headers = target.accessed_headers();
+
+                if let IrType::Base(IrBaseType::Header { .. }) = target.ty {
+                    headers.push(target);
+                }
+
+                headers
             }
         }
     }
@@ -412,6 +869,7 @@ impl ToGcl for IrIfStatement {
         let end_node = GclNode {
             name: graph.create_name("if_end"),
             commands: Vec::new(),
+            span: Some(self.span),
         };
         let end_node_idx = graph.add_node(end_node);
 
@@ -461,7 +919,25 @@ impl ToGcl for IrExpr {
         match &self.data {
             IrExprData::Bool(b) => {
                 let loc = graph.fresh_mem_location(None);
-                let node_idx = Self::single_assignment_node(graph, loc.clone(), GclExpr::bool(*b));
+                let node_idx =
+                    Self::single_assignment_node(graph, loc.clone(), GclExpr::bool(*b), self.span);
+
+                (
+                    loc,
+                    GclNodeRange {
+                        start: node_idx,
+                        end: node_idx,
+                    },
+                )
+            }
+            IrExprData::Int(value) => {
+                let loc = graph.fresh_mem_location(None);
+                let node_idx = Self::single_assignment_node(
+                    graph,
+                    loc.clone(),
+                    GclExpr::int(value.clone(), self.ty.clone()),
+                    self.span,
+                );
 
                 (
                     loc,
@@ -477,12 +953,18 @@ impl ToGcl for IrExpr {
                 let node = GclNode {
                     name: graph.create_name("expr_var"),
                     commands: Vec::new(),
+                    span: Some(self.span),
                 };
                 let node_idx = graph.add_node(node);
                 let assert_idx = make_assert_node(
                     graph,
                     GclExpr::fact(GclFact::HasValue(loc.clone())),
                     node_idx,
+                    GclBug {
+                        kind: BugKind::UninitializedRead,
+                        variable: Some(var.1.clone()),
+                        span: self.span,
+                    },
                 );
 
                 (
@@ -494,31 +976,81 @@ impl ToGcl for IrExpr {
                 )
             }
             IrExprData::And(left, right) => {
-                Self::short_circuit_logic(graph, metadata, left, right, true)
+                Self::short_circuit_logic(graph, metadata, left, right, true, self.span)
             }
             IrExprData::Or(left, right) => {
-                Self::short_circuit_logic(graph, metadata, left, right, false)
+                Self::short_circuit_logic(graph, metadata, left, right, false, self.span)
             }
             IrExprData::Negation(inner) => {
                 let (inner_loc, inner_range) = inner.to_gcl(graph, metadata);
                 let inner_pred = GclExpr::var(inner_loc, inner.ty.clone());
                 let loc = graph.fresh_mem_location(None);
-                let name = graph.create_name("expr");
-
-                let true_idx =
-                    Self::single_assignment_node(graph, loc.clone(), GclExpr::bool(true));
-                let false_idx =
-                    Self::single_assignment_node(graph, loc.clone(), GclExpr::bool(false));
+                let node_idx = Self::single_assignment_node(
+                    graph,
+                    loc.clone(),
+                    inner_pred.negate(),
+                    self.span,
+                );
+                graph.add_edge(inner_range.end, node_idx, GclExpr::default());
 
-                let node_idx = graph.add_node(GclNode {
-                    name,
-                    commands: Vec::new(),
-                });
+                (
+                    loc,
+                    GclNodeRange {
+                        start: inner_range.start,
+                        end: node_idx,
+                    },
+                )
+            }
+            IrExprData::BinOp(op, left, right) => {
+                let (left_loc, left_range) = left.to_gcl(graph, metadata);
+                let (right_loc, right_range) = right.to_gcl(graph, metadata);
+                let loc = graph.fresh_mem_location(None);
+                let node = GclNode {
+                    name: graph.create_name("expr_bin_op"),
+                    commands: vec![GclCommand::Assignment(GclAssignment {
+                        lvalue: GclLValue::Var(loc.clone()),
+                        expr: GclExpr {
+                            ty: self.ty.clone(),
+                            data: GclExprData::BinOp(
+                                (*op).into(),
+                                Box::new(GclExpr::var(left_loc, left.ty.clone())),
+                                Box::new(GclExpr::var(right_loc, right.ty.clone())),
+                            ),
+                        },
+                    })],
+                    span: Some(self.span),
+                };
+                let node_idx = graph.add_node(node);
+                graph.add_edge(left_range.end, right_range.start, GclExpr::default());
+                graph.add_edge(right_range.end, node_idx, GclExpr::default());
 
-                graph.add_edge(inner_range.end, true_idx, inner_pred.negate());
-                graph.add_edge(inner_range.end, false_idx, inner_pred);
-                graph.add_edge(true_idx, node_idx, GclExpr::default());
-                graph.add_edge(false_idx, node_idx, GclExpr::default());
+                (
+                    loc,
+                    GclNodeRange {
+                        start: left_range.start,
+                        end: node_idx,
+                    },
+                )
+            }
+            IrExprData::UnOp(op, inner) => {
+                let (inner_loc, inner_range) = inner.to_gcl(graph, metadata);
+                let loc = graph.fresh_mem_location(None);
+                let node = GclNode {
+                    name: graph.create_name("expr_un_op"),
+                    commands: vec![GclCommand::Assignment(GclAssignment {
+                        lvalue: GclLValue::Var(loc.clone()),
+                        expr: GclExpr {
+                            ty: self.ty.clone(),
+                            data: GclExprData::UnOp(
+                                (*op).into(),
+                                Box::new(GclExpr::var(inner_loc, inner.ty.clone())),
+                            ),
+                        },
+                    })],
+                    span: Some(self.span),
+                };
+                let node_idx = graph.add_node(node);
+                graph.add_edge(inner_range.end, node_idx, GclExpr::default());
 
                 (
                     loc,
@@ -537,6 +1069,7 @@ impl ToGcl for IrExpr {
                         lvalue: GclLValue::Var(loc.clone()),
                         expr: GclExpr::var(MemoryLocation::ReturnVal, self.ty.clone()),
                     })],
+                    span: Some(self.span),
                 };
                 let node_idx = graph.add_node(node);
                 graph.add_edge(func_range.end, node_idx, GclExpr::default());
@@ -549,6 +1082,36 @@ impl ToGcl for IrExpr {
                     },
                 )
             }
+            IrExprData::MethodCall(method_call) => {
+                let method_range = method_call.to_gcl(graph, metadata);
+                let loc = graph.fresh_mem_location(None);
+
+                match method_call {
+                    IrMethodCall::IsValid(header) => {
+                        let header = header.to_gcl(graph, metadata);
+                        let node_idx = Self::single_assignment_node(
+                            graph,
+                            loc.clone(),
+                            GclExpr::fact(GclFact::IsValid(header)),
+                            self.span,
+                        );
+                        graph.add_edge(method_range.end, node_idx, GclExpr::default());
+
+                        (
+                            loc,
+                            GclNodeRange {
+                                start: method_range.start,
+                                end: node_idx,
+                            },
+                        )
+                    }
+                    // These methods don't return a value
+                    IrMethodCall::SetValid(_)
+                    | IrMethodCall::SetInvalid(_)
+                    | IrMethodCall::Extract(_)
+                    | IrMethodCall::Apply(..) => (loc, method_range),
+                }
+            }
             IrExprData::FieldAccess(target, field) => {
                 let (target_loc, target_range) = target.to_gcl(graph, metadata);
                 let loc = graph.fresh_mem_location(None);
@@ -564,9 +1127,29 @@ impl ToGcl for IrExpr {
                             ),
                         },
                     })],
+                    span: Some(self.span),
                 };
                 let node_idx = graph.add_node(node);
-                graph.add_edge(target_range.end, node_idx, GclExpr::default());
+
+                // Reading a field of an invalid header is a bug
+                let access_start = match (&target.ty, target.as_lvalue()) {
+                    (IrType::Base(IrBaseType::Header { .. }), Some(header)) => {
+                        let bug = GclBug {
+                            kind: BugKind::InvalidHeaderAccess,
+                            variable: Some(header.to_string()),
+                            span: self.span,
+                        };
+                        let header = header.to_gcl(graph, metadata);
+                        make_assert_node(
+                            graph,
+                            GclExpr::fact(GclFact::IsValid(header)),
+                            node_idx,
+                            bug,
+                        )
+                    }
+                    _ => node_idx,
+                };
+                graph.add_edge(target_range.end, access_start, GclExpr::default());
 
                 (
                     loc,
@@ -611,6 +1194,7 @@ impl ToGcl for IrExpr {
                             data: GclExprData::Struct { fields: fields_gcl },
                         },
                     })],
+                    span: Some(self.span),
                 };
                 let node_idx = graph.add_node(node);
 
@@ -640,6 +1224,7 @@ impl IrExpr {
         graph: &mut GclGraph,
         location: MemoryLocation,
         value: GclExpr,
+        span: Span,
     ) -> NodeIndex {
         let name = graph.create_name("expr");
         graph.add_node(GclNode {
@@ -648,6 +1233,7 @@ impl IrExpr {
                 lvalue: GclLValue::Var(location),
                 expr: value,
             })],
+            span: Some(span),
         })
     }
 
@@ -659,11 +1245,48 @@ impl IrExpr {
         left: &IrExpr,
         right: &IrExpr,
         is_add: bool,
+        span: Span,
     ) -> (MemoryLocation, GclNodeRange) {
         let (left_loc, left_range) = left.to_gcl(graph, metadata);
         let (right_loc, right_range) = right.to_gcl(graph, metadata);
         let result_loc = graph.fresh_mem_location(None);
         let op_name = if is_add { "add" } else { "or" };
+        let left_pred = GclExpr::var(left_loc, left.ty.clone());
+        let left_pred_negated = left_pred.negate();
+        let (left_to_right, left_to_set) = if is_add {
+            (left_pred.clone(), left_pred_negated)
+        } else {
+            (left_pred_negated, left_pred.clone())
+        };
+
+        // If the right side can be read after the branches join, compute the
+        // result there. Otherwise the result would be true on one branch and
+        // false on the other, and the value would no longer be tied to the
+        // condition which picked the branch.
+        if let Some(right_value) = right.pure_value(graph, metadata) {
+            let op = if is_add { GclBinOp::And } else { GclBinOp::Or };
+            let end_node = GclNode {
+                name: graph.create_name(&format!("{}_expr_end", op_name)),
+                commands: vec![GclCommand::Assignment(GclAssignment {
+                    lvalue: GclLValue::Var(result_loc.clone()),
+                    expr: GclExpr::bin_op(op, left_pred, right_value),
+                })],
+                span: Some(span),
+            };
+            let end_node_idx = graph.add_node(end_node);
+
+            graph.add_edge(left_range.end, right_range.start, left_to_right);
+            graph.add_edge(left_range.end, end_node_idx, left_to_set);
+            graph.add_edge(right_range.end, end_node_idx, GclExpr::default());
+
+            return (
+                result_loc,
+                GclNodeRange {
+                    start: left_range.start,
+                    end: end_node_idx,
+                },
+            );
+        }
 
         let true_node = GclNode {
             name: graph.create_name(&format!("{}_expr_true", op_name)),
@@ -671,6 +1294,7 @@ impl IrExpr {
                 lvalue: GclLValue::Var(result_loc.clone()),
                 expr: GclExpr::bool(true),
             })],
+            span: Some(span),
         };
         let false_node = GclNode {
             name: graph.create_name(&format!("{}_expr_false", op_name)),
@@ -678,26 +1302,20 @@ impl IrExpr {
                 lvalue: GclLValue::Var(result_loc.clone()),
                 expr: GclExpr::bool(false),
             })],
+            span: Some(span),
         };
         let end_node = GclNode {
             name: graph.create_name(&format!("{}_expr_end", op_name)),
             commands: Vec::new(),
+            span: Some(span),
         };
 
         let true_node_idx = graph.add_node(true_node);
         let false_node_idx = graph.add_node(false_node);
         let end_node_idx = graph.add_node(end_node);
-        let left_pred = GclExpr::var(left_loc, left.ty.clone());
         let right_pred = GclExpr::var(right_loc, right.ty.clone());
-        let left_pred_negated = left_pred.negate();
         let right_pred_negated = right_pred.negate();
 
-        let (left_to_right, left_to_set) = if is_add {
-            (left_pred, left_pred_negated)
-        } else {
-            (left_pred_negated, left_pred)
-        };
-
         graph.add_edge(left_range.end, right_range.start, left_to_right);
         graph.add_edge(
             left_range.end,
@@ -721,62 +1339,357 @@ impl IrExpr {
             },
         )
     }
+
+    /// Get the value of the expression as a single GCL expression over the
+    /// variables it reads, if evaluating it has no side effects (ex. calls).
+    /// The expression's checks (ex. reading an uninitialized variable) still
+    /// need its nodes.
+    fn pure_value(&self, graph: &mut GclGraph, metadata: &ProgramMetadata) -> Option<GclExpr> {
+        let data = match &self.data {
+            IrExprData::Bool(b) => return Some(GclExpr::bool(*b)),
+            IrExprData::Int(value) => return Some(GclExpr::int(value.clone(), self.ty.clone())),
+            IrExprData::Var(var) => {
+                return Some(GclExpr::var(graph.get_var_location(var), self.ty.clone()))
+            }
+            IrExprData::And(left, right) => GclExprData::BinOp(
+                GclBinOp::And,
+                Box::new(left.pure_value(graph, metadata)?),
+                Box::new(right.pure_value(graph, metadata)?),
+            ),
+            IrExprData::Or(left, right) => GclExprData::BinOp(
+                GclBinOp::Or,
+                Box::new(left.pure_value(graph, metadata)?),
+                Box::new(right.pure_value(graph, metadata)?),
+            ),
+            IrExprData::Negation(inner) => {
+                return Some(inner.pure_value(graph, metadata)?.negate())
+            }
+            IrExprData::BinOp(op, left, right) => GclExprData::BinOp(
+                (*op).into(),
+                Box::new(left.pure_value(graph, metadata)?),
+                Box::new(right.pure_value(graph, metadata)?),
+            ),
+            IrExprData::UnOp(op, inner) => {
+                GclExprData::UnOp((*op).into(), Box::new(inner.pure_value(graph, metadata)?))
+            }
+            IrExprData::MethodCall(IrMethodCall::IsValid(header)) => {
+                GclExprData::Fact(GclFact::IsValid(header.to_gcl(graph, metadata)))
+            }
+            IrExprData::FieldAccess(target, field) => GclExprData::FieldAccess(
+                Box::new(target.pure_value(graph, metadata)?),
+                field.clone(),
+            ),
+            IrExprData::Struct(fields) => GclExprData::Struct {
+                fields: fields
+                    .iter()
+                    .map(|(name, value)| Some((name.clone(), value.pure_value(graph, metadata)?)))
+                    .collect::<Option<_>>()?,
+            },
+            IrExprData::FunctionCall(_) | IrExprData::MethodCall(_) => return None,
+        };
+
+        Some(GclExpr {
+            ty: self.ty.clone(),
+            data,
+        })
+    }
 }
 
 impl ToGcl for IrFunctionCall {
     type Output = GclNodeRange;
 
-    fn to_gcl(&self, graph: &mut GclGraph, _metadata: &ProgramMetadata) -> Self::Output {
-        // TODO: handle setting arguments and verifying args have values
-        let function_range = graph
-            .get_function(&self.target)
-            .unwrap_or_else(|| panic!("Unable to find function {}", self.target));
+    fn to_gcl(&self, graph: &mut GclGraph, metadata: &ProgramMetadata) -> Self::Output {
+        let action = graph
+            .get_action(&self.target)
+            .unwrap_or_else(|| panic!("Unable to find function {}", self.target))
+            .clone();
+
+        // Evaluate the arguments and copy them into the parameters. Outputs
+        // are copied back into the arguments after the call.
+        let mut args_range: Option<GclNodeRange> = None;
+        let mut copy_in_commands = Vec::new();
+        let mut copy_out_commands = Vec::new();
+        for (param, argument) in bind_arguments(&action.params, &self.arguments) {
+            let param_loc = graph.get_var_location(&param.id);
+            let param_ty = IrType::Base(param.ty.clone());
+            let value = match argument {
+                IrArgument::Value(value) | IrArgument::Named(_, value) => value,
+                IrArgument::DontCare => {
+                    copy_in_commands.push(GclCommand::RemoveFact(GclFact::HasValue(param_loc)));
+                    continue;
+                }
+            };
 
+            if param.direction == Direction::Out {
+                // The parameter starts out uninitialized
+                copy_in_commands.push(GclCommand::RemoveFact(GclFact::HasValue(param_loc.clone())));
+            } else {
+                let (value_loc, value_range) = value.to_gcl(graph, metadata);
+                match &mut args_range {
+                    Some(range) => {
+                        graph.add_edge(range.end, value_range.start, GclExpr::default());
+                        range.end = value_range.end;
+                    }
+                    None => args_range = Some(value_range),
+                }
+
+                copy_in_commands.push(GclCommand::AddFact(GclFact::HasValue(param_loc.clone())));
+                copy_in_commands.push(GclCommand::Assignment(GclAssignment {
+                    lvalue: GclLValue::Var(param_loc.clone()),
+                    expr: GclExpr::var(value_loc, value.ty.clone()),
+                }));
+                copy_in_commands.extend(copy_validity(
+                    graph,
+                    metadata,
+                    GclLValue::Var(param_loc.clone()),
+                    value,
+                ));
+            }
+
+            if let Direction::Out | Direction::InOut = param.direction {
+                let target = value
+                    .as_lvalue()
+                    .expect("Output argument is not an lvalue. This should have been caught during type checking")
+                    .to_gcl(graph, metadata);
+                let param_expr = IrExpr {
+                    ty: param_ty.clone(),
+                    data: IrExprData::Var(param.id.clone()),
+                    span: param.span,
+                };
+
+                // The argument only has a value if the parameter was written
+                let has_value = match &target {
+                    GclLValue::Var(_) => GclExpr::fact(GclFact::HasValue(param_loc.clone())),
+                    // Only part of the variable is written
+                    GclLValue::Field(..) => GclExpr::bin_op(
+                        GclBinOp::Or,
+                        GclExpr::fact(GclFact::HasValue(target.mem_location())),
+                        GclExpr::fact(GclFact::HasValue(param_loc.clone())),
+                    ),
+                };
+                copy_out_commands.push(GclCommand::SetFact(
+                    GclFact::HasValue(target.mem_location()),
+                    has_value,
+                ));
+                copy_out_commands.push(GclCommand::Assignment(GclAssignment {
+                    lvalue: target.clone(),
+                    expr: GclExpr::var(param_loc, param_ty),
+                }));
+                copy_out_commands.extend(copy_validity(graph, metadata, target, &param_expr));
+            }
+        }
+
+        // Each call gets its own copy of the action's body
+        let function_range = action.to_gcl(graph, metadata);
         let start_name = graph.create_name("func_call_start");
         let end_name = graph.create_name("func_call_end");
-        let ret_target_var = graph.fresh_mem_location(None);
         let start_idx = graph.add_node(GclNode {
             name: start_name,
-            commands: vec![GclCommand::Assignment(GclAssignment {
-                lvalue: GclLValue::Var(ret_target_var.clone()),
-                expr: GclExpr::string(end_name.clone()),
-            })],
+            commands: copy_in_commands,
+            span: Some(self.span),
         });
         let end_idx = graph.add_node(GclNode {
-            name: end_name.clone(),
-            commands: Vec::new(),
+            name: end_name,
+            commands: copy_out_commands,
+            span: Some(self.span),
         });
 
         graph.add_edge(start_idx, function_range.start, GclExpr::default());
-        graph.add_edge(
-            function_range.end,
-            end_idx,
-            GclExpr::bin_op(
-                GclBinOp::Equals,
-                GclExpr::var(ret_target_var, IrType::string()),
-                GclExpr::string(end_name),
-            ),
-        );
+        graph.add_edge(function_range.end, end_idx, GclExpr::default());
+
+        let call_start = match args_range {
+            Some(args_range) => {
+                graph.add_edge(args_range.end, start_idx, GclExpr::default());
+                args_range.start
+            }
+            None => start_idx,
+        };
 
         GclNodeRange {
-            start: start_idx,
+            start: call_start,
             end: end_idx,
         }
     }
 }
 
+/// Pair each argument of a call with the parameter it is passed to
+fn bind_arguments<'a>(
+    params: &'a [IrParam],
+    arguments: &'a [IrArgument],
+) -> Vec<(&'a IrParam, &'a IrArgument)> {
+    arguments
+        .iter()
+        .enumerate()
+        .map(|(i, argument)| {
+            let param = match argument {
+                IrArgument::Named(param_id, _) => {
+                    params.iter().find(|param| param.id == *param_id).expect(
+                        "Unknown named argument. This should have been caught during type checking",
+                    )
+                }
+                IrArgument::Value(_) | IrArgument::DontCare => &params[i],
+            };
+
+            (param, argument)
+        })
+        .collect()
+}
+
+impl ToGcl for IrMethodCall {
+    type Output = GclNodeRange;
+
+    fn to_gcl(&self, graph: &mut GclGraph, metadata: &ProgramMetadata) -> Self::Output {
+        let commands = match self {
+            // The validity is read by the expression using the result
+            IrMethodCall::IsValid(_) => Vec::new(),
+            IrMethodCall::SetValid(header) => {
+                vec![GclCommand::AddFact(GclFact::IsValid(
+                    header.to_gcl(graph, metadata),
+                ))]
+            }
+            IrMethodCall::SetInvalid(header) => {
+                vec![GclCommand::RemoveFact(GclFact::IsValid(
+                    header.to_gcl(graph, metadata),
+                ))]
+            }
+            IrMethodCall::Extract(header) => {
+                // The header is filled in with unknown data from the packet
+                let header_lvalue = header.to_gcl(graph, metadata);
+                let packet_data = graph.fresh_mem_location(Some("packet".to_string()));
+
+                vec![
+                    GclCommand::AddFact(GclFact::HasValue(header_lvalue.mem_location())),
+                    GclCommand::Assignment(GclAssignment {
+                        lvalue: header_lvalue.clone(),
+                        expr: GclExpr::var(packet_data, header.ty.clone()),
+                    }),
+                    GclCommand::AddFact(GclFact::IsValid(header_lvalue)),
+                ]
+            }
+            IrMethodCall::Apply(table_id, call_span) => {
+                let table = graph
+                    .get_table(table_id)
+                    .cloned()
+                    .unwrap_or_else(|| panic!("Unable to find table {}", table_id));
+                return table.apply_to_gcl(*call_span, graph, metadata);
+            }
+        };
+        let span = match self {
+            IrMethodCall::IsValid(header)
+            | IrMethodCall::SetValid(header)
+            | IrMethodCall::SetInvalid(header)
+            | IrMethodCall::Extract(header) => header.span,
+            IrMethodCall::Apply(..) => unreachable!("Tables are applied above"),
+        };
+        let node = GclNode {
+            name: graph.create_name("method_call"),
+            commands,
+            span: Some(span),
+        };
+        let node_idx = graph.add_node(node);
+
+        GclNodeRange {
+            start: node_idx,
+            end: node_idx,
+        }
+    }
+}
+
+/// Get the headers stored in a value of the given type, such as the header
+/// fields of a struct.
+fn header_lvalues(lvalue: GclLValue, ty: &IrBaseType) -> Vec<GclLValue> {
+    match ty {
+        IrBaseType::Header { .. } => vec![lvalue],
+        IrBaseType::Struct { fields } => fields
+            .iter()
+            .flat_map(|(field_ty, field)| {
+                let field_lvalue = GclLValue::Field(
+                    Box::new(lvalue.clone()),
+                    IrType::Base(ty.clone()),
+                    field.clone(),
+                );
+
+                header_lvalues(field_lvalue, field_ty)
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// When assigning a value containing headers, the validity of each header is
+/// copied from the value.
+fn copy_validity(
+    graph: &mut GclGraph,
+    metadata: &ProgramMetadata,
+    target: GclLValue,
+    value: &IrExpr,
+) -> Vec<GclCommand> {
+    let (value_ty, value_lvalue) = match (&value.ty, value.as_lvalue()) {
+        (IrType::Base(ty), Some(lvalue)) => (ty, lvalue.to_gcl(graph, metadata)),
+        // Header values can only come from other variables
+        _ => return Vec::new(),
+    };
+
+    header_lvalues(target, value_ty)
+        .into_iter()
+        .zip(header_lvalues(value_lvalue, value_ty))
+        .map(|(target_header, value_header)| {
+            GclCommand::SetFact(
+                GclFact::IsValid(target_header),
+                GclExpr::fact(GclFact::IsValid(value_header)),
+            )
+        })
+        .collect()
+}
+
+/// Assert that each `out` parameter has been given a value before jumping to
+/// `next_node`. Returns the node to jump to instead.
+fn assert_out_params_set(
+    graph: &mut GclGraph,
+    params: &[IrParam],
+    next_node: NodeIndex,
+) -> NodeIndex {
+    let mut start_idx = next_node;
+
+    for param in params.iter().rev() {
+        if param.direction == Direction::Out {
+            let loc = graph.get_var_location(&param.id);
+            start_idx = make_assert_node(
+                graph,
+                GclExpr::fact(GclFact::HasValue(loc)),
+                start_idx,
+                GclBug {
+                    kind: BugKind::UnsetOutParam,
+                    variable: Some(param.id.1.clone()),
+                    span: param.span,
+                },
+            );
+        }
+    }
+
+    start_idx
+}
+
 /// Create an assertion node which, when the predicate is true, jumps to
 /// the `next_node`, otherwise jumps to a new "bug" node.
-fn make_assert_node(graph: &mut GclGraph, predicate: GclExpr, next_node: NodeIndex) -> NodeIndex {
+fn make_assert_node(
+    graph: &mut GclGraph,
+    predicate: GclExpr,
+    next_node: NodeIndex,
+    bug: GclBug,
+) -> NodeIndex {
+    let span = Some(bug.span);
     let bug_node = GclNode {
         name: graph.create_name("bug"),
-        commands: vec![GclCommand::Bug],
+        commands: vec![GclCommand::Bug(bug)],
+        span,
     };
     let bug_node_idx = graph.add_node(bug_node);
 
     let assert_node = GclNode {
         name: graph.create_name("assert"),
         commands: Vec::new(),
+        span,
     };
     let assert_node_idx = graph.add_node(assert_node);
 
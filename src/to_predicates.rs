@@ -2,6 +2,8 @@ use crate::gcl::{
     GclAssignment, GclBinOp, GclCommand, GclExpr, GclExprData, GclFact, GclGraph, GclLValue,
     MemoryLocation,
 };
+use crate::ir::{IrBaseType, IrType};
+use num_bigint::BigInt;
 use petgraph::graph::NodeIndex;
 use petgraph::visit::{EdgeRef, Topo};
 use petgraph::Direction;
@@ -11,7 +13,7 @@ use std::ops::Deref;
 
 pub type PredicateMap = HashMap<NodeIndex, GclExpr>;
 pub type VariableMap = HashMap<NodeIndex, HashMap<MemoryLocation, HashSet<GclExpr>>>;
-pub type FactSets = HashMap<NodeIndex, HashSet<GclFact>>;
+pub type FactSets = HashMap<NodeIndex, HashMap<GclFact, HashSet<GclExpr>>>;
 
 impl GclGraph {
     pub fn to_reachability_predicates(&self) -> (PredicateMap, VariableMap) {
@@ -33,57 +35,9 @@ impl GclGraph {
                 .edges_directed(node_idx, Direction::Incoming)
                 .map(|edge| node_facts.get(&edge.source()).unwrap())
                 .collect();
-            let mut current_variables: HashMap<MemoryLocation, HashSet<GclExpr>> = HashMap::new();
-
-            // Add all of the common variables among the parents into the current
-            // node's variable cache.
-            for variables in &parent_variables {
-                for (loc, values) in *variables {
-                    if parent_variables.iter().all(|map| map.contains_key(loc)) {
-                        current_variables
-                            .entry(loc.clone())
-                            .or_default()
-                            .extend(values.iter().cloned());
-                    }
-                }
-            }
-
-            // TODO: add some notion of uncertainty if a fact is not shared by all parents?
-            // Add all of the common facts among the parents into the current
-            // node's facts
-            let mut current_facts = parent_facts
-                .into_iter()
-                .cloned()
-                .reduce(|acc, next| acc.intersection(&next).cloned().collect())
-                .unwrap_or_default();
-
-            // Consider this node's variable assignments
-            for cmd in &node.commands {
-                let (lvalue, expr) = match cmd {
-                    GclCommand::Assignment(GclAssignment { lvalue, expr }) => (lvalue, expr),
-                    GclCommand::AddFact(fact) => {
-                        current_facts.insert(fact.clone());
-                        continue;
-                    }
-                    GclCommand::RemoveFact(fact) => {
-                        current_facts.remove(fact);
-                        continue;
-                    }
-                    GclCommand::Bug => continue,
-                };
-
-                let loc = lvalue.mem_location();
-                let values = expr.fill_in(&current_variables, &current_facts);
-                let new_exprs = values
-                    .into_iter()
-                    .map(|value| expr.update_by_lvalue(lvalue, value))
-                    .collect();
-
-                current_variables.insert(loc, new_exprs);
-            }
 
             // Calculate the predicate for each incoming edge
-            let edge_preds = self
+            let edge_preds: Vec<_> = self
                 .edges_directed(node_idx, Direction::Incoming)
                 .map(|edge| {
                     let parent_idx = edge.source();
@@ -100,10 +54,126 @@ impl GclGraph {
                     // The parent's predicate and this edge's predicate have to
                     // be true in order for the node to be reachable via this edge
                     GclExpr::bin_op(GclBinOp::And, parent_pred.clone(), edge_pred)
-                });
+                })
+                .collect();
+
+            // Add all of the facts known to the parents into the current
+            // node's facts. If the parents disagree on a fact, its value
+            // depends on which edge was taken to get here, which is tracked
+            // by the node's edge selector.
+            let edge_selector = GclExpr::var(
+                MemoryLocation::EdgeSelector(node_idx),
+                IrType::Base(IrBaseType::Int),
+            );
+            let mut uses_edge_selector = false;
+            let mut current_facts: HashMap<GclFact, HashSet<GclExpr>> = HashMap::new();
+            for facts in &parent_facts {
+                for fact in facts.keys() {
+                    if current_facts.contains_key(fact) {
+                        continue;
+                    }
+
+                    let parent_values: Vec<_> =
+                        parent_facts.iter().map(|facts| facts.get(fact)).collect();
+                    let values = if parent_values.iter().all(|values| *values == parent_values[0]) {
+                        parent_values[0].cloned().unwrap()
+                    } else {
+                        uses_edge_selector = true;
+                        let value = parent_values
+                            .into_iter()
+                            .enumerate()
+                            .map(|(edge_num, values)| {
+                                let value = values
+                                    .into_iter()
+                                    .flatten()
+                                    .cloned()
+                                    .reduce(|acc, next| GclExpr::bin_op(GclBinOp::Or, acc, next))
+                                    .unwrap_or_else(|| GclExpr::bool(false));
+
+                                GclExpr::bin_op(
+                                    GclBinOp::And,
+                                    is_edge_taken(&edge_selector, edge_num),
+                                    value,
+                                )
+                            })
+                            .reduce(|acc, next| GclExpr::bin_op(GclBinOp::Or, acc, next))
+                            .unwrap();
+
+                        HashSet::from_iter(Some(value))
+                    };
+
+                    current_facts.insert(fact.clone(), values);
+                }
+            }
+
+            // Add all of the common variables among the parents into the current
+            // node's variable cache. If the parents disagree on a variable, it
+            // gets a new value here, which is equal to one of the values from the
+            // edge that was taken.
+            let mut current_variables: HashMap<MemoryLocation, HashSet<GclExpr>> = HashMap::new();
+            let mut edge_constraints: Vec<Vec<GclExpr>> = vec![Vec::new(); parent_variables.len()];
+            let mut common_locations: Vec<_> = parent_variables
+                .first()
+                .into_iter()
+                .flat_map(|variables| variables.keys())
+                .filter(|loc| parent_variables.iter().all(|map| map.contains_key(loc)))
+                .collect();
+            common_locations.sort_by_key(|loc| loc.to_string());
+
+            for loc in common_locations {
+                let parent_values: Vec<_> = parent_variables.iter().map(|map| &map[loc]).collect();
+                if parent_values
+                    .iter()
+                    .all(|values| *values == parent_values[0])
+                {
+                    current_variables.insert(loc.clone(), parent_values[0].clone());
+                    continue;
+                }
 
-            // Calculate this node's predicate by taking an OR of the edge prdicates
+                let value_ty = parent_values[0].iter().next().unwrap().ty.clone();
+                let merged_value = GclExpr::var(
+                    MemoryLocation::MergedValue(node_idx, Box::new(loc.clone())),
+                    value_ty,
+                );
+                for (edge_num, values) in parent_values.into_iter().enumerate() {
+                    let is_edge_value = values
+                        .iter()
+                        .map(|value| {
+                            GclExpr::bin_op(GclBinOp::Equals, merged_value.clone(), value.clone())
+                        })
+                        .reduce(|acc, next| GclExpr::bin_op(GclBinOp::Or, acc, next))
+                        .unwrap();
+                    edge_constraints[edge_num].push(is_edge_value);
+                }
+                current_variables.insert(loc.clone(), HashSet::from_iter(Some(merged_value)));
+            }
+
+            // Consider this node's variable assignments
+            apply_commands(&node.commands, &mut current_variables, &mut current_facts);
+
+            // Calculate this node's predicate by taking an OR of the edge
+            // predicates, along with the values of the merged variables. If a
+            // value depends on the edge which was taken, the edge selector says
+            // which one it was.
             let predicate = edge_preds
+                .into_iter()
+                .zip(edge_constraints)
+                .enumerate()
+                .map(|(edge_num, (edge_pred, constraints))| {
+                    let edge_pred = constraints.into_iter().fold(edge_pred, |acc, next| {
+                        GclExpr::bin_op(GclBinOp::And, acc, next)
+                    });
+
+                    if uses_edge_selector {
+                        GclExpr::bin_op(
+                            GclBinOp::And,
+                            is_edge_taken(&edge_selector, edge_num),
+                            edge_pred,
+                        )
+                    } else {
+                        edge_pred
+                    }
+                })
                 .reduce(|acc, next| GclExpr::bin_op(GclBinOp::Or, acc, next))
                 .unwrap_or_default();
 
@@ -121,32 +191,121 @@ impl GclGraph {
     }
 }
 
-impl GclExpr {
-    fn update_by_lvalue(&self, lvalue: &GclLValue, value: GclExpr) -> GclExpr {
-        let mut expr = self.clone();
-        let mut current = &mut expr;
-
-        for entry in lvalue.entries() {
-            match entry {
-                GclLValueEntry::Var(_) => {
-                    *current = value;
-                    break;
-                }
-                GclLValueEntry::Field(field) => match &mut current.data {
-                    GclExprData::Struct { fields } => {
-                        current = fields
-                            .iter_mut()
-                            .find_map(|(name, expr)| if *name == field { Some(expr) } else { None })
-                            .expect("Tried to access an unknown struct field. This should have been caught during type checking");
-                    }
-                    _ => {
-                        panic!("Tried to use a field lvalue on a non-header/-struct type. This should have been caught during type checking")
+/// Update the values of the variables and facts with the node's commands.
+/// Each variable may have several values, since branches can converge.
+pub(crate) fn apply_commands(
+    commands: &[GclCommand],
+    variables: &mut HashMap<MemoryLocation, HashSet<GclExpr>>,
+    facts: &mut HashMap<GclFact, HashSet<GclExpr>>,
+) {
+    for cmd in commands {
+        let (lvalue, expr) = match cmd {
+            GclCommand::Assignment(GclAssignment { lvalue, expr }) => (lvalue, expr),
+            GclCommand::AddFact(fact) => {
+                facts.insert(fact.clone(), HashSet::from_iter(Some(GclExpr::bool(true))));
+                continue;
+            }
+            GclCommand::SetFact(fact, expr) => {
+                let values = expr.fill_in(variables, facts);
+                facts.insert(fact.clone(), values);
+                continue;
+            }
+            GclCommand::HavocFact(fact) => {
+                // The fact is represented by a free variable
+                facts.insert(
+                    fact.clone(),
+                    HashSet::from_iter(Some(GclExpr::fact(fact.clone()))),
+                );
+                continue;
+            }
+            GclCommand::RemoveFact(fact) => {
+                facts.remove(fact);
+                continue;
+            }
+            GclCommand::Bug(_) | GclCommand::UnrollBoundExceeded => continue,
+        };
+
+        let loc = lvalue.mem_location();
+        let values = expr.fill_in(variables, facts);
+        let new_exprs = match lvalue {
+            GclLValue::Var(_) => values,
+            GclLValue::Field(..) => {
+                // Only part of the variable is updated, so start from
+                // its current value(s)
+                let old_values = variables.get(&loc).cloned().unwrap_or_else(|| {
+                    HashSet::from_iter(Some(GclExpr::var(loc.clone(), lvalue.var_ty())))
+                });
+                let field_path = lvalue.field_path();
+                let mut new_exprs = HashSet::new();
+
+                for old_value in &old_values {
+                    for value in &values {
+                        new_exprs.insert(old_value.update_field(&field_path, value.clone()));
                     }
-                },
+                }
+
+                new_exprs
             }
-        }
+        };
+
+        variables.insert(loc, new_exprs);
+    }
+}
+
+/// Check if the edge selector picks the node's `edge_num`-th incoming edge
+fn is_edge_taken(edge_selector: &GclExpr, edge_num: usize) -> GclExpr {
+    GclExpr::bin_op(
+        GclBinOp::Equals,
+        edge_selector.clone(),
+        GclExpr::int(BigInt::from(edge_num), IrType::Base(IrBaseType::Int)),
+    )
+}
+
+impl GclExpr {
+    /// Create a copy of this struct/header value with the field at the given
+    /// path replaced by `value`.
+    fn update_field(&self, field_path: &[String], value: GclExpr) -> GclExpr {
+        let (field, rest) = match field_path.split_first() {
+            Some(split) => split,
+            None => return value,
+        };
+
+        let fields = match (&self.data, &self.ty) {
+            (GclExprData::Struct { fields }, _) => fields.clone(),
+            // Expand the value into its fields so one of them can be replaced
+            (_, IrType::Base(IrBaseType::Struct { fields }))
+            | (_, IrType::Base(IrBaseType::Header { fields })) => fields
+                .iter()
+                .map(|(field_ty, name)| {
+                    let field_value = GclExpr {
+                        ty: IrType::Base(field_ty.clone()),
+                        data: GclExprData::FieldAccess(Box::new(self.clone()), name.clone()),
+                    };
+
+                    (name.clone(), field_value)
+                })
+                .collect(),
+            _ => {
+                panic!("Tried to use a field lvalue on a non-header/-struct type. This should have been caught during type checking")
+            }
+        };
+
+        let fields = fields
+            .into_iter()
+            .map(|(name, field_value)| {
+                if name == *field {
+                    let new_value = field_value.update_field(rest, value.clone());
+                    (name, new_value)
+                } else {
+                    (name, field_value)
+                }
+            })
+            .collect();
 
-        expr
+        GclExpr {
+            ty: self.ty.clone(),
+            data: GclExprData::Struct { fields },
+        }
     }
 }
 
@@ -157,7 +316,7 @@ impl GclExpr {
     pub fn fill_in(
         &self,
         values: &HashMap<MemoryLocation, HashSet<GclExpr>>,
-        facts: &HashSet<GclFact>,
+        facts: &HashMap<GclFact, HashSet<GclExpr>>,
     ) -> HashSet<GclExpr> {
         match &self.data {
             GclExprData::BinOp(op, left, right) => {
@@ -181,11 +340,16 @@ impl GclExpr {
                     data: GclExprData::UnOp(*op, Box::new(pred)),
                 })
                 .collect(),
-            GclExprData::Bool(_) | GclExprData::String(_) => HashSet::from_iter(Some(self.clone())),
+            GclExprData::Bool(_) | GclExprData::Int(_) | GclExprData::String(_) => {
+                HashSet::from_iter(Some(self.clone()))
+            }
             GclExprData::Fact(fact) => {
                 // TODO: this loses info about facts, should we keep facts around
                 //       to improve diagnostics in Z3 later?
-                HashSet::from_iter(Some(GclExpr::bool(facts.contains(fact))))
+                facts
+                    .get(fact)
+                    .cloned()
+                    .unwrap_or_else(|| HashSet::from_iter(Some(GclExpr::bool(false))))
             }
             GclExprData::Var(loc) => {
                 if let Some(values) = values.get(loc) {
@@ -255,31 +419,31 @@ impl GclExpr {
     }
 }
 
-enum GclLValueEntry {
-    Var(MemoryLocation),
-    Field(String),
-}
-
 impl GclLValue {
-    fn entries(&self) -> Vec<GclLValueEntry> {
-        let mut entries = Vec::new();
+    /// Get the type of the variable at the root of this lvalue. Only field
+    /// lvalues keep track of types.
+    fn var_ty(&self) -> IrType {
+        match self {
+            GclLValue::Var(_) => panic!("The type of a variable lvalue is not known"),
+            GclLValue::Field(target, target_ty, _) => match **target {
+                GclLValue::Var(_) => target_ty.clone(),
+                _ => target.var_ty(),
+            },
+        }
+    }
+
+    /// Get the fields accessed by this lvalue, starting from the variable
+    fn field_path(&self) -> Vec<String> {
+        let mut path = Vec::new();
         let mut current = self;
 
-        loop {
-            match current {
-                GclLValue::Var(loc) => {
-                    entries.push(GclLValueEntry::Var(loc.clone()));
-                    break;
-                }
-                GclLValue::Field(target, field) => {
-                    entries.push(GclLValueEntry::Field(field.clone()));
-                    current = target;
-                }
-            }
+        while let GclLValue::Field(target, _, field) = current {
+            path.push(field.clone());
+            current = target;
         }
 
-        entries.reverse();
-        entries
+        path.reverse();
+        path
     }
 }
 
@@ -1,75 +1,92 @@
 use crate::gcl::{GclBinOp, GclExpr, GclExprData, GclUnOp};
 use crate::generate_z3_types::Z3TypeMap;
 use crate::ir::{IrBaseType, IrType};
-use z3::ast::{Ast, Bool, Datatype, Dynamic, String as Z3String};
-use z3::Context;
+use num_bigint::BigInt;
+use std::convert::TryFrom;
+use std::str::FromStr;
+use z3::ast::{Ast, Bool, Datatype, Dynamic, Int, Real, String as Z3String, BV};
 
 impl GclExpr {
-    /// Convert the GCL predicate into a Z3 AST type
-    pub fn as_z3_ast<'ctx>(
-        &self,
-        context: &'ctx Context,
-        type_map: &Z3TypeMap<'ctx>,
-    ) -> Dynamic<'ctx> {
+    /// Convert the GCL predicate into a Z3 AST type, in the current thread's
+    /// Z3 context
+    pub fn as_z3_ast(&self, type_map: &Z3TypeMap) -> Dynamic {
         match &self.data {
-            GclExprData::Bool(value) => Bool::from_bool(context, *value).into(),
-            GclExprData::String(value) => z3::ast::String::from_str(context, value).unwrap().into(),
-            GclExprData::Fact(fact) => Bool::new_const(context, fact.z3_name()).into(),
+            GclExprData::Bool(value) => Bool::from_bool(*value).into(),
+            GclExprData::Int(value) => int_to_z3(value, &self.ty),
+            GclExprData::String(value) => Z3String::from_str(value).unwrap().into(),
+            GclExprData::Fact(fact) => Bool::new_const(fact.z3_name()).into(),
             GclExprData::Var(name) => match &self.ty {
-                IrType::Base(IrBaseType::Bool) => Bool::new_const(context, name.to_string()).into(),
-                IrType::Base(IrBaseType::String) => {
-                    Z3String::new_const(context, name.to_string()).into()
+                IrType::Base(IrBaseType::Bool) => Bool::new_const(name.to_string()).into(),
+                IrType::Base(IrBaseType::String) => Z3String::new_const(name.to_string()).into(),
+                IrType::Base(IrBaseType::Int) => Int::new_const(name.to_string()).into(),
+                IrType::Base(IrBaseType::Bit { width })
+                | IrType::Base(IrBaseType::SignedInt { width }) => {
+                    BV::new_const(name.to_string(), *width as u32).into()
                 }
-                IrType::Base(IrBaseType::Struct { .. }) => {
+                IrType::Base(IrBaseType::Struct { .. })
+                | IrType::Base(IrBaseType::Header { .. }) => {
                     let z3_type = type_map.get(&self.ty).unwrap();
-                    Datatype::new_const(context, name.to_string(), &z3_type.sort).into()
+                    Datatype::new_const(name.to_string(), &z3_type.sort).into()
                 }
                 IrType::Base(_) => unimplemented!(),
                 _ => unimplemented!(),
             },
-            GclExprData::UnOp(GclUnOp::Negate, inner) => {
-                Dynamic::from(inner.as_z3_ast(context, type_map).as_bool().unwrap().not())
+            GclExprData::UnOp(op, inner) => {
+                let inner_z3 = inner.as_z3_ast(type_map);
+
+                match op {
+                    GclUnOp::Negate => inner_z3.as_bool().unwrap().not().into(),
+                    GclUnOp::BitNot => inner_z3.as_bv().unwrap().bvnot().into(),
+                    GclUnOp::Minus => match inner_z3.as_bv() {
+                        Some(inner_bv) => inner_bv.bvneg().into(),
+                        None => inner_z3.as_int().unwrap().unary_minus().into(),
+                    },
+                }
+            }
+            GclExprData::BinOp(op, left, right) => {
+                let left_z3 = left.as_z3_ast(type_map);
+                let right_z3 = right.as_z3_ast(type_map);
+
+                match op {
+                    GclBinOp::And => {
+                        Bool::and(&[left_z3.as_bool().unwrap(), right_z3.as_bool().unwrap()]).into()
+                    }
+                    GclBinOp::Or => {
+                        Bool::or(&[left_z3.as_bool().unwrap(), right_z3.as_bool().unwrap()]).into()
+                    }
+                    GclBinOp::Equals => left_z3.eq(&right_z3).into(),
+                    GclBinOp::NotEquals => left_z3.eq(&right_z3).not().into(),
+                    _ => match left_z3.as_bv() {
+                        Some(left_bv) => {
+                            let is_signed =
+                                matches!(left.ty, IrType::Base(IrBaseType::SignedInt { .. }));
+                            bv_bin_op(*op, &left_bv, &right_z3, is_signed)
+                        }
+                        None => {
+                            int_bin_op(*op, &left_z3.as_int().unwrap(), &right_z3.as_int().unwrap())
+                        }
+                    },
+                }
             }
-            GclExprData::BinOp(op, left, right) => match op {
-                GclBinOp::And => Bool::and(
-                    context,
-                    &[
-                        &left.as_z3_ast(context, type_map).as_bool().unwrap(),
-                        &right.as_z3_ast(context, type_map).as_bool().unwrap(),
-                    ],
-                )
-                .into(),
-                GclBinOp::Or => Bool::or(
-                    context,
-                    &[
-                        &left.as_z3_ast(context, type_map).as_bool().unwrap(),
-                        &right.as_z3_ast(context, type_map).as_bool().unwrap(),
-                    ],
-                )
-                .into(),
-                GclBinOp::Equals => left
-                    .as_z3_ast(context, type_map)
-                    ._eq(&right.as_z3_ast(context, type_map))
-                    .into(),
-            },
             GclExprData::Struct { fields } => {
                 let z3_type = type_map.get(&self.ty).unwrap();
                 let fields_z3: Vec<_> = fields
                     .iter()
-                    .map(|(_, field)| field.as_z3_ast(context, type_map))
+                    .map(|(_, field)| field.as_z3_ast(type_map))
                     .collect();
-                let fields_z3_ref: Vec<_> = fields_z3.iter().collect();
+                let fields_z3_ref: Vec<&dyn Ast> =
+                    fields_z3.iter().map(|field| field as _).collect();
 
                 z3_type.variants[0].constructor.apply(&fields_z3_ref)
             }
             GclExprData::FieldAccess(target, field) => {
-                let target_z3 = target.as_z3_ast(context, type_map);
+                let target_z3 = target.as_z3_ast(type_map);
                 let z3_type = type_map.get(&target.ty).unwrap();
                 let field_idx = match &target.ty {
-                    IrType::Base(IrBaseType::Struct { fields }) => {
+                    IrType::Base(IrBaseType::Struct { fields })
+                    | IrType::Base(IrBaseType::Header { fields }) => {
                         fields.iter().position(|(_, name)| name == field).unwrap()
                     }
-                    IrType::Base(IrBaseType::Header { .. }) => todo!(),
                     _ => panic!(
                         "Type error in Z3 gen: cannot access field of ty: {:?}",
                         target.ty
@@ -81,3 +98,147 @@ impl GclExpr {
         }
     }
 }
+
+/// Convert an integer constant into a Z3 integer or bit-vector, depending on
+/// its type.
+fn int_to_z3(value: &BigInt, ty: &IrType) -> Dynamic {
+    let width = match ty {
+        IrType::Base(IrBaseType::Int) => return Int::from_str(&value.to_string()).unwrap().into(),
+        IrType::Base(IrBaseType::Bit { width }) | IrType::Base(IrBaseType::SignedInt { width }) => {
+            *width
+        }
+        _ => panic!("Type error in Z3 gen: integer with type {:?}", ty),
+    };
+
+    // Get the two's complement representation of the value
+    let modulus = BigInt::from(1) << width;
+    let value = ((value % &modulus) + &modulus) % &modulus;
+
+    match u64::try_from(&value) {
+        Ok(value) => BV::from_u64(value, width as u32).into(),
+        Err(_) => BV::from_int(&Int::from_str(&value.to_string()).unwrap(), width as u32).into(),
+    }
+}
+
+/// Convert an arithmetic, bitwise or comparison operator on bit-vectors
+fn bv_bin_op(op: GclBinOp, left: &BV, right: &Dynamic, is_signed: bool) -> Dynamic {
+    if let GclBinOp::ShiftLeft | GclBinOp::ShiftRight = op {
+        return bv_shift(op, left, right, is_signed);
+    }
+
+    let right = right.as_bv().unwrap();
+    let width = left.get_size();
+
+    // Bounds used by saturating arithmetic
+    let zero = BV::from_u64(0, width);
+    let unsigned_max = zero.bvnot();
+    let signed_min = BV::from_u64(1, width).bvshl(BV::from_u64((width - 1) as u64, width));
+    let signed_max = signed_min.bvnot();
+
+    match op {
+        GclBinOp::Add => left.bvadd(&right).into(),
+        GclBinOp::Sub => left.bvsub(&right).into(),
+        GclBinOp::Mul => left.bvmul(&right).into(),
+        GclBinOp::Div if is_signed => left.bvsdiv(&right).into(),
+        GclBinOp::Div => left.bvudiv(&right).into(),
+        GclBinOp::Mod if is_signed => left.bvsrem(&right).into(),
+        GclBinOp::Mod => left.bvurem(&right).into(),
+        GclBinOp::SaturatingAdd if is_signed => left
+            .bvadd_no_overflow(&right, true)
+            .ite(
+                &left
+                    .bvadd_no_underflow(&right)
+                    .ite(&left.bvadd(&right), &signed_min),
+                &signed_max,
+            )
+            .into(),
+        GclBinOp::SaturatingAdd => left
+            .bvadd_no_overflow(&right, false)
+            .ite(&left.bvadd(&right), &unsigned_max)
+            .into(),
+        GclBinOp::SaturatingSub if is_signed => left
+            .bvsub_no_overflow(&right)
+            .ite(
+                &left
+                    .bvsub_no_underflow(&right, true)
+                    .ite(&left.bvsub(&right), &signed_min),
+                &signed_max,
+            )
+            .into(),
+        GclBinOp::SaturatingSub => left
+            .bvsub_no_underflow(&right, false)
+            .ite(&left.bvsub(&right), &zero)
+            .into(),
+        GclBinOp::BitAnd => left.bvand(&right).into(),
+        GclBinOp::BitOr => left.bvor(&right).into(),
+        GclBinOp::BitXor => left.bvxor(&right).into(),
+        GclBinOp::LessThan if is_signed => left.bvslt(&right).into(),
+        GclBinOp::LessThan => left.bvult(&right).into(),
+        GclBinOp::LessThanEquals if is_signed => left.bvsle(&right).into(),
+        GclBinOp::LessThanEquals => left.bvule(&right).into(),
+        GclBinOp::GreaterThan if is_signed => left.bvsgt(&right).into(),
+        GclBinOp::GreaterThan => left.bvugt(&right).into(),
+        GclBinOp::GreaterThanEquals if is_signed => left.bvsge(&right).into(),
+        GclBinOp::GreaterThanEquals => left.bvuge(&right).into(),
+        _ => panic!("Type error in Z3 gen: {} is not a bit-vector operator", op),
+    }
+}
+
+/// Convert a shift of a bit-vector. The shift amount may be an integer or a
+/// bit-vector of any width.
+fn bv_shift(op: GclBinOp, value: &BV, amount: &Dynamic, is_signed: bool) -> Dynamic {
+    let width = value.get_size();
+    let amount = match amount.as_bv() {
+        Some(amount) => amount,
+        None => {
+            // Shifting by the width or more has the same result, so clamp the
+            // amount to make sure it fits in the bit-vector.
+            let amount = amount.as_int().unwrap();
+            let max_amount = Int::from_u64(width as u64);
+            BV::from_int(&amount.gt(&max_amount).ite(&max_amount, &amount), width)
+        }
+    };
+
+    // Extend the operands to the same width, so large shift amounts are not
+    // truncated.
+    let amount_width = amount.get_size();
+    let shift_width = width.max(amount_width);
+    let value = if is_signed {
+        value.sign_ext(shift_width - width)
+    } else {
+        value.zero_ext(shift_width - width)
+    };
+    let amount = amount.zero_ext(shift_width - amount_width);
+
+    let result = match op {
+        GclBinOp::ShiftLeft => value.bvshl(&amount),
+        GclBinOp::ShiftRight if is_signed => value.bvashr(&amount),
+        GclBinOp::ShiftRight => value.bvlshr(&amount),
+        _ => panic!("Type error in Z3 gen: {} is not a shift operator", op),
+    };
+
+    result.extract(width - 1, 0).into()
+}
+
+/// Convert an arithmetic or comparison operator on arbitrary-precision integers
+fn int_bin_op(op: GclBinOp, left: &Int, right: &Int) -> Dynamic {
+    // Z3 computes powers as reals. The shift amount is never negative, so the
+    // power is a whole number.
+    let two = Real::from_int(&Int::from_u64(2));
+    let shift_factor = || two.power(Real::from_int(right)).to_int();
+
+    match op {
+        GclBinOp::Add => Int::add(&[left, right]).into(),
+        GclBinOp::Sub => Int::sub(&[left, right]).into(),
+        GclBinOp::Mul => Int::mul(&[left, right]).into(),
+        GclBinOp::Div => left.div(right).into(),
+        GclBinOp::Mod => left.modulo(right).into(),
+        GclBinOp::ShiftLeft => Int::mul(&[left, &shift_factor()]).into(),
+        GclBinOp::ShiftRight => left.div(shift_factor()).into(),
+        GclBinOp::LessThan => left.lt(right).into(),
+        GclBinOp::LessThanEquals => left.le(right).into(),
+        GclBinOp::GreaterThan => left.gt(right).into(),
+        GclBinOp::GreaterThanEquals => left.ge(right).into(),
+        _ => panic!("Type error in Z3 gen: {} is not an integer operator", op),
+    }
+}
@@ -2,34 +2,75 @@
 //! * Binding analysis (connect variables to declarations and give each a unique name)
 //! * Type checking (check the type usage and attach type information to AST nodes)
 
+use num_bigint::{BigInt, Sign};
 use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+use std::fmt::{Display, Formatter};
 
 use crate::ast::{
-    ActionDecl, Argument, Assignment, BaseType, BlockStatement, ConstantDecl, ControlDecl,
-    ControlLocalDecl, Declaration, Expr, FunctionCall, IfStatement, Instantiation, KeyElement,
-    LValue, Param, Program, Statement, StatementOrDecl, StructDecl, TableDecl, TableProperty,
-    TypeRef, VariableDecl,
+    ActionDecl, Argument, Assignment, BaseType, BinOp, BlockStatement, ConstantDecl, ControlDecl,
+    ControlLocalDecl, Declaration, Direction, Expr, ExprData, FunctionCall, HeaderDecl,
+    IfStatement, Instantiation, KeyElement, Keyset, LValue, LValueData, MethodCall, Param,
+    ParserDecl, ParserLocalDecl, ParserState, Program, SelectCase, SelectExpr, Statement,
+    StatementOrDecl, StructDecl, TableDecl, TableProperty, Transition, TypeRef, UnOp, VariableDecl,
 };
+use crate::diagnostics::Diagnostic;
 use crate::ir::{
-    IrActionDecl, IrArgument, IrAssignment, IrBaseType, IrBlockStatement, IrControlDecl,
+    IrActionDecl, IrArgument, IrAssert, IrAssignment, IrBaseType, IrBlockStatement, IrControlDecl,
     IrControlLocalDecl, IrDeclaration, IrExpr, IrExprData, IrFunctionCall, IrFunctionType,
-    IrIfStatement, IrInstantiation, IrKeyElement, IrLValue, IrLValueData, IrParam, IrProgram,
-    IrStatement, IrStatementOrDecl, IrTableDecl, IrTableProperty, IrType, IrVariableDecl,
-    VariableId,
+    IrIfStatement, IrInstantiation, IrKeyElement, IrKeyset, IrLValue, IrLValueData, IrMethodCall,
+    IrParam, IrParserDecl, IrParserLocalDecl, IrParserState, IrProgram, IrSelectCase,
+    IrSelectExpr, IrStatement, IrStatementOrDecl, IrTableDecl, IrTableProperty, IrTransition,
+    IrTransitionTarget, IrType, IrVariableDecl, VariableId,
 };
+use crate::span::Span;
 
+/// A type checking error and the location of the code which caused it
 #[derive(Debug)]
-pub enum TypeCheckError {
+pub struct TypeCheckError {
+    pub kind: TypeCheckErrorKind,
+    /// The innermost AST node which contains the error. This is filled in as
+    /// the error is returned up the AST.
+    pub span: Option<Span>,
+}
+
+impl TypeCheckError {
+    /// Attribute the error to the span if it doesn't have a location yet
+    fn or_span(mut self, span: Option<Span>) -> Self {
+        self.span = self.span.or(span);
+        self
+    }
+}
+
+impl From<TypeCheckErrorKind> for TypeCheckError {
+    fn from(kind: TypeCheckErrorKind) -> Self {
+        Self { kind, span: None }
+    }
+}
+
+#[derive(Debug)]
+pub enum TypeCheckErrorKind {
     /// The declaration of this variable was not found
     UnknownVar(String),
     /// The declaration of this type was not found
     UnknownType(String),
     /// The given field was not found on the struct/header
     UnknownField(String),
+    /// The given method was not found on the type
+    UnknownMethod { method: String, ty: IrType },
     /// There is more than one declaration of this variable in the same scope
-    DuplicateDecl(String),
+    DuplicateDecl {
+        name: String,
+        /// The previous declaration, unless it is built in
+        previous: Option<Span>,
+    },
     /// There is already a type declared with the given name
-    DuplicateTypeDecl(String),
+    DuplicateTypeDecl {
+        name: String,
+        /// The previous declaration, unless it is built in
+        previous: Option<Span>,
+    },
     /// Expected one type but got another
     MismatchedTypes { expected: IrType, found: IrType },
     /// Expected a {expected}, found other type
@@ -37,19 +78,137 @@ pub enum TypeCheckError {
         expected: &'static str,
         found: IrType,
     },
+    /// Called a function or method with the wrong number of arguments
+    WrongArgumentCount { expected: usize, found: usize },
+    /// A named argument does not match any parameter of the function
+    UnknownParam(String),
+    /// More than one argument was given for this parameter
+    DuplicateArgument(String),
+    /// Named and positional arguments were used in the same call
+    MixedNamedArguments,
+    /// A select case has a different number of keysets than the select has keys
+    WrongKeysetCount { expected: usize, found: usize },
     /// Tried to assign to a const value
     ModifyingConstValue,
+    /// A fixed-width integer type was declared with a width of zero
+    ZeroWidthType,
+    /// An integer literal, or a constant expression, does not fit in its type
+    IntLiteralOutOfRange { value: BigInt, ty: IrType },
+}
+
+impl TypeCheckErrorKind {
+    /// Check if the error was caused by a poisoned type, i.e. it is a result
+    /// of an error which was already reported.
+    fn is_poisoned(&self) -> bool {
+        match self {
+            TypeCheckErrorKind::MismatchedTypes { expected, found } => {
+                expected.is_poisoned() || found.is_poisoned()
+            }
+            TypeCheckErrorKind::MismatchedTypeKind { found, .. } => found.is_poisoned(),
+            TypeCheckErrorKind::UnknownMethod { ty, .. } => ty.is_poisoned(),
+            _ => false,
+        }
+    }
+}
+
+impl Display for TypeCheckErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeCheckErrorKind::UnknownVar(name) => write!(f, "unknown variable `{}`", name),
+            TypeCheckErrorKind::UnknownType(name) => write!(f, "unknown type `{}`", name),
+            TypeCheckErrorKind::UnknownField(field) => write!(f, "unknown field `{}`", field),
+            TypeCheckErrorKind::UnknownMethod { method, ty } => {
+                write!(f, "unknown method `{}` on type `{}`", method, ty)
+            }
+            TypeCheckErrorKind::DuplicateDecl { name, .. } => {
+                write!(f, "`{}` is already declared in this scope", name)
+            }
+            TypeCheckErrorKind::DuplicateTypeDecl { name, .. } => {
+                write!(f, "type `{}` is already declared", name)
+            }
+            TypeCheckErrorKind::MismatchedTypes { expected, found } => {
+                write!(
+                    f,
+                    "mismatched types: expected `{}`, found `{}`",
+                    expected, found
+                )
+            }
+            TypeCheckErrorKind::MismatchedTypeKind { expected, found } => {
+                let article = if expected.starts_with(|c| "aeiou".contains(c)) {
+                    "an"
+                } else {
+                    "a"
+                };
+                write!(f, "expected {} {}, found `{}`", article, expected, found)
+            }
+            TypeCheckErrorKind::WrongArgumentCount { expected, found } => write!(
+                f,
+                "wrong number of arguments: expected {}, found {}",
+                expected, found
+            ),
+            TypeCheckErrorKind::UnknownParam(name) => write!(f, "unknown parameter `{}`", name),
+            TypeCheckErrorKind::DuplicateArgument(name) => {
+                write!(f, "parameter `{}` is given more than one argument", name)
+            }
+            TypeCheckErrorKind::MixedNamedArguments => {
+                f.write_str("named and positional arguments can't be mixed")
+            }
+            TypeCheckErrorKind::WrongKeysetCount { expected, found } => write!(
+                f,
+                "wrong number of keysets: expected {}, found {}",
+                expected, found
+            ),
+            TypeCheckErrorKind::ModifyingConstValue => f.write_str("cannot modify a const value"),
+            TypeCheckErrorKind::ZeroWidthType => {
+                f.write_str("integer types can't have a width of 0")
+            }
+            TypeCheckErrorKind::IntLiteralOutOfRange { value, ty } => {
+                write!(f, "integer {} does not fit in `{}`", value, ty)
+            }
+        }
+    }
+}
+
+impl From<TypeCheckError> for Diagnostic {
+    fn from(error: TypeCheckError) -> Self {
+        let diagnostic = Diagnostic::error(error.kind.to_string(), error.span);
+
+        match error.kind {
+            TypeCheckErrorKind::DuplicateDecl {
+                previous: Some(previous),
+                ..
+            }
+            | TypeCheckErrorKind::DuplicateTypeDecl {
+                previous: Some(previous),
+                ..
+            } => diagnostic.with_note("previously declared here", Some(previous)),
+            _ => diagnostic,
+        }
+    }
 }
 
 /// Run binding analysis on the program, creating a new program with unique
 /// variable names given to each variable and a map from new name to ID.
+/// Type checking continues after an error, so all errors in the program are
+/// returned.
 pub fn run_type_checking(
     program: &Program,
-) -> Result<(IrProgram, ProgramMetadata), TypeCheckError> {
+) -> Result<(IrProgram, ProgramMetadata), Vec<TypeCheckError>> {
     let mut env = EnvironmentStack::new();
-    let new_program = program.type_check(&mut env)?;
 
-    Ok((new_program, env.into()))
+    match program.type_check(&mut env) {
+        Ok(new_program) if env.errors.is_empty() => Ok((new_program, env.into())),
+        result => {
+            if let Err(error) = result {
+                env.report(error);
+            }
+
+            // Show the errors in the order they appear in the program
+            env.errors
+                .sort_by_key(|error| error.span.map(|span| span.start));
+            Err(env.errors)
+        }
+    }
 }
 
 /// Holds some metadata about the program, such as the IR type of each declared type.
@@ -84,12 +243,27 @@ struct EnvironmentStack {
     types: HashMap<String, IrType>,
     types_in_order: Vec<(String, IrType)>,
     const_set: HashSet<VariableId>,
+    /// The parameters of each function, used to resolve named arguments
+    function_params: HashMap<VariableId, Vec<VariableId>>,
+    /// Where each variable and user-defined type was declared
+    var_spans: HashMap<VariableId, Span>,
+    type_spans: HashMap<String, Span>,
+    /// The errors found so far
+    errors: Vec<TypeCheckError>,
     next_id: usize,
 }
 
 impl EnvironmentStack {
     fn new() -> Self {
-        Self::default()
+        let mut env = Self::default();
+
+        // Built-in types are not user-defined, so they aren't in types_in_order
+        env.types.insert(
+            "packet_in".to_string(),
+            IrType::Base(IrBaseType::PacketIn),
+        );
+
+        env
     }
 
     /// Get the ID and type of the variable
@@ -108,50 +282,89 @@ impl EnvironmentStack {
 
     fn get_var_or_err(&self, name: &str) -> Result<(VariableId, &IrType), TypeCheckError> {
         self.get_var(name)
-            .ok_or_else(|| TypeCheckError::UnknownVar(name.to_string()))
+            .ok_or_else(|| TypeCheckErrorKind::UnknownVar(name.to_string()).into())
     }
 
     /// Insert a variable into the environment and return a unique ID for it.
     /// If the variable has already been declared in this same scope, an
     /// error is returned.
-    fn insert_var(&mut self, name: String, ty: IrType) -> Result<VariableId, TypeCheckError> {
+    fn insert_var(
+        &mut self,
+        name: String,
+        ty: IrType,
+        span: Span,
+    ) -> Result<VariableId, TypeCheckError> {
         if self.stack.is_empty() {
             self.stack.push(Environment::default());
         }
 
         let env = self.stack.last_mut().unwrap();
 
-        if env.variables.contains_key(&name) {
-            return Err(TypeCheckError::DuplicateDecl(name));
+        if let Some(previous) = env.variables.get(&name) {
+            return Err(TypeCheckErrorKind::DuplicateDecl {
+                previous: self.var_spans.get(previous).copied(),
+                name,
+            }
+            .into());
         }
 
         let id = VariableId(self.next_id, name.clone());
         self.next_id += 1;
         self.var_tys.insert(id.clone(), ty);
+        self.var_spans.insert(id.clone(), span);
         env.variables.insert(name, id.clone());
 
         Ok(id)
     }
 
+    /// Declare a variable whose declaration failed to type check, so uses of
+    /// it don't cause more errors. Nothing is declared if the name is already
+    /// taken in this scope.
+    fn insert_poisoned_var(&mut self, name: &str, span: Span) {
+        let is_declared =
+            matches!(self.stack.last(), Some(env) if env.variables.contains_key(name));
+
+        if !is_declared {
+            let ty = IrType::Base(IrBaseType::Poisoned);
+            self.insert_var(name.to_string(), ty, span).unwrap();
+        }
+    }
+
     fn get_type(&self, name: &str) -> Option<&IrType> {
         self.types.get(name)
     }
 
     fn get_type_or_err(&self, name: &str) -> Result<&IrType, TypeCheckError> {
         self.get_type(name)
-            .ok_or_else(|| TypeCheckError::UnknownType(name.to_string()))
+            .ok_or_else(|| TypeCheckErrorKind::UnknownType(name.to_string()).into())
     }
 
     /// Insert a user-defined type into the map
-    fn insert_type(&mut self, name: String, ty: IrType) -> Result<(), TypeCheckError> {
-        if self.types.insert(name.clone(), ty.clone()).is_some() {
-            return Err(TypeCheckError::DuplicateTypeDecl(name));
+    fn insert_type(&mut self, name: String, ty: IrType, span: Span) -> Result<(), TypeCheckError> {
+        if self.types.contains_key(&name) {
+            return Err(TypeCheckErrorKind::DuplicateTypeDecl {
+                previous: self.type_spans.get(&name).copied(),
+                name,
+            }
+            .into());
         }
 
+        self.types.insert(name.clone(), ty.clone());
+        self.type_spans.insert(name.clone(), span);
         self.types_in_order.push((name, ty));
         Ok(())
     }
 
+    /// Declare a type whose declaration failed to type check, so uses of it
+    /// don't cause more errors
+    fn insert_poisoned_type(&mut self, name: &str, span: Span) {
+        if !self.types.contains_key(name) {
+            self.types
+                .insert(name.to_string(), IrType::Base(IrBaseType::Poisoned));
+            self.type_spans.insert(name.to_string(), span);
+        }
+    }
+
     /// Mark a variable as const
     fn mark_const(&mut self, id: VariableId) {
         self.const_set.insert(id);
@@ -171,6 +384,14 @@ impl EnvironmentStack {
     fn pop_scope(&mut self) {
         self.stack.pop();
     }
+
+    /// Record an error so type checking can continue. Errors caused by a
+    /// poisoned type are dropped, since the original error was reported.
+    fn report(&mut self, error: TypeCheckError) {
+        if !error.kind.is_poisoned() {
+            self.errors.push(error);
+        }
+    }
 }
 
 impl IrType {
@@ -178,10 +399,11 @@ impl IrType {
     fn unwrap_base(self) -> Result<IrBaseType, TypeCheckError> {
         match self {
             IrType::Base(ty) => Ok(ty),
-            ty => Err(TypeCheckError::MismatchedTypeKind {
+            ty => Err(TypeCheckErrorKind::MismatchedTypeKind {
                 expected: "base type",
                 found: ty,
-            }),
+            }
+            .into()),
         }
     }
 
@@ -191,17 +413,18 @@ impl IrType {
             IrType::Base(IrBaseType::Struct { fields })
             | IrType::Base(IrBaseType::Header { fields }) => fields,
             _ => {
-                return Err(TypeCheckError::MismatchedTypeKind {
+                return Err(TypeCheckErrorKind::MismatchedTypeKind {
                     expected: "struct or header",
                     found: self.clone(),
-                })
+                }
+                .into())
             }
         };
 
         fields
             .iter()
             .find_map(|(ty, name)| if name == field { Some(ty) } else { None })
-            .ok_or_else(|| TypeCheckError::UnknownField(field.to_string()))
+            .ok_or_else(|| TypeCheckErrorKind::UnknownField(field.to_string()).into())
     }
 }
 
@@ -210,17 +433,50 @@ impl IrType {
 trait TypeCheck: Sized {
     type IrNode;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError>;
+    /// Type check the node. Errors which don't have a location yet are
+    /// attributed to this node.
+    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+        self.type_check_node(env)
+            .map_err(|error| error.or_span(self.span()))
+    }
+
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError>;
+
+    /// The location of the node in the source code, if it has one
+    fn span(&self) -> Option<Span> {
+        None
+    }
+
+    /// Called after the node failed to type check. Declarations use this to
+    /// declare their name with a poisoned type.
+    fn recover(&self, _env: &mut EnvironmentStack) {}
+}
+
+/// Type check a list of items such as statements or declarations. An item
+/// which fails to type check is left out, and its error is reported so the
+/// following items are still checked.
+fn type_check_items<T: TypeCheck>(items: &[T], env: &mut EnvironmentStack) -> Vec<T::IrNode> {
+    items
+        .iter()
+        .filter_map(|item| match item.type_check(env) {
+            Ok(ir_item) => Some(ir_item),
+            Err(error) => {
+                env.report(error);
+                item.recover(env);
+                None
+            }
+        })
+        .collect()
 }
 
 impl<T: TypeCheck> TypeCheck for Vec<T> {
     type IrNode = Vec<T::IrNode>;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         let items = self
             .iter()
             .map(|item| item.type_check(env))
-            .collect::<Result<_, _>>()?;
+            .collect::<Result<_, TypeCheckError>>()?;
 
         Ok(items)
     }
@@ -229,7 +485,7 @@ impl<T: TypeCheck> TypeCheck for Vec<T> {
 impl<T: TypeCheck> TypeCheck for Option<T> {
     type IrNode = Option<T::IrNode>;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         self.as_ref().map(|inner| inner.type_check(env)).transpose()
     }
 }
@@ -237,11 +493,9 @@ impl<T: TypeCheck> TypeCheck for Option<T> {
 impl TypeCheck for Program {
     type IrNode = IrProgram;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         Ok(IrProgram {
-            declarations: self
-                .declarations
-                .type_check(env)?
+            declarations: type_check_items(&self.declarations, env)
                 .into_iter()
                 .flatten()
                 .collect(),
@@ -252,9 +506,9 @@ impl TypeCheck for Program {
 impl TypeCheck for Declaration {
     type IrNode = Option<IrDeclaration>;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         match self {
-            Declaration::Struct(StructDecl { name, fields }) => {
+            Declaration::Struct(StructDecl { name, fields, .. }) => {
                 let struct_ty = IrType::Base(IrBaseType::Struct {
                     fields: fields
                         .iter()
@@ -262,15 +516,32 @@ impl TypeCheck for Declaration {
                             let ty = ty_ref.type_check(env)?.unwrap_base()?;
                             Ok((ty, field_name.clone()))
                         })
-                        .collect::<Result<_, _>>()?,
+                        .collect::<Result<_, TypeCheckError>>()?,
+                });
+
+                env.insert_type(name.clone(), struct_ty, self.span().unwrap())?;
+                Ok(None)
+            }
+            Declaration::Header(HeaderDecl { name, fields, .. }) => {
+                let header_ty = IrType::Base(IrBaseType::Header {
+                    fields: fields
+                        .iter()
+                        .map(|(ty_ref, field_name)| {
+                            let ty = ty_ref.type_check(env)?.unwrap_base()?;
+                            Ok((ty, field_name.clone()))
+                        })
+                        .collect::<Result<_, TypeCheckError>>()?,
                 });
 
-                env.insert_type(name.clone(), struct_ty)?;
+                env.insert_type(name.clone(), header_ty, self.span().unwrap())?;
                 Ok(None)
             }
             Declaration::Control(control_decl) => {
                 Ok(Some(IrDeclaration::Control(control_decl.type_check(env)?)))
             }
+            Declaration::Parser(parser_decl) => {
+                Ok(Some(IrDeclaration::Parser(parser_decl.type_check(env)?)))
+            }
             Declaration::Constant(const_decl) => Ok(Some(IrDeclaration::Constant(Box::new(
                 const_decl.type_check(env)?,
             )))),
@@ -279,48 +550,283 @@ impl TypeCheck for Declaration {
             ))),
         }
     }
+
+    fn span(&self) -> Option<Span> {
+        // The other declarations have their own spans
+        match self {
+            Declaration::Struct(struct_decl) => Some(struct_decl.span),
+            Declaration::Header(header_decl) => Some(header_decl.span),
+            _ => None,
+        }
+    }
+
+    fn recover(&self, env: &mut EnvironmentStack) {
+        match self {
+            Declaration::Struct(StructDecl { name, span, .. })
+            | Declaration::Header(HeaderDecl { name, span, .. }) => {
+                env.insert_poisoned_type(name, *span)
+            }
+            Declaration::Constant(const_decl) => const_decl.recover(env),
+            Declaration::Instantiation(instantiation) => instantiation.recover(env),
+            Declaration::Control(_) | Declaration::Parser(_) => {}
+        }
+    }
 }
 
 impl TypeCheck for ControlDecl {
     type IrNode = IrControlDecl;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         // TODO: check name against types
 
         env.push_scope();
-        let params = self.params.type_check(env)?;
-        let local_decls = self.local_decls.type_check(env)?;
-        let apply_body = self.apply_body.type_check(env)?;
+        let params = type_check_items(&self.params, env);
+        let local_decls = type_check_items(&self.local_decls, env);
+        let apply_body = self.apply_body.type_check(env);
         env.pop_scope();
+        let apply_body = apply_body?;
 
         Ok(IrControlDecl {
-            // name: self.name.clone(),
+            name: self.name.clone(),
             params,
             local_decls,
             apply_body,
+            span: self.span,
+        })
+    }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
+    }
+}
+
+impl TypeCheck for ParserDecl {
+    type IrNode = IrParserDecl;
+
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+        env.push_scope();
+        let params = type_check_items(&self.params, env);
+
+        // Declare the states up front, so transitions can refer to any state
+        for state in &self.states {
+            let result = if state.name == "accept" || state.name == "reject" {
+                Err(TypeCheckErrorKind::DuplicateDecl {
+                    name: state.name.clone(),
+                    previous: None,
+                }
+                .into())
+            } else {
+                env.insert_var(state.name.clone(), IrType::ParserState, state.span)
+            };
+
+            if let Err(error) = result {
+                env.report(error.or_span(Some(state.span)));
+                env.insert_poisoned_var(&state.name, state.span);
+            }
+        }
+
+        let local_decls = type_check_items(&self.local_decls, env);
+        let states = type_check_items(&self.states, env);
+        let start_state = resolve_transition_target(env, "start");
+        env.pop_scope();
+
+        let start_state = match start_state? {
+            IrTransitionTarget::State(id) => id,
+            _ => unreachable!("start is not a built-in state"),
+        };
+
+        Ok(IrParserDecl {
+            name: self.name.clone(),
+            params,
+            local_decls,
+            states,
+            start_state,
+            span: self.span,
+        })
+    }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
+    }
+}
+
+impl TypeCheck for ParserLocalDecl {
+    type IrNode = IrParserLocalDecl;
+
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+        match self {
+            ParserLocalDecl::Variable(var_decl) => {
+                Ok(IrParserLocalDecl::Variable(var_decl.type_check(env)?))
+            }
+            ParserLocalDecl::Instantiation(instantiation) => Ok(
+                IrParserLocalDecl::Instantiation(instantiation.type_check(env)?),
+            ),
+            ParserLocalDecl::Constant(const_decl) => {
+                Ok(IrParserLocalDecl::Variable(const_decl.type_check(env)?))
+            }
+        }
+    }
+
+    fn recover(&self, env: &mut EnvironmentStack) {
+        match self {
+            ParserLocalDecl::Variable(var_decl) => var_decl.recover(env),
+            ParserLocalDecl::Instantiation(instantiation) => instantiation.recover(env),
+            ParserLocalDecl::Constant(const_decl) => const_decl.recover(env),
+        }
+    }
+}
+
+impl TypeCheck for ParserState {
+    type IrNode = IrParserState;
+
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+        let (id, _) = env.get_var_or_err(&self.name)?;
+
+        // The transition can use variables declared in the state's body
+        env.push_scope();
+        let body = type_check_items(&self.body.statements, env);
+        let transition = self.transition.type_check(env);
+        env.pop_scope();
+        let transition = transition?;
+
+        Ok(IrParserState {
+            id,
+            body: IrBlockStatement(body),
+            transition,
+            span: self.span,
         })
     }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
+    }
+}
+
+impl TypeCheck for Transition {
+    type IrNode = IrTransition;
+
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+        match self {
+            Transition::Direct(state) => Ok(IrTransition::Direct(resolve_transition_target(
+                env, state,
+            )?)),
+            Transition::Select(select) => Ok(IrTransition::Select(select.type_check(env)?)),
+        }
+    }
+}
+
+impl TypeCheck for SelectExpr {
+    type IrNode = IrSelectExpr;
+
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+        let keys = self.keys.type_check(env)?;
+        let cases = self
+            .cases
+            .iter()
+            .map(|case| {
+                type_check_select_case(env, case, &keys)
+                    .map_err(|error| error.or_span(Some(case.span)))
+            })
+            .collect::<Result<_, TypeCheckError>>()?;
+
+        Ok(IrSelectExpr { keys, cases })
+    }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
+    }
+}
+
+/// Check the keysets of a select case against the select's keys
+fn type_check_select_case(
+    env: &mut EnvironmentStack,
+    case: &SelectCase,
+    keys: &[IrExpr],
+) -> Result<IrSelectCase, TypeCheckError> {
+    let keysets = match case.keysets.as_slice() {
+        // A single default matches all of the keys
+        [Keyset::Default] => keys.iter().map(|_| IrKeyset::Default).collect(),
+        keysets if keysets.len() == keys.len() => keysets
+            .iter()
+            .zip(keys)
+            .map(|(keyset, key)| match keyset {
+                Keyset::Expr(value) => {
+                    Ok(IrKeyset::Value(coerce_ty(value.type_check(env)?, &key.ty)?))
+                }
+                Keyset::Default => Ok(IrKeyset::Default),
+            })
+            .collect::<Result<_, TypeCheckError>>()?,
+        keysets => {
+            return Err(TypeCheckErrorKind::WrongKeysetCount {
+                expected: keys.len(),
+                found: keysets.len(),
+            }
+            .into())
+        }
+    };
+
+    Ok(IrSelectCase {
+        keysets,
+        target: resolve_transition_target(env, &case.state)?,
+    })
+}
+
+/// Look up the state which a parser transitions to
+fn resolve_transition_target(
+    env: &EnvironmentStack,
+    name: &str,
+) -> Result<IrTransitionTarget, TypeCheckError> {
+    match name {
+        "accept" => Ok(IrTransitionTarget::Accept),
+        "reject" => Ok(IrTransitionTarget::Reject),
+        _ => {
+            let (id, ty) = env.get_var_or_err(name)?;
+
+            match ty {
+                IrType::ParserState => Ok(IrTransitionTarget::State(id)),
+                _ => Err(TypeCheckErrorKind::MismatchedTypeKind {
+                    expected: "parser state",
+                    found: ty.clone(),
+                }
+                .into()),
+            }
+        }
+    }
 }
 
 impl TypeCheck for Param {
     type IrNode = IrParam;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
-        let ty = self.ty.type_check(env)?;
-        let id = env.insert_var(self.name.clone(), ty.clone())?;
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+        // Keep the parameter if its type is invalid, so the function still
+        // has the right number of parameters
+        let ty = self
+            .ty
+            .type_check(env)
+            .and_then(IrType::unwrap_base)
+            .unwrap_or_else(|error| {
+                env.report(error.or_span(Some(self.span)));
+                IrBaseType::Poisoned
+            });
+        let id = env.insert_var(self.name.clone(), IrType::Base(ty.clone()), self.span)?;
 
         Ok(IrParam {
-            ty: ty.unwrap_base()?,
+            ty,
             id,
             direction: self.direction,
+            span: self.span,
         })
     }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
+    }
 }
 
 impl TypeCheck for ControlLocalDecl {
     type IrNode = IrControlLocalDecl;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         match self {
             ControlLocalDecl::Variable(var_decl) => {
                 Ok(IrControlLocalDecl::Variable(var_decl.type_check(env)?))
@@ -339,12 +845,22 @@ impl TypeCheck for ControlLocalDecl {
             }
         }
     }
+
+    fn recover(&self, env: &mut EnvironmentStack) {
+        match self {
+            ControlLocalDecl::Variable(var_decl) => var_decl.recover(env),
+            ControlLocalDecl::Instantiation(instantiation) => instantiation.recover(env),
+            ControlLocalDecl::Constant(const_decl) => const_decl.recover(env),
+            ControlLocalDecl::Action(action_decl) => action_decl.recover(env),
+            ControlLocalDecl::Table(table_decl) => table_decl.recover(env),
+        }
+    }
 }
 
 impl TypeCheck for StatementOrDecl {
     type IrNode = IrStatementOrDecl;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         match self {
             StatementOrDecl::Statement(stmt) => {
                 Ok(IrStatementOrDecl::Statement(stmt.type_check(env)?))
@@ -360,21 +876,36 @@ impl TypeCheck for StatementOrDecl {
             )),
         }
     }
+
+    fn recover(&self, env: &mut EnvironmentStack) {
+        match self {
+            StatementOrDecl::Statement(_) => {}
+            StatementOrDecl::VariableDecl(var_decl) => var_decl.recover(env),
+            StatementOrDecl::ConstantDecl(const_decl) => const_decl.recover(env),
+            StatementOrDecl::Instantiation(instantiation) => instantiation.recover(env),
+        }
+    }
 }
 
 impl TypeCheck for Statement {
     type IrNode = IrStatement;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         match self {
             Statement::Block(block) => Ok(IrStatement::Block(block.type_check(env)?)),
             Statement::If(if_stmt) => Ok(IrStatement::If(if_stmt.type_check(env)?)),
             Statement::Assignment(assignment) => {
                 Ok(IrStatement::Assignment(assignment.type_check(env)?))
             }
+            Statement::FunctionCall(func_call) if is_builtin_assert(env, func_call) => {
+                Ok(IrStatement::Assert(type_check_assert(env, func_call)?))
+            }
             Statement::FunctionCall(func_call) => {
                 Ok(IrStatement::FunctionCall(func_call.type_check(env)?))
             }
+            Statement::MethodCall(method_call) => {
+                Ok(IrStatement::MethodCall(method_call.type_check(env)?.0))
+            }
         }
     }
 }
@@ -382,23 +913,28 @@ impl TypeCheck for Statement {
 impl TypeCheck for BlockStatement {
     type IrNode = IrBlockStatement;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         env.push_scope();
-        let stmts = self.0.type_check(env)?;
+        let stmts = type_check_items(&self.statements, env);
         env.pop_scope();
 
         Ok(IrBlockStatement(stmts))
     }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
+    }
 }
 
 impl TypeCheck for ActionDecl {
     type IrNode = IrActionDecl;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         env.push_scope();
-        let params = self.params.type_check(env)?;
-        let body = self.body.type_check(env)?;
+        let params = type_check_items(&self.params, env);
+        let body = self.body.type_check(env);
         env.pop_scope();
+        let body = body?;
 
         let ty = IrFunctionType {
             result: Box::new(IrBaseType::void()),
@@ -407,32 +943,57 @@ impl TypeCheck for ActionDecl {
                 .map(|param| (param.direction, param.ty.clone()))
                 .collect(),
         };
-        let id = env.insert_var(self.name.clone(), IrType::Function(ty.clone()))?;
+        let id = env.insert_var(self.name.clone(), IrType::Function(ty.clone()), self.span)?;
+        env.function_params.insert(
+            id.clone(),
+            params.iter().map(|param| param.id.clone()).collect(),
+        );
 
         Ok(IrActionDecl {
             ty,
             id,
             params,
             body,
+            span: self.span,
         })
     }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
+    }
+
+    fn recover(&self, env: &mut EnvironmentStack) {
+        env.insert_poisoned_var(&self.name, self.span);
+    }
 }
 
 impl TypeCheck for TableDecl {
     type IrNode = IrTableDecl;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
-        let properties = self.properties.type_check(env)?;
-        let id = env.insert_var(self.name.clone(), IrType::Table)?;
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+        let properties = type_check_items(&self.properties, env);
+        let id = env.insert_var(self.name.clone(), IrType::Table, self.span)?;
+
+        Ok(IrTableDecl {
+            id,
+            properties,
+            span: self.span,
+        })
+    }
 
-        Ok(IrTableDecl { id, properties })
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
+    }
+
+    fn recover(&self, env: &mut EnvironmentStack) {
+        env.insert_poisoned_var(&self.name, self.span);
     }
 }
 
 impl TypeCheck for TableProperty {
     type IrNode = IrTableProperty;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         match self {
             TableProperty::Key(keys) => Ok(IrTableProperty::Key(keys.type_check(env)?)),
             TableProperty::Actions(actions) => Ok(IrTableProperty::Actions(
@@ -445,14 +1006,28 @@ impl TypeCheck for TableProperty {
                             IrType::Function(IrFunctionType { result, .. }) if result.is_void() => {
                                 Ok(id)
                             }
-                            _ => Err(TypeCheckError::MismatchedTypeKind {
+                            _ => Err(TypeCheckErrorKind::MismatchedTypeKind {
                                 expected: "action",
                                 found: ty.clone(),
-                            }),
+                            }
+                            .into()),
                         }
                     })
-                    .collect::<Result<_, _>>()?,
+                    .collect::<Result<_, TypeCheckError>>()?,
             )),
+            TableProperty::DefaultAction(action_call) => {
+                let action_call = action_call.type_check(env)?;
+
+                if !action_call.result_ty.is_void() {
+                    return Err(TypeCheckErrorKind::MismatchedTypes {
+                        expected: IrType::Base(IrBaseType::void()),
+                        found: IrType::Base(action_call.result_ty),
+                    }
+                    .into());
+                }
+
+                Ok(IrTableProperty::DefaultAction(action_call))
+            }
         }
     }
 }
@@ -460,7 +1035,7 @@ impl TypeCheck for TableProperty {
 impl TypeCheck for KeyElement {
     type IrNode = IrKeyElement;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         // Note: the "name" of the key is not to be modified. It refers to a key
         // type (ex. exact or lpm) and does not reference or declare a variable.
         // TODO: verify that the match kind has been declared previously
@@ -469,12 +1044,16 @@ impl TypeCheck for KeyElement {
             expr: self.expr.type_check(env)?,
         })
     }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
+    }
 }
 
 impl TypeCheck for TypeRef {
     type IrNode = IrType;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         match self {
             TypeRef::Base(base_ty) => Ok(IrType::Base(base_ty.type_check(env)?)),
             TypeRef::Identifier(name) => env.get_type_or_err(name).map(IrType::clone),
@@ -485,10 +1064,16 @@ impl TypeCheck for TypeRef {
 impl TypeCheck for BaseType {
     type IrNode = IrBaseType;
 
-    fn type_check(&self, _env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, _env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         match self {
             BaseType::Bool => Ok(IrBaseType::Bool),
             BaseType::String => Ok(IrBaseType::String),
+            BaseType::Int => Ok(IrBaseType::Int),
+            BaseType::Bit(0) | BaseType::SignedInt(0) => {
+                Err(TypeCheckErrorKind::ZeroWidthType.into())
+            }
+            BaseType::Bit(width) => Ok(IrBaseType::Bit { width: *width }),
+            BaseType::SignedInt(width) => Ok(IrBaseType::SignedInt { width: *width }),
         }
     }
 }
@@ -496,12 +1081,11 @@ impl TypeCheck for BaseType {
 impl TypeCheck for ConstantDecl {
     type IrNode = IrVariableDecl;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         let ty = self.ty.type_check(env)?;
-        let value = self.value.type_check(env)?;
-        let id = env.insert_var(self.name.clone(), ty.clone())?;
+        let value = coerce_ty(self.value.type_check(env)?, &ty)?;
+        let id = env.insert_var(self.name.clone(), ty.clone(), self.span)?;
 
-        assert_ty(&value.ty, &ty)?;
         env.mark_const(id.clone());
 
         Ok(IrVariableDecl {
@@ -509,135 +1093,413 @@ impl TypeCheck for ConstantDecl {
             id,
             value: Some(value),
             is_const: true,
+            span: self.span,
         })
     }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
+    }
+
+    fn recover(&self, env: &mut EnvironmentStack) {
+        env.insert_poisoned_var(&self.name, self.span);
+    }
 }
 
 impl TypeCheck for VariableDecl {
     type IrNode = IrVariableDecl;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         let ty = self.ty.type_check(env)?;
-        let value = self.value.type_check(env)?;
-        let id = env.insert_var(self.name.clone(), ty.clone())?;
-
-        if let Some(value) = &value {
-            assert_ty(&value.ty, &ty)?;
-        }
+        let value = self
+            .value
+            .type_check(env)?
+            .map(|value| coerce_ty(value, &ty))
+            .transpose()?;
+        let id = env.insert_var(self.name.clone(), ty.clone(), self.span)?;
 
         Ok(IrVariableDecl {
             ty,
             id,
             value,
             is_const: false,
+            span: self.span,
         })
     }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
+    }
+
+    fn recover(&self, env: &mut EnvironmentStack) {
+        env.insert_poisoned_var(&self.name, self.span);
+    }
 }
 
 impl TypeCheck for Instantiation {
     type IrNode = IrInstantiation;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         let ty = self.ty.type_check(env)?;
         let args = self.args.type_check(env)?;
-        let id = env.insert_var(self.name.clone(), ty.clone())?;
+        let id = env.insert_var(self.name.clone(), ty.clone(), self.span)?;
 
         // TODO: ensure type is a constructor and arg types match up
 
         Ok(IrInstantiation { ty, id, args })
     }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
+    }
+
+    fn recover(&self, env: &mut EnvironmentStack) {
+        env.insert_poisoned_var(&self.name, self.span);
+    }
 }
 
 impl TypeCheck for IfStatement {
     type IrNode = IrIfStatement;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         let condition = self.condition.type_check(env)?;
         let then_case = self.then_case.type_check(env)?;
         let else_case = self.else_case.type_check(env)?;
 
-        assert_ty(&condition.ty, &IrType::bool())?;
+        assert_ty(&condition.ty, &IrType::bool())
+            .map_err(|error| error.or_span(Some(condition.span)))?;
 
         Ok(IrIfStatement {
             condition,
             then_case,
             else_case,
+            span: self.span,
         })
     }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
+    }
 }
 
 impl TypeCheck for Assignment {
     type IrNode = IrAssignment;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         let lvalue = self.lvalue.type_check(env)?;
-        let value = self.value.type_check(env)?;
+        let value = coerce_ty(self.value.type_check(env)?, &lvalue.ty)?;
 
         if env.is_const(lvalue.var_id()) {
-            return Err(TypeCheckError::ModifyingConstValue);
+            return Err(TypeCheckErrorKind::ModifyingConstValue.into());
         }
 
-        assert_ty(&value.ty, &lvalue.ty)?;
+        Ok(IrAssignment {
+            lvalue,
+            value,
+            span: self.span,
+        })
+    }
 
-        Ok(IrAssignment { lvalue, value })
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
     }
 }
 
 impl TypeCheck for LValue {
     type IrNode = IrLValue;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
-        match self {
-            LValue::Var(var) => {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+        match &self.data {
+            LValueData::Var(var) => {
                 let (id, ty) = env.get_var_or_err(var)?;
                 Ok(IrLValue {
                     ty: ty.clone(),
                     data: IrLValueData::Var(id),
+                    span: self.span,
                 })
             }
-            LValue::Field(target, field) => {
+            LValueData::Field(target, field) => {
                 let target_ir = target.type_check(env)?;
                 let field_ty = target_ir.ty.get_field_ty(field)?;
 
                 Ok(IrLValue {
                     ty: IrType::Base(field_ty.clone()),
                     data: IrLValueData::Field(Box::new(target_ir), field.to_string()),
+                    span: self.span,
                 })
             }
         }
     }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
+    }
 }
 
 impl TypeCheck for FunctionCall {
     type IrNode = IrFunctionCall;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         let (target_id, target_ty) = env.get_var_or_err(&self.target)?;
         let target_ty = target_ty.clone();
-        let arguments = self.arguments.type_check(env)?;
 
         let func_ty = match target_ty {
             IrType::Function(ty) => ty,
             _ => {
-                return Err(TypeCheckError::MismatchedTypeKind {
+                return Err(TypeCheckErrorKind::MismatchedTypeKind {
                     expected: "function",
                     found: target_ty,
-                })
+                }
+                .into())
             }
         };
 
+        let arguments = check_arguments(env, &target_id, &func_ty, &self.arguments)?;
+
         Ok(IrFunctionCall {
             result_ty: *func_ty.result,
             target: target_id,
             arguments,
+            span: self.span,
         })
     }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
+    }
+}
+
+/// True if the call is to the built-in `assert` (i.e. it isn't shadowed by a
+/// user-defined `assert`)
+fn is_builtin_assert(env: &EnvironmentStack, func_call: &FunctionCall) -> bool {
+    func_call.target == "assert" && env.get_var("assert").is_none()
+}
+
+/// Type check a call to the built-in `assert`, which takes a single bool
+fn type_check_assert(
+    env: &mut EnvironmentStack,
+    func_call: &FunctionCall,
+) -> Result<IrAssert, TypeCheckError> {
+    let condition = match func_call.arguments.as_slice() {
+        [Argument::Value(condition)] => condition.type_check(env)?,
+        arguments => {
+            return Err(TypeCheckErrorKind::WrongArgumentCount {
+                expected: 1,
+                found: arguments.len(),
+            }
+            .into())
+        }
+    };
+    assert_ty(&condition.ty, &IrType::bool())
+        .map_err(|error| error.or_span(Some(condition.span)))?;
+
+    Ok(IrAssert {
+        condition,
+        span: func_call.span,
+    })
+}
+
+/// Check the arguments of a function call against the function's parameters.
+/// Named arguments are resolved to the parameter they are passed to.
+fn check_arguments(
+    env: &mut EnvironmentStack,
+    function: &VariableId,
+    func_ty: &IrFunctionType,
+    arguments: &[Argument],
+) -> Result<Vec<IrArgument>, TypeCheckError> {
+    if arguments.len() != func_ty.inputs.len() {
+        return Err(TypeCheckErrorKind::WrongArgumentCount {
+            expected: func_ty.inputs.len(),
+            found: arguments.len(),
+        }
+        .into());
+    }
+
+    // Either all or none of the arguments are named
+    let is_named = |argument: &Argument| matches!(argument, Argument::Named(..));
+    if arguments.iter().any(is_named) && !arguments.iter().all(is_named) {
+        return Err(TypeCheckErrorKind::MixedNamedArguments.into());
+    }
+
+    let param_ids = env
+        .function_params
+        .get(function)
+        .cloned()
+        .unwrap_or_default();
+    let mut bound_params = HashSet::new();
+
+    arguments
+        .iter()
+        .enumerate()
+        .map(|(i, argument)| {
+            let param_idx = match argument {
+                Argument::Named(name, _) => {
+                    let param_idx = param_ids
+                        .iter()
+                        .position(|param_id| param_id.1 == *name)
+                        .ok_or_else(|| TypeCheckErrorKind::UnknownParam(name.clone()))?;
+
+                    if !bound_params.insert(param_idx) {
+                        return Err(TypeCheckErrorKind::DuplicateArgument(name.clone()).into());
+                    }
+
+                    param_idx
+                }
+                Argument::Value(_) | Argument::DontCare => i,
+            };
+            let (direction, param_ty) = &func_ty.inputs[param_idx];
+            let param_ty = IrType::Base(param_ty.clone());
+
+            let value = match argument {
+                Argument::Value(value) | Argument::Named(_, value) => value,
+                // Only the output of a parameter can be ignored
+                Argument::DontCare if *direction == Direction::Out => {
+                    return Ok(IrArgument::DontCare)
+                }
+                Argument::DontCare => {
+                    return Err(TypeCheckErrorKind::MismatchedTypeKind {
+                        expected: "value",
+                        found: param_ty,
+                    }
+                    .into())
+                }
+            };
+            let value = coerce_ty(value.type_check(env)?, &param_ty)?;
+
+            // Outputs are copied back into the argument
+            if let Direction::Out | Direction::InOut = direction {
+                let lvalue =
+                    value
+                        .as_lvalue()
+                        .ok_or_else(|| TypeCheckErrorKind::MismatchedTypeKind {
+                            expected: "variable or field",
+                            found: value.ty.clone(),
+                        })?;
+
+                if env.is_const(lvalue.var_id()) {
+                    return Err(TypeCheckErrorKind::ModifyingConstValue.into());
+                }
+            }
+
+            match argument {
+                Argument::Named(..) => Ok(IrArgument::Named(param_ids[param_idx].clone(), value)),
+                _ => Ok(IrArgument::Value(value)),
+            }
+        })
+        .collect()
+}
+
+impl TypeCheck for MethodCall {
+    /// The method call and its result type
+    type IrNode = (IrMethodCall, IrType);
+
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+        let target = self.target.type_check(env)?;
+        let unknown_method = || TypeCheckErrorKind::UnknownMethod {
+            method: self.method.clone(),
+            ty: target.ty.clone(),
+        };
+
+        match &target.ty {
+            IrType::Base(IrBaseType::Header { .. }) => {
+                if !self.arguments.is_empty() {
+                    return Err(TypeCheckErrorKind::WrongArgumentCount {
+                        expected: 0,
+                        found: self.arguments.len(),
+                    }
+                    .into());
+                }
+
+                // Validity is tracked per header variable (or field)
+                let header = target
+                    .as_lvalue()
+                    .ok_or_else(|| TypeCheckErrorKind::MismatchedTypeKind {
+                        expected: "header variable or field",
+                        found: target.ty.clone(),
+                    })?;
+
+                match self.method.as_str() {
+                    "isValid" => Ok((IrMethodCall::IsValid(header), IrType::bool())),
+                    "setValid" | "setInvalid" if env.is_const(header.var_id()) => {
+                        Err(TypeCheckErrorKind::ModifyingConstValue.into())
+                    }
+                    "setValid" => Ok((
+                        IrMethodCall::SetValid(header),
+                        IrType::Base(IrBaseType::void()),
+                    )),
+                    "setInvalid" => Ok((
+                        IrMethodCall::SetInvalid(header),
+                        IrType::Base(IrBaseType::void()),
+                    )),
+                    _ => Err(unknown_method().into()),
+                }
+            }
+            IrType::Base(IrBaseType::PacketIn) => match self.method.as_str() {
+                "extract" => {
+                    let header = match self.arguments.as_slice() {
+                        [Argument::Value(header)] => header.type_check(env)?,
+                        arguments => {
+                            return Err(TypeCheckErrorKind::WrongArgumentCount {
+                                expected: 1,
+                                found: arguments.len(),
+                            }
+                            .into())
+                        }
+                    };
+                    let header_lvalue = match (&header.ty, header.as_lvalue()) {
+                        (IrType::Base(IrBaseType::Header { .. }), Some(lvalue)) => lvalue,
+                        _ => {
+                            return Err(TypeCheckErrorKind::MismatchedTypeKind {
+                                expected: "header variable or field",
+                                found: header.ty,
+                            }
+                            .into())
+                        }
+                    };
+
+                    if env.is_const(header_lvalue.var_id()) {
+                        return Err(TypeCheckErrorKind::ModifyingConstValue.into());
+                    }
+
+                    Ok((
+                        IrMethodCall::Extract(header_lvalue),
+                        IrType::Base(IrBaseType::void()),
+                    ))
+                }
+                _ => Err(unknown_method().into()),
+            },
+            IrType::Table => match self.method.as_str() {
+                "apply" => {
+                    if !self.arguments.is_empty() {
+                        return Err(TypeCheckErrorKind::WrongArgumentCount {
+                            expected: 0,
+                            found: self.arguments.len(),
+                        }
+                        .into());
+                    }
+
+                    let table = target.as_lvalue().ok_or_else(unknown_method)?;
+
+                    Ok((
+                        IrMethodCall::Apply(table.var_id(), self.span),
+                        IrType::Base(IrBaseType::void()),
+                    ))
+                }
+                _ => Err(unknown_method().into()),
+            },
+            _ => Err(unknown_method().into()),
+        }
+    }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
+    }
 }
 
 impl TypeCheck for Argument {
     type IrNode = IrArgument;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
         match self {
             Argument::Value(value) => Ok(IrArgument::Value(value.type_check(env)?)),
             Argument::Named(name, value) => Ok(IrArgument::Named(
@@ -653,31 +1515,296 @@ fn assert_ty(found: &IrType, expected: &IrType) -> Result<(), TypeCheckError> {
     if found == expected {
         Ok(())
     } else {
-        Err(TypeCheckError::MismatchedTypes {
+        Err(TypeCheckErrorKind::MismatchedTypes {
             expected: expected.clone(),
             found: found.clone(),
-        })
+        }
+        .into())
+    }
+}
+
+/// Check that the value has the expected type, implicitly casting
+/// arbitrary-precision integers to fixed-width integer types if necessary.
+/// Errors are attributed to the value.
+fn coerce_ty(mut value: IrExpr, expected: &IrType) -> Result<IrExpr, TypeCheckError> {
+    let span = Some(value.span);
+
+    match (&mut value.data, expected) {
+        // Coerce each field of a struct expression
+        (IrExprData::Struct(key_values), IrType::Base(IrBaseType::Struct { fields })) => {
+            for (key, field_value) in key_values.iter_mut() {
+                if let Some((field_ty, _)) = fields.iter().find(|(_, name)| name == key) {
+                    let field_ty = IrType::Base(field_ty.clone());
+                    *field_value = coerce_ty(field_value.clone(), &field_ty)?;
+                }
+            }
+
+            value.ty = IrType::Base(IrBaseType::Struct {
+                fields: key_values
+                    .iter()
+                    .map(|(key, field_value)| {
+                        Ok((field_value.ty.clone().unwrap_base()?, key.clone()))
+                    })
+                    .collect::<Result<_, TypeCheckError>>()?,
+            });
+        }
+        (_, IrType::Base(expected_base))
+            if value.ty == IrType::int() && expected_base.bit_width().is_some() =>
+        {
+            value
+                .cast_int(expected_base)
+                .map_err(|error| error.or_span(span))?;
+        }
+        _ => {}
+    }
+
+    assert_ty(&value.ty, expected).map_err(|error| error.or_span(span))?;
+    Ok(value)
+}
+
+impl IrExpr {
+    /// Cast an arbitrary-precision integer expression to a fixed-width integer
+    /// type. Constant expressions are folded into a literal, which is checked
+    /// to make sure it fits in the new type (so -8 fits in an int<4>, but
+    /// 2 - 3 does not fit in a bit<8>). Errors are attributed to the constant.
+    fn cast_int(&mut self, ty: &IrBaseType) -> Result<(), TypeCheckError> {
+        if let Some(value) = self.int_constant() {
+            if !ty.can_represent(&value) {
+                return Err(
+                    TypeCheckError::from(TypeCheckErrorKind::IntLiteralOutOfRange {
+                        value,
+                        ty: IrType::Base(ty.clone()),
+                    })
+                    .or_span(Some(self.span)),
+                );
+            }
+
+            self.data = IrExprData::Int(value);
+            self.ty = IrType::Base(ty.clone());
+            return Ok(());
+        }
+
+        match &mut self.data {
+            // The shift amount keeps its own type
+            IrExprData::BinOp(BinOp::ShiftLeft, left, _)
+            | IrExprData::BinOp(BinOp::ShiftRight, left, _) => left.cast_int(ty)?,
+            IrExprData::BinOp(op, left, right) if !op.is_comparison() => {
+                left.cast_int(ty)?;
+                right.cast_int(ty)?;
+            }
+            IrExprData::UnOp(_, inner) => inner.cast_int(ty)?,
+            _ => return Ok(()),
+        }
+
+        self.ty = IrType::Base(ty.clone());
+        Ok(())
+    }
+
+    /// Calculate the value of an arbitrary-precision integer expression which
+    /// only uses literals
+    fn int_constant(&self) -> Option<BigInt> {
+        match &self.data {
+            IrExprData::Int(value) => Some(value.clone()),
+            IrExprData::UnOp(UnOp::Minus, inner) => Some(-inner.int_constant()?),
+            IrExprData::BinOp(op, left, right) if self.ty == IrType::int() => {
+                let left = left.int_constant()?;
+                let right = right.int_constant()?;
+
+                match op {
+                    BinOp::Add => Some(left + right),
+                    BinOp::Sub => Some(left - right),
+                    BinOp::Mul => Some(left * right),
+                    BinOp::Div if right.sign() != Sign::NoSign => Some(left / right),
+                    BinOp::Mod if right.sign() != Sign::NoSign => Some(left % right),
+                    // Huge shifts are left for the solver
+                    BinOp::ShiftLeft => Some(left << u16::try_from(&right).ok()?),
+                    BinOp::ShiftRight => Some(left >> u16::try_from(&right).ok()?),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl IrBaseType {
+    /// Check if the integer value can be represented by this type
+    fn can_represent(&self, value: &BigInt) -> bool {
+        match self {
+            IrBaseType::Int => true,
+            IrBaseType::Bit { width } => {
+                value.sign() != Sign::Minus && value.bits() <= *width as u64
+            }
+            IrBaseType::SignedInt { width } => {
+                let bound = BigInt::from(1) << (width - 1);
+                -&bound <= *value && *value < bound
+            }
+            _ => false,
+        }
+    }
+}
+
+/// If one operand is an arbitrary-precision integer and the other is a
+/// fixed-width integer, implicitly cast the first to the type of the second.
+fn unify_int_operands(left: &mut IrExpr, right: &mut IrExpr) -> Result<(), TypeCheckError> {
+    match (&left.ty, &right.ty) {
+        (IrType::Base(IrBaseType::Int), IrType::Base(right_ty)) if right_ty.bit_width().is_some() => {
+            left.cast_int(&right_ty.clone())
+        }
+        (IrType::Base(left_ty), IrType::Base(IrBaseType::Int)) if left_ty.bit_width().is_some() => {
+            right.cast_int(&left_ty.clone())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Check that the expression has an integer type. If `fixed_width` is true,
+/// arbitrary-precision integers are not allowed.
+fn assert_numeric(expr: &IrExpr, fixed_width: bool) -> Result<(), TypeCheckError> {
+    match &expr.ty {
+        IrType::Base(ty) if ty.bit_width().is_some() => Ok(()),
+        IrType::Base(IrBaseType::Int) if !fixed_width => Ok(()),
+        ty => Err(
+            TypeCheckError::from(TypeCheckErrorKind::MismatchedTypeKind {
+                expected: if fixed_width {
+                    "fixed-width integer"
+                } else {
+                    "integer"
+                },
+                found: ty.clone(),
+            })
+            .or_span(Some(expr.span)),
+        ),
+    }
+}
+
+/// Check that the right operand of a binary operator has the same type as the
+/// left one. Errors are attributed to the right operand.
+fn assert_same_ty(left: &IrExpr, right: &IrExpr) -> Result<(), TypeCheckError> {
+    assert_ty(&right.ty, &left.ty).map_err(|error| error.or_span(Some(right.span)))
+}
+
+/// Type check a binary operator (other than the short-circuiting && and ||)
+/// and calculate the result type.
+fn type_check_bin_op(
+    op: BinOp,
+    left: &mut IrExpr,
+    right: &mut IrExpr,
+) -> Result<IrType, TypeCheckError> {
+    match op {
+        BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod => {
+            unify_int_operands(left, right)?;
+            assert_numeric(left, false)?;
+            assert_same_ty(left, right)?;
+            Ok(left.ty.clone())
+        }
+        BinOp::SaturatingAdd
+        | BinOp::SaturatingSub
+        | BinOp::BitAnd
+        | BinOp::BitOr
+        | BinOp::BitXor => {
+            unify_int_operands(left, right)?;
+            assert_numeric(left, true)?;
+            assert_same_ty(left, right)?;
+            Ok(left.ty.clone())
+        }
+        BinOp::ShiftLeft | BinOp::ShiftRight => {
+            assert_numeric(left, false)?;
+
+            // The shift amount must be unsigned
+            match &right.data {
+                IrExprData::Int(value) if value.sign() == Sign::Minus => {
+                    return Err(
+                        TypeCheckError::from(TypeCheckErrorKind::IntLiteralOutOfRange {
+                            value: value.clone(),
+                            ty: right.ty.clone(),
+                        })
+                        .or_span(Some(right.span)),
+                    );
+                }
+                IrExprData::Int(_) => {}
+                // An arbitrary-precision integer can only be shifted by a
+                // known amount
+                _ if left.ty == IrType::Base(IrBaseType::Int) => {
+                    return Err(
+                        TypeCheckError::from(TypeCheckErrorKind::MismatchedTypeKind {
+                            expected: "constant shift amount",
+                            found: right.ty.clone(),
+                        })
+                        .or_span(Some(right.span)),
+                    )
+                }
+                _ if matches!(right.ty, IrType::Base(IrBaseType::Bit { .. })) => {}
+                _ => {
+                    return Err(
+                        TypeCheckError::from(TypeCheckErrorKind::MismatchedTypeKind {
+                            expected: "unsigned integer",
+                            found: right.ty.clone(),
+                        })
+                        .or_span(Some(right.span)),
+                    )
+                }
+            }
+
+            Ok(left.ty.clone())
+        }
+        BinOp::Equals | BinOp::NotEquals => {
+            unify_int_operands(left, right)?;
+            assert_same_ty(left, right)?;
+            Ok(IrType::bool())
+        }
+        BinOp::LessThan | BinOp::LessThanEquals | BinOp::GreaterThan | BinOp::GreaterThanEquals => {
+            unify_int_operands(left, right)?;
+            assert_numeric(left, false)?;
+            assert_same_ty(left, right)?;
+            Ok(IrType::bool())
+        }
     }
 }
 
 impl TypeCheck for Expr {
     type IrNode = IrExpr;
 
-    fn type_check(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
-        match self {
-            Expr::Bool(value) => Ok(IrExpr {
+    fn type_check_node(&self, env: &mut EnvironmentStack) -> Result<Self::IrNode, TypeCheckError> {
+        match &self.data {
+            ExprData::Bool(value) => Ok(IrExpr {
                 ty: IrType::bool(),
                 data: IrExprData::Bool(*value),
+                span: self.span,
             }),
-            Expr::Var(name) => {
+            ExprData::Int(literal) => {
+                // Unsized literals are arbitrary-precision integers
+                let ty = match literal.width {
+                    None => IrBaseType::Int,
+                    Some(0) => return Err(TypeCheckErrorKind::ZeroWidthType.into()),
+                    Some(width) if literal.signed => IrBaseType::SignedInt { width },
+                    Some(width) => IrBaseType::Bit { width },
+                };
+
+                if !ty.can_represent(&literal.value) {
+                    return Err(TypeCheckErrorKind::IntLiteralOutOfRange {
+                        value: literal.value.clone(),
+                        ty: IrType::Base(ty),
+                    }
+                    .into());
+                }
+
+                Ok(IrExpr {
+                    ty: IrType::Base(ty),
+                    data: IrExprData::Int(literal.value.clone()),
+                    span: self.span,
+                })
+            }
+            ExprData::Var(name) => {
                 let (id, ty) = env.get_var_or_err(name)?;
 
                 Ok(IrExpr {
                     ty: ty.clone(),
                     data: IrExprData::Var(id),
+                    span: self.span,
                 })
             }
-            Expr::And(left, right) => {
+            ExprData::And(left, right) => {
                 let left_ir = left.type_check(env)?;
                 let right_ir = right.type_check(env)?;
 
@@ -687,9 +1814,10 @@ impl TypeCheck for Expr {
                 Ok(IrExpr {
                     ty: IrType::bool(),
                     data: IrExprData::And(Box::new(left_ir), Box::new(right_ir)),
+                    span: self.span,
                 })
             }
-            Expr::Or(left, right) => {
+            ExprData::Or(left, right) => {
                 let left_ir = left.type_check(env)?;
                 let right_ir = right.type_check(env)?;
 
@@ -699,9 +1827,10 @@ impl TypeCheck for Expr {
                 Ok(IrExpr {
                     ty: IrType::bool(),
                     data: IrExprData::Or(Box::new(left_ir), Box::new(right_ir)),
+                    span: self.span,
                 })
             }
-            Expr::Negation(inner) => {
+            ExprData::Negation(inner) => {
                 let inner_ir = inner.type_check(env)?;
 
                 assert_ty(&inner_ir.ty, &IrType::bool())?;
@@ -709,42 +1838,84 @@ impl TypeCheck for Expr {
                 Ok(IrExpr {
                     ty: IrType::bool(),
                     data: IrExprData::Negation(Box::new(inner_ir)),
+                    span: self.span,
+                })
+            }
+            ExprData::BinOp(op, left, right) => {
+                let mut left_ir = left.type_check(env)?;
+                let mut right_ir = right.type_check(env)?;
+                let ty = type_check_bin_op(*op, &mut left_ir, &mut right_ir)?;
+
+                Ok(IrExpr {
+                    ty,
+                    data: IrExprData::BinOp(*op, Box::new(left_ir), Box::new(right_ir)),
+                    span: self.span,
+                })
+            }
+            ExprData::UnOp(op, inner) => {
+                let inner_ir = inner.type_check(env)?;
+
+                match op {
+                    UnOp::BitNot => assert_numeric(&inner_ir, true)?,
+                    UnOp::Minus => assert_numeric(&inner_ir, false)?,
+                }
+
+                Ok(IrExpr {
+                    ty: inner_ir.ty.clone(),
+                    data: IrExprData::UnOp(*op, Box::new(inner_ir)),
+                    span: self.span,
                 })
             }
-            Expr::FunctionCall(func_call) => {
+            ExprData::FunctionCall(func_call) => {
                 let func_call_ir = func_call.type_check(env)?;
 
                 Ok(IrExpr {
                     ty: IrType::Base(func_call_ir.result_ty.clone()),
                     data: IrExprData::FunctionCall(func_call_ir),
+                    span: self.span,
                 })
             }
-            Expr::FieldAccess(target, field) => {
+            ExprData::MethodCall(method_call) => {
+                let (method_call_ir, ty) = method_call.type_check(env)?;
+
+                Ok(IrExpr {
+                    ty,
+                    data: IrExprData::MethodCall(method_call_ir),
+                    span: self.span,
+                })
+            }
+            ExprData::FieldAccess(target, field) => {
                 let target_ir = target.type_check(env)?;
                 let field_ty = target_ir.ty.get_field_ty(field)?;
 
                 Ok(IrExpr {
                     ty: IrType::Base(field_ty.clone()),
                     data: IrExprData::FieldAccess(Box::new(target_ir), field.clone()),
+                    span: self.span,
                 })
             }
-            Expr::Struct(key_values) => {
+            ExprData::Struct(key_values) => {
                 let key_values_ir: Vec<_> = key_values
                     .iter()
                     .map(|(key, value)| Ok((key.clone(), value.type_check(env)?)))
-                    .collect::<Result<_, _>>()?;
+                    .collect::<Result<_, TypeCheckError>>()?;
                 let field_tys = key_values_ir
                     .iter()
                     .map(|(key, value_ir)| Ok((value_ir.ty.clone().unwrap_base()?, key.clone())))
-                    .collect::<Result<_, _>>()?;
+                    .collect::<Result<_, TypeCheckError>>()?;
 
                 let ty = IrType::Base(IrBaseType::Struct { fields: field_tys });
 
                 Ok(IrExpr {
                     ty,
                     data: IrExprData::Struct(key_values_ir),
+                    span: self.span,
                 })
             }
         }
     }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
+    }
 }
@@ -0,0 +1,316 @@
+//! Bounded loop unrolling. Later passes require the GCL graph to be a DAG, so
+//! each loop (ex. a parser state which transitions back to itself) is replaced
+//! by a fixed number of copies of its body.
+
+use crate::gcl::{GclCommand, GclExpr, GclGraph, GclNode, MemoryLocation, TableChoice};
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+use petgraph::Direction;
+use std::collections::{HashMap, HashSet};
+
+/// The default number of times a loop may repeat
+pub const DEFAULT_UNROLL_BOUND: usize = 4;
+
+/// Unroll the loops in the graph so that each loop may repeat up to `bound`
+/// times. Paths which would repeat a loop more often end in an "unroll bound
+/// exceeded" node. Only parser state transitions can form loops, since actions
+/// get their own nodes at each call.
+pub fn unroll_loops(graph: &mut GclGraph, start_idx: NodeIndex, bound: usize) {
+    let loops: Vec<Vec<NodeIndex>> = tarjan_scc(&**graph)
+        .into_iter()
+        .filter(|scc| scc.len() > 1 || graph.contains_edge(scc[0], scc[0]))
+        .collect();
+
+    for loop_nodes in loops {
+        unroll_loop(graph, start_idx, &loop_nodes, bound);
+    }
+}
+
+/// An edge between two nodes of the loop
+struct LoopEdge {
+    source: NodeIndex,
+    target: NodeIndex,
+    weight: GclExpr,
+    /// Taking this edge starts another iteration of the loop
+    is_back_edge: bool,
+}
+
+fn unroll_loop(graph: &mut GclGraph, start_idx: NodeIndex, loop_nodes: &[NodeIndex], bound: usize) {
+    let in_loop: HashSet<NodeIndex> = loop_nodes.iter().copied().collect();
+
+    // The loop is entered through the nodes with a parent outside of the loop
+    let entries: Vec<NodeIndex> = loop_nodes
+        .iter()
+        .copied()
+        .filter(|node_idx| {
+            *node_idx == start_idx
+                || graph
+                    .edges_directed(*node_idx, Direction::Incoming)
+                    .any(|edge| !in_loop.contains(&edge.source()))
+        })
+        .collect();
+
+    // Take the edges out of the loop, they are added back between the copies
+    let back_edges = find_back_edges(graph, &entries, &in_loop);
+    let edge_ids: Vec<EdgeIndex> = loop_nodes
+        .iter()
+        .flat_map(|node_idx| graph.edges_directed(*node_idx, Direction::Outgoing))
+        .filter(|edge| in_loop.contains(&edge.target()))
+        .map(|edge| edge.id())
+        .collect();
+    let loop_edges: Vec<LoopEdge> = edge_ids
+        .into_iter()
+        .map(|edge_id| {
+            let (source, target) = graph.edge_endpoints(edge_id).unwrap();
+            LoopEdge {
+                source,
+                target,
+                weight: graph.remove_edge(edge_id).unwrap(),
+                is_back_edge: back_edges.contains(&edge_id),
+            }
+        })
+        .collect();
+    let exit_edges: Vec<(NodeIndex, NodeIndex, GclExpr)> = loop_nodes
+        .iter()
+        .flat_map(|node_idx| graph.edges_directed(*node_idx, Direction::Outgoing))
+        .map(|edge| (edge.source(), edge.target(), edge.weight().clone()))
+        .collect();
+
+    // Each copy of the loop gets its own temporaries and packet data
+    let local_locations = find_local_locations(graph, &in_loop, &loop_edges);
+    let mut iteration_renames = vec![HashMap::new()];
+    let mut copies: HashMap<(NodeIndex, usize), NodeIndex> = entries
+        .iter()
+        .map(|node_idx| ((*node_idx, 0), *node_idx))
+        .collect();
+    let mut queue: Vec<(NodeIndex, usize)> =
+        entries.iter().map(|node_idx| (*node_idx, 0)).collect();
+    let mut bound_exceeded_idx = None;
+
+    while let Some((node_idx, iteration)) = queue.pop() {
+        let copy_idx = copies[&(node_idx, iteration)];
+
+        for edge in loop_edges.iter().filter(|edge| edge.source == node_idx) {
+            let mut weight = edge.weight.clone();
+            rename_expr(&mut weight, &iteration_renames[iteration]);
+            let target_iteration = iteration + edge.is_back_edge as usize;
+
+            let target_idx = if target_iteration > bound {
+                *bound_exceeded_idx.get_or_insert_with(|| {
+                    // Point at the start of the loop
+                    let span = graph.node_weight(edge.target).unwrap().span;
+                    let node = GclNode {
+                        name: graph.create_name("unroll_bound_exceeded"),
+                        commands: vec![GclCommand::UnrollBoundExceeded],
+                        span,
+                    };
+                    graph.add_node(node)
+                })
+            } else if let Some(target_idx) = copies.get(&(edge.target, target_iteration)) {
+                *target_idx
+            } else {
+                if iteration_renames.len() <= target_iteration {
+                    let iteration = iteration_renames.len();
+                    let renames = local_locations
+                        .iter()
+                        .map(|loc| {
+                            let name = match loc {
+                                MemoryLocation::Var(_, name) => name.clone(),
+                                MemoryLocation::ReturnVal
+                                | MemoryLocation::EdgeSelector(_)
+                                | MemoryLocation::MergedValue(..) => None,
+                            };
+                            let renamed = graph.fresh_mem_location(name);
+
+                            // Each copy of a table application makes its own choice
+                            if let Some(choice) = graph.get_table_choice(loc).cloned() {
+                                graph.register_table_choice(
+                                    renamed.clone(),
+                                    TableChoice {
+                                        iteration,
+                                        ..choice
+                                    },
+                                );
+                            }
+
+                            (loc.clone(), renamed)
+                        })
+                        .collect();
+                    iteration_renames.push(renames);
+                }
+
+                let target_idx = if target_iteration == 0 {
+                    edge.target
+                } else {
+                    copy_node(
+                        graph,
+                        edge.target,
+                        target_iteration,
+                        &iteration_renames[target_iteration],
+                    )
+                };
+                copies.insert((edge.target, target_iteration), target_idx);
+                queue.push((edge.target, target_iteration));
+                target_idx
+            };
+
+            graph.add_edge(copy_idx, target_idx, weight);
+        }
+
+        // The original nodes already have their exit edges
+        if iteration > 0 {
+            for (_, exit_target, weight) in exit_edges
+                .iter()
+                .filter(|(source, _, _)| *source == node_idx)
+            {
+                let mut weight = weight.clone();
+                rename_expr(&mut weight, &iteration_renames[iteration]);
+                graph.add_edge(copy_idx, *exit_target, weight);
+            }
+        }
+    }
+
+    // Nodes which can only be reached by repeating the loop are now covered by
+    // their copies
+    for node_idx in loop_nodes {
+        if !copies.contains_key(&(*node_idx, 0)) {
+            graph.remove_node(*node_idx);
+        }
+    }
+}
+
+/// Find the edges which go back to an earlier node of the loop, via a depth
+/// first search from the loop entries. Removing them breaks every cycle.
+fn find_back_edges(
+    graph: &GclGraph,
+    entries: &[NodeIndex],
+    in_loop: &HashSet<NodeIndex>,
+) -> HashSet<EdgeIndex> {
+    let mut back_edges = HashSet::new();
+    let mut visited = HashSet::new();
+    let mut on_stack = HashSet::new();
+
+    for entry in entries {
+        if !visited.insert(*entry) {
+            continue;
+        }
+
+        on_stack.insert(*entry);
+        let mut stack = vec![(*entry, out_edges_in_loop(graph, *entry, in_loop))];
+
+        while let Some((node_idx, edges)) = stack.last_mut() {
+            let (edge_id, target) = match edges.pop() {
+                Some(edge) => edge,
+                None => {
+                    on_stack.remove(node_idx);
+                    stack.pop();
+                    continue;
+                }
+            };
+
+            if on_stack.contains(&target) {
+                back_edges.insert(edge_id);
+            } else if visited.insert(target) {
+                on_stack.insert(target);
+                stack.push((target, out_edges_in_loop(graph, target, in_loop)));
+            }
+        }
+    }
+
+    back_edges
+}
+
+fn out_edges_in_loop(
+    graph: &GclGraph,
+    node_idx: NodeIndex,
+    in_loop: &HashSet<NodeIndex>,
+) -> Vec<(EdgeIndex, NodeIndex)> {
+    graph
+        .edges_directed(node_idx, Direction::Outgoing)
+        .filter(|edge| in_loop.contains(&edge.target()))
+        .map(|edge| (edge.id(), edge.target()))
+        .collect()
+}
+
+/// Find the memory locations which are only used inside of the loop. These are
+/// the temporaries and packet data of a single iteration.
+fn find_local_locations(
+    graph: &GclGraph,
+    in_loop: &HashSet<NodeIndex>,
+    loop_edges: &[LoopEdge],
+) -> Vec<MemoryLocation> {
+    let mut loop_locations = HashSet::new();
+    let mut other_locations = HashSet::new();
+
+    for node_idx in graph.node_indices() {
+        let locations = if in_loop.contains(&node_idx) {
+            &mut loop_locations
+        } else {
+            &mut other_locations
+        };
+
+        for cmd in &graph.node_weight(node_idx).unwrap().commands {
+            cmd.clone().visit_locations_mut(&mut |loc| {
+                locations.insert(loc.clone());
+            });
+        }
+    }
+
+    for edge in graph.edge_references() {
+        let locations = if in_loop.contains(&edge.source()) {
+            &mut loop_locations
+        } else {
+            &mut other_locations
+        };
+
+        edge.weight().clone().visit_locations_mut(&mut |loc| {
+            locations.insert(loc.clone());
+        });
+    }
+    for edge in loop_edges {
+        edge.weight.clone().visit_locations_mut(&mut |loc| {
+            loop_locations.insert(loc.clone());
+        });
+    }
+
+    let mut local_locations: Vec<_> = loop_locations
+        .difference(&other_locations)
+        .cloned()
+        .collect();
+    local_locations.sort_by_key(|loc| loc.to_string());
+    local_locations
+}
+
+/// Copy the node for the given iteration of the loop
+fn copy_node(
+    graph: &mut GclGraph,
+    node_idx: NodeIndex,
+    iteration: usize,
+    renames: &HashMap<MemoryLocation, MemoryLocation>,
+) -> NodeIndex {
+    let node = graph.node_weight(node_idx).unwrap();
+    let mut commands = node.commands.clone();
+    for cmd in &mut commands {
+        cmd.visit_locations_mut(&mut |loc| rename_location(loc, renames));
+    }
+
+    let copy = GclNode {
+        name: format!("{}__iter_{}", node.name, iteration),
+        commands,
+        span: node.span,
+    };
+    let copy_idx = graph.add_node(copy);
+    graph.copy_node_block(copy_idx, node_idx);
+    copy_idx
+}
+
+fn rename_expr(expr: &mut GclExpr, renames: &HashMap<MemoryLocation, MemoryLocation>) {
+    expr.visit_locations_mut(&mut |loc| rename_location(loc, renames));
+}
+
+fn rename_location(loc: &mut MemoryLocation, renames: &HashMap<MemoryLocation, MemoryLocation>) {
+    if let Some(new_loc) = renames.get(loc) {
+        *loc = new_loc.clone();
+    }
+}
@@ -0,0 +1,70 @@
+//! A solver backend which uses the Z3 library
+
+use crate::gcl::GclExpr;
+use crate::generate_z3_types::{generate_types, Z3TypeMap};
+use crate::ir::IrType;
+use crate::smtlib::SExpr;
+use crate::solver::{CheckResult, ModelValue, SolverBackend, SolverError, SolverModel};
+use std::convert::TryFrom;
+use std::time::Duration;
+use z3::{Model, Params, SatResult, Solver};
+
+/// A Z3 solver in the current thread's Z3 context
+pub struct Z3Solver {
+    type_map: Z3TypeMap,
+    solver: Solver,
+}
+
+impl Z3Solver {
+    /// Create a solver which knows about the user-defined types. Checks which
+    /// take longer than the timeout are unknown.
+    pub fn new(types: &[(String, IrType)], timeout: Option<Duration>) -> Self {
+        let solver = Solver::new();
+        if let Some(timeout) = timeout {
+            let mut params = Params::new();
+            let timeout_ms = u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX);
+            params.set_u32("timeout", timeout_ms);
+            solver.set_params(&params);
+        }
+
+        Z3Solver {
+            type_map: generate_types(types),
+            solver,
+        }
+    }
+}
+
+impl SolverBackend for Z3Solver {
+    fn check(&mut self, predicate: &GclExpr) -> Result<CheckResult<'_>, SolverError> {
+        let z3_predicate = predicate.as_z3_ast(&self.type_map).as_bool().unwrap();
+
+        match self.solver.check_assumptions(&[z3_predicate]) {
+            SatResult::Sat => Ok(CheckResult::Sat(Box::new(Z3Model {
+                model: self.solver.get_model().unwrap(),
+                type_map: &self.type_map,
+            }))),
+            SatResult::Unsat => Ok(CheckResult::Unsat),
+            SatResult::Unknown => {
+                log::debug!(
+                    "Z3 gave up: {}",
+                    self.solver.get_reason_unknown().unwrap_or_default()
+                );
+                Ok(CheckResult::Unknown)
+            }
+        }
+    }
+}
+
+struct Z3Model<'a> {
+    model: Model,
+    type_map: &'a Z3TypeMap,
+}
+
+impl SolverModel for Z3Model<'_> {
+    fn eval(&self, expr: &GclExpr) -> Option<ModelValue> {
+        let value = self.model.eval(&expr.as_z3_ast(self.type_map), true)?;
+
+        // Z3 writes values as SMT-LIB2 constants
+        SExpr::parse(&value.to_string())?.to_model_value()
+    }
+}
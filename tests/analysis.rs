@@ -0,0 +1,670 @@
+use p4_analyzer::analysis::Reachability;
+use p4_analyzer::gcl::BugKind;
+use p4_analyzer::solver::SolverKind;
+use p4_analyzer::{analyze, AnalysisError, AnalysisOptions, AnalysisReport, AnalyzedFile, Bug};
+use serde_json::Value;
+use std::path::Path;
+
+fn analyze_ok(source: &str) -> AnalysisReport {
+    match analyze(source, &AnalysisOptions::default()) {
+        Ok(report) => report,
+        Err(error) => panic!("Analysis failed: {:?}", error.diagnostics()),
+    }
+}
+
+/// The kind and line of each bug in the report
+fn bugs(report: &AnalysisReport) -> Vec<(BugKind, usize)> {
+    report.bugs.iter().map(|bug| (bug.kind, bug.line)).collect()
+}
+
+/// The line and column of each error which stopped the analysis
+fn error_locations(source: &str, error: &AnalysisError) -> Vec<(usize, usize)> {
+    error
+        .diagnostics()
+        .iter()
+        .map(|diagnostic| {
+            let start = diagnostic
+                .span
+                .expect("Diagnostic without a location")
+                .start;
+            let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+            (
+                source[..start].lines().count().max(1),
+                start - line_start + 1,
+            )
+        })
+        .collect()
+}
+
+fn analyze_file(name: &str, source: &str) -> AnalyzedFile {
+    AnalyzedFile {
+        name: name.to_string(),
+        source: source.to_string(),
+        result: analyze(source, &AnalysisOptions::default()),
+    }
+}
+
+#[cfg(unix)]
+/// Options which check reachability with the stub solver program. It answers
+/// `check-sat` with `answer`, and its model gives every input a default value.
+fn stub_solver_options(answer: &str) -> AnalysisOptions {
+    let script = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/stub-solver.sh");
+    AnalysisOptions {
+        solver: SolverKind::External(vec![
+            "sh".to_string(),
+            script.display().to_string(),
+            answer.to_string(),
+        ]),
+        ..AnalysisOptions::default()
+    }
+}
+
+/// The name and value of each input in the bug's counterexample
+fn counterexample_values(bug: &Bug) -> Vec<(&str, &str)> {
+    bug.counterexample
+        .values
+        .iter()
+        .map(|entry| (entry.name.as_str(), entry.value.as_str()))
+        .collect()
+}
+
+#[test]
+fn examples() {
+    use BugKind::*;
+    let expected: &[(&str, &[(BugKind, usize)])] = &[
+        ("action-arguments.p4", &[(UninitializedRead, 29)]),
+        ("assert.p4", &[(UserAssert, 8)]),
+        ("bf4-missed-case.p4", &[(UninitializedRead, 12)]),
+        ("even-odd.p4", &[(UninitializedRead, 15)]),
+        (
+            "header-validity.p4",
+            &[(InvalidHeaderAccess, 12), (InvalidHeaderAccess, 23)],
+        ),
+        ("invert-boolean.p4", &[(UninitializedRead, 10)]),
+        ("logical-assert.p4", &[(UserAssert, 8)]),
+        ("out-params.p4", &[(UnsetOutParam, 9), (UnsetOutParam, 3)]),
+        ("parser-loop.p4", &[]),
+        ("parser.p4", &[(InvalidHeaderAccess, 29)]),
+        ("poster-example.p4", &[(UninitializedRead, 11)]),
+        ("preliminary-results.p4", &[(UninitializedRead, 11)]),
+        ("presentation.p4", &[(UninitializedRead, 9)]),
+        ("results-presentation.p4", &[(UninitializedRead, 11)]),
+        ("sample-input.p4", &[]),
+        ("table.p4", &[(UninitializedRead, 19)]),
+    ];
+
+    let examples_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let mut example_count = 0;
+    for entry in examples_dir.read_dir().unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension() == Some("p4".as_ref()) {
+            example_count += 1;
+        }
+    }
+    assert_eq!(example_count, expected.len(), "Every example is checked");
+
+    for (name, expected_bugs) in expected {
+        let source = std::fs::read_to_string(examples_dir.join(name)).unwrap();
+        let report = analyze_ok(&source);
+
+        assert_eq!(bugs(&report), *expected_bugs, "Bugs in {}", name);
+        for bug in &report.bugs {
+            assert_eq!(bug.reachability, Reachability::Reachable, "{}", name);
+            assert!(
+                bug.is_confirmed(),
+                "Counterexample in {} is confirmed",
+                name
+            );
+        }
+    }
+}
+
+#[test]
+fn operator_precedence() {
+    let source = "
+        control c(in bit<8> a) {
+            apply {
+                assert(8w2 + 8w3 * 8w4 == 8w14);
+                assert(a & 8w1 == 8w0 || a & 8w1 == 8w1);
+                assert(a | 8w1 != 8w0);
+                assert(false && false || true);
+                assert(!false && true);
+            }
+        }
+    ";
+    assert_eq!(bugs(&analyze_ok(source)), []);
+}
+
+#[test]
+fn int_shifts() {
+    let source = "
+        control c(in bit<4> s) {
+            apply {
+                int i = 5;
+                int n = i << 2;
+                assert(n == 20);
+                assert(n >> 1 == 10);
+                bit<8> m = 8w1 << s;
+                assert(m != 8w0);
+            }
+        }
+    ";
+    assert_eq!(bugs(&analyze_ok(source)), [(BugKind::UserAssert, 9)]);
+}
+
+#[test]
+fn int_shift_by_variable_is_rejected() {
+    let source = "
+        control c(in bit<4> s) {
+            apply {
+                int i = 5;
+                int j = i << s;
+                assert(j != 8);
+            }
+        }
+    ";
+    match analyze(source, &AnalysisOptions::default()) {
+        Err(error @ AnalysisError::TypeCheck(_)) => {
+            let messages: Vec<_> = error
+                .diagnostics()
+                .iter()
+                .map(|diagnostic| diagnostic.message.as_str())
+                .collect();
+            assert_eq!(
+                messages,
+                ["expected a constant shift amount, found `bit<4>`"]
+            );
+        }
+        Err(error) => panic!("Unexpected error {:?}", error.diagnostics()),
+        Ok(_) => panic!("The program type checked"),
+    }
+}
+
+#[test]
+fn parser_loop_exceeds_unroll_bound() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/parser-loop.p4");
+    let source = std::fs::read_to_string(path).unwrap();
+    assert!(analyze_ok(&source).unroll_bound_exceeded);
+}
+
+#[test]
+fn action_called_several_times() {
+    let source = "
+        control c(in bit<8> a) {
+            bit<8> x;
+            action inc() {
+                x = x + 8w1;
+            }
+            apply {
+                x = a;
+                inc();
+                inc();
+                inc();
+                assert(x == a + 8w3);
+                assert(x != 8w5);
+            }
+        }
+    ";
+    let report = analyze_ok(source);
+
+    assert_eq!(bugs(&report), [(BugKind::UserAssert, 13)]);
+    assert!(!report.unroll_bound_exceeded);
+
+    let bug = &report.bugs[0];
+    assert!(bug.is_confirmed());
+    assert_eq!(counterexample_values(bug), [("a", "8w2")]);
+}
+
+#[test]
+fn action_locals_are_uninitialized_at_each_call() {
+    let source = "
+        control c(in bit<8> a) {
+            action act(bool init) {
+                bit<8> x;
+                if (init) {
+                    x = a;
+                }
+                bit<8> y = x;
+            }
+            apply {
+                act(true);
+                act(false);
+            }
+        }
+    ";
+    assert_eq!(bugs(&analyze_ok(source)), [(BugKind::UninitializedRead, 8)]);
+}
+
+#[test]
+fn action_local_headers_are_invalid_at_each_call() {
+    let source = "
+        header h_t { bit<8> f; }
+        control c(in bit<8> a) {
+            action act(bool init) {
+                h_t h;
+                if (init) {
+                    h.setValid();
+                }
+                h.f = a;
+            }
+            apply {
+                act(true);
+                act(false);
+            }
+        }
+    ";
+    assert_eq!(
+        bugs(&analyze_ok(source)),
+        [(BugKind::InvalidHeaderAccess, 9)]
+    );
+}
+
+#[test]
+fn tautological_asserts() {
+    let source = "
+        control c(in bit<16> port, in bool drop) {
+            apply {
+                assert(port == 16w1 || port != 16w1);
+                assert(!(drop && !drop));
+                assert(drop || !drop);
+                if (port == 16w0 || drop) {
+                    assert(port == 16w0 || drop);
+                }
+            }
+        }
+    ";
+    assert_eq!(bugs(&analyze_ok(source)), []);
+}
+
+#[test]
+fn type_errors_are_all_reported() {
+    let source = "
+        control c(in bit<8> a) {
+            apply {
+                bool b = a;
+                bit<8> d = true;
+                assert(missing);
+            }
+        }
+    ";
+    match analyze(source, &AnalysisOptions::default()) {
+        Err(error @ AnalysisError::TypeCheck(_)) => {
+            let lines: Vec<_> = error_locations(source, &error)
+                .into_iter()
+                .map(|(line, _)| line)
+                .collect();
+            assert_eq!(lines, [4, 5, 6]);
+        }
+        Err(error) => panic!("Unexpected error {:?}", error.diagnostics()),
+        Ok(_) => panic!("The program type checked"),
+    }
+}
+
+#[test]
+fn type_errors_point_at_the_operand() {
+    let source = "
+        control c(in bit<8> a, in bool b) {
+            apply {
+                assert(a != -1);
+                bit<8> d = a + (2 - 3);
+                assert(a == b);
+                bit<8> e = 8w1 + 300;
+            }
+        }
+    ";
+    match analyze(source, &AnalysisOptions::default()) {
+        Err(error @ AnalysisError::TypeCheck(_)) => {
+            assert_eq!(
+                error_locations(source, &error),
+                [(4, 29), (5, 33), (6, 29), (7, 34)]
+            );
+        }
+        Err(error) => panic!("Unexpected error {:?}", error.diagnostics()),
+        Ok(_) => panic!("The program type checked"),
+    }
+}
+
+#[test]
+fn folded_constants_which_fit() {
+    let source = "
+        control c(in bit<8> a) {
+            apply {
+                bit<8> b = a + (300 - 299);
+                int<4> s = -8;
+                assert(b != a + 8w1 || s == -8);
+            }
+        }
+    ";
+    assert_eq!(bugs(&analyze_ok(source)), []);
+}
+
+#[test]
+fn parse_errors_are_all_reported() {
+    let source = "
+        control c(in bit<8> a) {
+            apply {
+                bit<8> b = a +;
+                bit<8> d = ;
+            }
+        }
+    ";
+    match analyze(source, &AnalysisOptions::default()) {
+        Err(error @ AnalysisError::Parse(_)) => {
+            let lines: Vec<_> = error_locations(source, &error)
+                .into_iter()
+                .map(|(line, _)| line)
+                .collect();
+            assert_eq!(lines, [4, 5]);
+        }
+        Err(error) => panic!("Unexpected error {:?}", error.diagnostics()),
+        Ok(_) => panic!("The program parsed"),
+    }
+}
+
+#[test]
+fn duplicate_type_declarations() {
+    let source = "
+        struct s_t { bit<8> f; }
+        header h_t { bit<8> f; }
+        struct s_t { bit<8> g; }
+        header h_t { bit<8> g; }
+    ";
+    match analyze(source, &AnalysisOptions::default()) {
+        Err(error @ AnalysisError::TypeCheck(_)) => {
+            let diagnostics = error.diagnostics();
+            let messages: Vec<_> = diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.message.as_str())
+                .collect();
+            assert_eq!(
+                messages,
+                [
+                    "type `s_t` is already declared",
+                    "type `h_t` is already declared"
+                ]
+            );
+            assert_eq!(error_locations(source, &error), [(4, 9), (5, 9)]);
+
+            let note_starts: Vec<_> = diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.notes[0].span.unwrap().start)
+                .collect();
+            assert_eq!(
+                note_starts,
+                [
+                    source.find("struct").unwrap(),
+                    source.find("header").unwrap()
+                ]
+            );
+        }
+        Err(error) => panic!("Unexpected error {:?}", error.diagnostics()),
+        Ok(_) => panic!("The program type checked"),
+    }
+}
+
+#[test]
+fn json_report() {
+    let files = [
+        analyze_file(
+            "assert.p4",
+            "control c(in bit<8> a) {\n    apply {\n        assert(a != 8w7);\n    }\n}\n",
+        ),
+        analyze_file("broken.p4", "control c( {"),
+    ];
+    let json = p4_analyzer::json::files_to_json(&files);
+
+    let report = &json["files"][0];
+    assert_eq!(report["file"], "assert.p4");
+    assert_eq!(report["unroll_bound_exceeded"], false);
+    assert!(report["timings_ms"]["total"].is_number());
+
+    let bug = &report["bugs"][0];
+    assert_eq!(bug["kind"], "assertion_failure");
+    assert_eq!(bug["status"], "reachable");
+    assert_eq!(bug["variable"], Value::Null);
+    assert_eq!(bug["location"]["line"], 3);
+    assert_eq!(bug["location"]["column"], 9);
+    assert!(!bug["path"].as_array().unwrap().is_empty());
+    assert_eq!(bug["counterexample"]["values"][0]["name"], "a");
+    assert_eq!(bug["counterexample"]["values"][0]["value"], "8w7");
+    assert_eq!(bug["counterexample"]["table_actions"], Value::Array(vec![]));
+
+    let failed = &json["files"][1];
+    assert_eq!(failed["file"], "broken.p4");
+    assert_eq!(failed["stage"], "parse");
+    assert_eq!(failed["errors"].as_array().unwrap().len(), 1);
+    assert!(failed["errors"][0]["message"].is_string());
+    assert_eq!(failed["errors"][0]["location"]["line"], 1);
+}
+
+#[test]
+fn sarif_report() {
+    let files = [
+        analyze_file(
+            "assert.p4",
+            "control c(in bit<8> a) {\n    apply {\n        assert(a != 8w7);\n    }\n}\n",
+        ),
+        analyze_file("broken.p4", "control c( {"),
+    ];
+    let sarif = p4_analyzer::sarif::files_to_sarif(&files);
+
+    assert_eq!(sarif["version"], "2.1.0");
+    let run = &sarif["runs"][0];
+    assert_eq!(run["tool"]["driver"]["name"], "p4-analyzer");
+    assert_eq!(run["artifacts"][0]["location"]["uri"], "assert.p4");
+    assert_eq!(run["artifacts"][1]["location"]["uri"], "broken.p4");
+
+    let results = run["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    let result = &results[0];
+    let rule_index = result["ruleIndex"].as_u64().unwrap() as usize;
+    assert_eq!(
+        result["ruleId"],
+        run["tool"]["driver"]["rules"][rule_index]["id"]
+    );
+    assert_eq!(result["level"], "error");
+    let location = &result["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"], "assert.p4");
+    assert_eq!(location["region"]["startLine"], 3);
+    assert!(!result["codeFlows"][0]["threadFlows"][0]["locations"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+
+    let invocation = &run["invocations"][0];
+    assert_eq!(invocation["executionSuccessful"], false);
+    let notifications = invocation["toolExecutionNotifications"].as_array().unwrap();
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0]["level"], "error");
+}
+
+#[test]
+fn counterexample_fields_are_not_repeated() {
+    let source = "
+        header h_t { bit<8> f; }
+        struct s_t { h_t h; }
+        control c(inout s_t s, in bool b) {
+            apply {
+                if (s.h.isValid()) {
+                    s_t all = s;
+                    if (b) {
+                        all.h.f = 8w1;
+                    }
+                    assert(s.h.f != 8w0 || all.h.f == 8w1);
+                }
+            }
+        }
+    ";
+    let report = analyze_ok(source);
+    assert_eq!(bugs(&report), [(BugKind::UserAssert, 11)]);
+
+    let names: Vec<_> = report.bugs[0]
+        .counterexample
+        .values
+        .iter()
+        .map(|entry| entry.name.as_str())
+        .collect();
+    assert_eq!(names, ["b", "s.h.f", "s.h.isValid()"]);
+}
+
+#[test]
+fn table_actions_are_tagged_with_their_call() {
+    let source = "
+        control c(in bit<8> a) {
+            bit<8> x = 8w0;
+            action inc() {
+                x = x + 8w1;
+            }
+            table t {
+                actions = { inc; }
+            }
+            apply {
+                t.apply();
+                t.apply();
+                assert(x != 8w1);
+            }
+        }
+    ";
+    let report = analyze_ok(source);
+    assert_eq!(bugs(&report), [(BugKind::UserAssert, 13)]);
+
+    let calls: Vec<_> = report.bugs[0]
+        .counterexample
+        .table_actions
+        .iter()
+        .map(|table_action| {
+            let line = source[..table_action.call_span.start].lines().count();
+            (table_action.table.as_str(), line, table_action.iteration)
+        })
+        .collect();
+    assert_eq!(calls, [("t", 11, 0), ("t", 12, 0)]);
+}
+
+#[cfg(unix)]
+#[test]
+fn external_solver_completes_models() {
+    // `b` only decides which branch is taken, so it is not part of the
+    // assertion's predicate and the solver never declares it
+    let source = "
+        control c(in bit<8> a, in bool b) {
+            bit<8> x;
+            apply {
+                if (b) {
+                    x = 8w1;
+                } else {
+                    x = 8w2;
+                }
+                assert(a != 8w0);
+            }
+        }
+    ";
+    let report = analyze(source, &stub_solver_options("sat")).unwrap();
+    let bug = report
+        .bugs
+        .iter()
+        .find(|bug| bug.kind == BugKind::UserAssert)
+        .unwrap();
+
+    assert!(bug.is_confirmed());
+    assert_eq!(counterexample_values(bug), [("a", "8w0"), ("b", "false")]);
+
+    // Z3 completes the model with the same values
+    #[cfg(feature = "z3")]
+    {
+        let z3_report = analyze_ok(source);
+        assert_eq!(bugs(&z3_report), [(BugKind::UserAssert, 10)]);
+        assert_eq!(
+            counterexample_values(&z3_report.bugs[0]),
+            counterexample_values(bug)
+        );
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn unknown_results_are_possible_bugs() {
+    use std::time::Duration;
+
+    let source = "
+        control c(in bit<8> a) {
+            apply {
+                assert(a != 8w0);
+            }
+        }
+    ";
+    let no_answer = AnalysisOptions {
+        timeout: Some(Duration::from_millis(200)),
+        ..stub_solver_options("none")
+    };
+
+    for options in &[stub_solver_options("unknown"), no_answer] {
+        // Bugs which Z3 proves unreachable can't be ruled out either
+        let report = analyze(source, options).unwrap();
+        assert_eq!(
+            bugs(&report),
+            [(BugKind::UninitializedRead, 4), (BugKind::UserAssert, 4)]
+        );
+
+        for bug in &report.bugs {
+            assert_eq!(bug.reachability, Reachability::Unknown);
+            assert!(!bug.is_confirmed());
+            assert!(bug.counterexample.values.is_empty());
+        }
+    }
+}
+
+#[test]
+fn parallel_checks_give_the_same_report() {
+    let source = "
+        header h_t { bit<8> f; }
+        control c(in bit<8> a, inout h_t h, out bit<8> o) {
+            bit<8> x;
+            apply {
+                assert(a != 8w1);
+                if (a == 8w2) {
+                    assert(x != 8w0);
+                }
+                h.f = a;
+                assert(a != 8w3 && h.f != 8w4);
+                if (a == 8w5) {
+                    o = x;
+                }
+            }
+        }
+    ";
+    let examples_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let mut sources = vec![source.to_string()];
+    for name in &["header-validity.p4", "out-params.p4"] {
+        sources.push(std::fs::read_to_string(examples_dir.join(name)).unwrap());
+    }
+
+    let mut solvers = vec![AnalysisOptions::default()];
+    #[cfg(unix)]
+    solvers.push(stub_solver_options("sat"));
+
+    for options in solvers {
+        for source in &sources {
+            // The whole JSON report, except for how long each stage took
+            let report_with_jobs = |jobs| {
+                let file = AnalyzedFile {
+                    name: "program.p4".to_string(),
+                    source: source.clone(),
+                    result: analyze(
+                        source,
+                        &AnalysisOptions {
+                            jobs,
+                            ..options.clone()
+                        },
+                    ),
+                };
+                let mut json = p4_analyzer::json::file_to_json(&file);
+                json.as_object_mut().unwrap().remove("timings_ms");
+                json
+            };
+
+            let sequential = report_with_jobs(1);
+            assert!(sequential["bugs"].as_array().unwrap().len() > 1);
+            assert_eq!(report_with_jobs(4), sequential);
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Run the analyzer on the example files, returning its exit code
+fn exit_code(args: &[&str]) -> i32 {
+    let status = Command::new(env!("CARGO_BIN_EXE_p4-analyzer"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+
+    status.code().expect("The analyzer was stopped by a signal")
+}
+
+/// Run the analyzer on the program read from stdin, returning its exit code
+fn exit_code_for_stdin(args: &[&str], source: &str) -> i32 {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_p4-analyzer"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(source.as_bytes())
+        .unwrap();
+
+    child
+        .wait()
+        .unwrap()
+        .code()
+        .expect("The analyzer was stopped by a signal")
+}
+
+#[test]
+fn no_bugs_exits_with_0() {
+    assert_eq!(exit_code(&["examples/sample-input.p4"]), 0);
+    assert_eq!(
+        exit_code(&["--format", "json", "examples/sample-input.p4"]),
+        0
+    );
+}
+
+#[test]
+fn bugs_exit_with_1() {
+    assert_eq!(exit_code(&["examples/assert.p4"]), 1);
+    assert_eq!(exit_code(&["--format", "sarif", "examples/assert.p4"]), 1);
+    assert_eq!(
+        exit_code(&["examples/sample-input.p4", "examples/assert.p4"]),
+        1
+    );
+}
+
+#[test]
+fn errors_exit_with_2() {
+    assert_eq!(exit_code_for_stdin(&[], "control c( {"), 2);
+    assert_eq!(
+        exit_code_for_stdin(&["-"], "control c() { apply { bool b = 8w1; } }"),
+        2
+    );
+    assert_eq!(exit_code(&["examples/does-not-exist.p4"]), 2);
+    assert_eq!(
+        exit_code(&["examples/assert.p4", "examples/does-not-exist.p4"]),
+        2
+    );
+    assert_eq!(exit_code(&["--not-an-option"]), 2);
+}
@@ -0,0 +1,104 @@
+use p4_analyzer::ast::{BinOp, Declaration, Expr, ExprData, UnOp};
+use p4_analyzer::{parse, run_type_checking};
+
+/// Parse a constant's value and write it out with every operation in
+/// parentheses, so the parse tree can be compared
+fn parse_expr(expr: &str) -> String {
+    let program = parse(&format!("const bool c = {};", expr)).unwrap();
+    match &program.declarations[..] {
+        [Declaration::Constant(constant)] => parenthesize(&constant.value),
+        _ => panic!("Expected a single constant"),
+    }
+}
+
+fn parenthesize(expr: &Expr) -> String {
+    match &expr.data {
+        ExprData::Bool(value) => value.to_string(),
+        ExprData::Int(literal) => literal.value.to_string(),
+        ExprData::Var(name) => name.clone(),
+        ExprData::And(left, right) => {
+            format!("({} && {})", parenthesize(left), parenthesize(right))
+        }
+        ExprData::Or(left, right) => {
+            format!("({} || {})", parenthesize(left), parenthesize(right))
+        }
+        ExprData::Negation(inner) => format!("(!{})", parenthesize(inner)),
+        ExprData::UnOp(UnOp::BitNot, inner) => format!("(~{})", parenthesize(inner)),
+        ExprData::UnOp(UnOp::Minus, inner) => format!("(-{})", parenthesize(inner)),
+        ExprData::BinOp(op, left, right) => format!(
+            "({} {} {})",
+            parenthesize(left),
+            bin_op_symbol(*op),
+            parenthesize(right)
+        ),
+        _ => panic!("Unexpected expression {:?}", expr),
+    }
+}
+
+fn bin_op_symbol(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "+",
+        BinOp::Sub => "-",
+        BinOp::Mul => "*",
+        BinOp::Div => "/",
+        BinOp::Mod => "%",
+        BinOp::SaturatingAdd => "|+|",
+        BinOp::SaturatingSub => "|-|",
+        BinOp::BitAnd => "&",
+        BinOp::BitOr => "|",
+        BinOp::BitXor => "^",
+        BinOp::ShiftLeft => "<<",
+        BinOp::ShiftRight => ">>",
+        BinOp::Equals => "==",
+        BinOp::NotEquals => "!=",
+        BinOp::LessThan => "<",
+        BinOp::LessThanEquals => "<=",
+        BinOp::GreaterThan => ">",
+        BinOp::GreaterThanEquals => ">=",
+    }
+}
+
+#[test]
+fn arithmetic_precedence() {
+    assert_eq!(parse_expr("a + b * c"), "(a + (b * c))");
+    assert_eq!(parse_expr("a - b - c"), "((a - b) - c)");
+    assert_eq!(parse_expr("a << b + c"), "(a << (b + c))");
+    assert_eq!(parse_expr("-a * ~b"), "((-a) * (~b))");
+}
+
+#[test]
+fn bitwise_operators_bind_tighter_than_comparisons() {
+    assert_eq!(parse_expr("a & 1 == 0"), "((a & 1) == 0)");
+    assert_eq!(parse_expr("a == b & c"), "(a == (b & c))");
+    assert_eq!(parse_expr("a | b != c ^ d"), "((a | b) != (c ^ d))");
+    assert_eq!(parse_expr("a & b < c | d"), "((a & b) < (c | d))");
+}
+
+#[test]
+fn bitwise_precedence() {
+    assert_eq!(parse_expr("a | b ^ c & d"), "(a | (b ^ (c & d)))");
+    assert_eq!(parse_expr("a & b << c"), "(a & (b << c))");
+}
+
+#[test]
+fn comparison_and_logical_precedence() {
+    assert_eq!(parse_expr("a < b == c > d"), "((a < b) == (c > d))");
+    assert_eq!(
+        parse_expr("a == b || c && d != e"),
+        "((a == b) || (c && (d != e)))"
+    );
+    assert_eq!(parse_expr("!a && b"), "((!a) && b)");
+}
+
+#[test]
+fn masked_comparison_type_checks() {
+    let source = "
+        control c(in bit<8> a) {
+            apply {
+                bool b = a & 8w1 == 8w0;
+            }
+        }
+    ";
+    let program = parse(source).unwrap();
+    assert!(run_type_checking(&program).is_ok());
+}
@@ -0,0 +1,186 @@
+#!/bin/sh
+# A stand-in for an SMT-LIB2 solver program, for testing the external solver
+# backend. Every command is expected on its own line. It is run as
+# `sh stub-solver.sh [answer]`.
+#
+# `check-sat` is answered with the answer, which defaults to `sat`. If it is
+# `none`, there is no answer, like a solver which is stuck on the check.
+#
+# The model gives every declared constant a default value (false, 0 or a zero
+# bit-vector), and `get-value` evaluates terms made of literals, declared
+# constants, `let`, `not`, `and`, `or`, `=`, `distinct` and `ite`. Other terms, and
+# constants which were not declared, are errors.
+
+answer=${1:-sat}
+
+# Reads the `declare-fun` commands, followed by the `get-value` command
+evaluate='
+/^\(declare-fun / {
+    # (declare-fun name () sort)
+    sort = $0
+    sub(/^\(declare-fun [^ ]+ \(\) /, "", sort)
+    sub(/\)$/, "", sort)
+    sorts[$2] = sort
+    next
+}
+
+{
+    term = $0
+    sub(/^\(get-value \(/, "", term)
+    sub(/\)\)$/, "", term)
+
+    tokenize(term)
+    position = 1
+    value = evaluate()
+    if (error != "") {
+        print "(error \"" error "\")"
+    } else {
+        print "((" term " " value "))"
+    }
+}
+
+# Split the term into parentheses and atoms, which are stored in `tokens`
+function tokenize(text,    count) {
+    split("", tokens)
+    while (length(text) > 0) {
+        if (match(text, /^[ \t]+/)) {
+            text = substr(text, RLENGTH + 1)
+            continue
+        }
+        if (match(text, /^[()]/) || match(text, /^\|[^|]*\|/) || match(text, /^[^ \t()]+/)) {
+            tokens[++count] = substr(text, 1, RLENGTH)
+            text = substr(text, RLENGTH + 1)
+        }
+    }
+}
+
+function next_token() {
+    return tokens[position++]
+}
+
+# Evaluate the term starting at the current token. Errors are stored in
+# `error`, and the returned value is then meaningless.
+function evaluate(    token, operator, name, value, result, left, right) {
+    if (error != "") {
+        return ""
+    }
+
+    token = next_token()
+    if (token != "(") {
+        return constant_value(token)
+    }
+
+    operator = next_token()
+    if (operator == "_") {
+        # A bit-vector literal like (_ bv0 8)
+        value = next_token()
+        value = "(_ " value " " next_token() ")"
+        next_token()
+        return value
+    }
+    if (operator == "let") {
+        # Bindings are not undone, since the backend only binds constants
+        # around the whole term
+        next_token()
+        while (tokens[position] == "(" && error == "") {
+            next_token()
+            name = next_token()
+            bound[name] = evaluate()
+            next_token()
+        }
+        next_token()
+        result = evaluate()
+        next_token()
+        return result
+    }
+    if (operator == "not") {
+        result = evaluate() == "true" ? "false" : "true"
+        next_token()
+        return result
+    }
+    if (operator == "and" || operator == "or") {
+        result = operator == "and" ? "true" : "false"
+        while (tokens[position] != ")" && error == "") {
+            value = evaluate()
+            if (operator == "and" && value == "false") {
+                result = "false"
+            }
+            if (operator == "or" && value == "true") {
+                result = "true"
+            }
+        }
+        next_token()
+        return result
+    }
+    if (operator == "=" || operator == "distinct") {
+        left = evaluate()
+        right = evaluate()
+        next_token()
+        return (left == right) == (operator == "=") ? "true" : "false"
+    }
+    if (operator == "ite") {
+        result = evaluate()
+        left = evaluate()
+        right = evaluate()
+        next_token()
+        return result == "true" ? left : right
+    }
+
+    error = "unsupported operator " operator
+    return ""
+}
+
+function constant_value(token,    sort) {
+    if (token == "true" || token == "false" || token ~ /^[0-9]+$/) {
+        return token
+    }
+    if (token in bound) {
+        return bound[token]
+    }
+    if (!(token in sorts)) {
+        error = "unknown constant " token
+        return ""
+    }
+
+    sort = sorts[token]
+    if (sort == "Bool") {
+        return "false"
+    }
+    if (sort == "Int") {
+        return "0"
+    }
+    if (match(sort, /^\(_ BitVec [0-9]+\)$/)) {
+        return "(_ bv0 " substr(sort, 11, length(sort) - 11) ")"
+    }
+
+    error = "unsupported sort " sort
+    return ""
+}
+'
+
+# The commands are read by the shell, since awk may wait for more input
+# before it handles a line
+declarations=""
+while IFS= read -r command; do
+    case $command in
+    "(declare-fun "*)
+        declarations="$declarations$command
+"
+        echo success
+        ;;
+    "(set-option "* | "(declare-datatypes "* | "(push "* | "(pop "* | "(assert "*)
+        echo success
+        ;;
+    "(check-sat)")
+        if [ "$answer" != none ]; then
+            echo "$answer"
+        fi
+        ;;
+    "(get-value ("*)
+        printf "%s%s\n" "$declarations" "$command" | awk "$evaluate"
+        ;;
+    *)
+        echo "(error \"unsupported command\")"
+        ;;
+    esac
+done